@@ -4,8 +4,12 @@ use reqwest_middleware::Error as MiddlewareReqwestError;
 use tracing::{event, instrument, Level};
 use warp::{
     filters::{body::BodyDeserializeError, cors::CorsForbidden},
-    http::StatusCode,
+    http::{
+        header::{RETRY_AFTER, WWW_AUTHENTICATE},
+        HeaderValue, StatusCode,
+    },
     reject::Reject,
+    reply::WithStatus,
     Rejection, Reply,
 };
 
@@ -13,16 +17,50 @@ use warp::{
 pub enum Error {
     ParseError(std::num::ParseIntError),
     MissingParameters,
+    InvalidCursor,
+    InvalidSortKey(String),
+    InvalidSortOrder(String),
+    TooManyTags(usize),
+    InvalidTagMode(String),
+    InvalidFilter(String, String),
+    UnsupportedSearchParameter(String),
+    UnsupportedCursorParameter(String),
     WrongPassword,
+    AccountAlreadyExists,
+    AccountNotFound,
+    /// 管理者が指定したアカウントが存在しない
+    ///
+    /// ログインとは異なり、存在しないことをそのまま 404 で返す
+    TargetAccountNotFound(i32),
+    TooManyLoginAttempts(u64),
+    MissingToken,
+    TokenExpired,
     CannotDecryptToken,
+    TokenRevoked,
+    InvalidApiKey,
+    InsufficientScope,
+    TokenRequired,
+    ApiKeyNotFound,
+    InvalidResetToken,
+    InvalidVerificationToken,
+    InvalidEmail(String),
+    EmailNotVerified,
     Unauthorized,
+    Forbidden,
+    QuestionNotFound,
+    AnswerNotFound,
     ArgonLibraryError(ArgonError),
+    InvalidPasswordHashing(ArgonError),
     DatabaseQueryError(sqlx::Error),
     MigrationError(sqlx::migrate::MigrateError),
     ReqwestAPIError(ReqwestError),
     MiddlewareReqwestAPIError(MiddlewareReqwestError),
     ClientError(APILayerError),
     ServerError(APILayerError),
+    MailError(String),
+    InvalidWordlist(String),
+    CircuitOpen(u64),
+    ProfaneContent(Vec<ProfanityMatch>),
 }
 
 #[derive(Debug, Clone)]
@@ -31,6 +69,23 @@ pub struct APILayerError {
     pub message: String,
 }
 
+/// 投稿から検出した不適切な単語
+///
+/// `start` と `end` は文章の先頭からの文字数で、`end` の位置は含まない
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfanityMatch {
+    /// 投稿に書かれていたままの単語
+    pub word: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl std::fmt::Display for ProfanityMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\"{}\" at {}..{}", self.word, self.start, self.end)
+    }
+}
+
 impl std::fmt::Display for APILayerError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Status: {} Message: {}", self.status, self.message)
@@ -44,15 +99,84 @@ impl std::fmt::Display for Error {
                 write!(f, "Cannot parse parameter: {}", err)
             }
             Error::MissingParameters => write!(f, "Missing parameter"),
+            Error::InvalidCursor => write!(f, "Invalid cursor"),
+            Error::InvalidSortKey(key) => write!(
+                f,
+                "Invalid sort key: {} (expected created_on, updated_on, answers or votes)",
+                key
+            ),
+            Error::InvalidSortOrder(order) => {
+                write!(f, "Invalid sort order: {} (expected asc or desc)", order)
+            }
+            Error::TooManyTags(max) => {
+                write!(f, "Too many tags: at most {} tags are allowed", max)
+            }
+            Error::InvalidTagMode(mode) => {
+                write!(f, "Invalid tag mode: {} (expected any or all)", mode)
+            }
+            Error::InvalidFilter(name, value) => write!(
+                f,
+                "Invalid value for {}: {} (expected true or false)",
+                name, value
+            ),
+            Error::UnsupportedSearchParameter(name) => {
+                write!(f, "Parameter {} cannot be combined with q", name)
+            }
+            Error::UnsupportedCursorParameter(name) => {
+                write!(f, "Parameter {} cannot be combined with cursor", name)
+            }
             Error::WrongPassword => write!(f, "Wrong Password"),
-            Error::CannotDecryptToken => write!(f, "Cannot decrypt error"),
+            Error::AccountAlreadyExists => {
+                write!(f, "Account already exists")
+            }
+            Error::AccountNotFound => write!(f, "Account not found"),
+            Error::TargetAccountNotFound(id) => {
+                write!(f, "Account {} not found", id)
+            }
+            Error::TooManyLoginAttempts(seconds) => write!(
+                f,
+                "Too many failed login attempts, retry after {} seconds",
+                seconds
+            ),
+            Error::MissingToken => write!(f, "Missing authorization token"),
+            Error::TokenExpired => write!(f, "Token has expired"),
+            Error::CannotDecryptToken => write!(f, "Cannot decrypt token"),
+            Error::TokenRevoked => write!(f, "Token has been revoked"),
+            Error::InvalidApiKey => write!(f, "Invalid API key"),
+            Error::InsufficientScope => {
+                write!(f, "API key scope does not allow this action")
+            }
+            Error::TokenRequired => {
+                write!(f, "This action cannot be performed with an API key")
+            }
+            Error::ApiKeyNotFound => write!(f, "API key not found"),
+            Error::InvalidResetToken => {
+                write!(f, "Invalid or expired password reset token")
+            }
+            Error::InvalidVerificationToken => {
+                write!(f, "Invalid or expired email verification token")
+            }
+            Error::InvalidEmail(email) => {
+                write!(f, "Invalid email address: {}", email)
+            }
+            Error::EmailNotVerified => {
+                write!(f, "Email address has not been verified")
+            }
             Error::Unauthorized => write!(
                 f,
                 "No permission to change the underlying resource"
             ),
+            Error::Forbidden => {
+                write!(f, "Insufficient role to perform this action")
+            }
+            Error::QuestionNotFound => write!(f, "Question not found"),
+            Error::AnswerNotFound => write!(f, "Answer not found"),
             Error::ArgonLibraryError(_) => {
                 write!(f, "Cannot verify password")
             }
+            Error::InvalidPasswordHashing(err) => {
+                write!(f, "Invalid argon2 parameters: {}", err)
+            }
             Error::DatabaseQueryError(_) => {
                 write!(f, "Cannot update, invalid data.")
             }
@@ -69,6 +193,51 @@ impl std::fmt::Display for Error {
             Error::ServerError(err) => {
                 write!(f, "External Server error: {}", err)
             }
+            Error::MailError(err) => write!(f, "Cannot send mail: {}", err),
+            Error::InvalidWordlist(err) => {
+                write!(f, "Cannot load profanity wordlist: {}", err)
+            }
+            Error::CircuitOpen(seconds) => write!(
+                f,
+                "Profanity check is temporarily unavailable, retry after {} seconds",
+                seconds
+            ),
+            Error::ProfaneContent(matches) => write!(
+                f,
+                "Content contains too many disallowed words: {}",
+                matches
+                    .iter()
+                    .map(ProfanityMatch::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+impl Error {
+    /// 認証に失敗した場合に `WWW-Authenticate` ヘッダーで返すチャレンジ
+    ///
+    /// RFC 6750 に従い、トークンが送られていない場合はエラーコードを含めない
+    pub fn www_authenticate(&self) -> Option<String> {
+        match self {
+            Error::MissingToken => Some("Bearer".to_string()),
+            Error::TokenExpired
+            | Error::CannotDecryptToken
+            | Error::TokenRevoked => Some(format!(
+                r#"Bearer error="invalid_token", error_description="{}""#,
+                self
+            )),
+            _ => None,
+        }
+    }
+
+    /// 再試行できるまでの秒数を `Retry-After` ヘッダーで返す
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            Error::TooManyLoginAttempts(seconds)
+            | Error::CircuitOpen(seconds) => Some(*seconds),
+            _ => None,
         }
     }
 }
@@ -80,17 +249,36 @@ const DUPLICATE_KEY: u32 = 23505;
 
 #[instrument]
 pub async fn return_error(r: Rejection) -> Result<impl Reply, Rejection> {
+    let challenge = r.find::<Error>().and_then(Error::www_authenticate);
+    let retry_after = r.find::<Error>().and_then(Error::retry_after);
+    let mut res = error_reply(&r)?.into_response();
+
+    if let Some(value) = challenge
+        .and_then(|challenge| HeaderValue::from_str(&challenge).ok())
+    {
+        res.headers_mut().insert(WWW_AUTHENTICATE, value);
+    }
+
+    if let Some(seconds) = retry_after {
+        res.headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(seconds));
+    }
+
+    Ok(res)
+}
+
+fn error_reply(r: &Rejection) -> Result<WithStatus<String>, Rejection> {
     if let Some(crate::Error::DatabaseQueryError(e)) = r.find() {
         event!(Level::ERROR, "Database query error");
 
         match e {
             sqlx::Error::Database(err) => {
-                if err.code().unwrap().parse::<u32>().unwrap()
-                    == DUPLICATE_KEY
+                if err.code().and_then(|code| code.parse::<u32>().ok())
+                    == Some(DUPLICATE_KEY)
                 {
                     Ok(warp::reply::with_status(
-                        "Account already exists".to_string(),
-                        StatusCode::UNPROCESSABLE_ENTITY,
+                        "Resource already exists".to_string(),
+                        StatusCode::CONFLICT,
                     ))
                 } else {
                     Ok(warp::reply::with_status(
@@ -118,18 +306,107 @@ pub async fn return_error(r: Rejection) -> Result<impl Reply, Rejection> {
             "Internal Server Error".to_string(),
             StatusCode::INTERNAL_SERVER_ERROR,
         ))
-    } else if let Some(crate::Error::WrongPassword) = r.find() {
-        event!(Level::ERROR, "Entered wrong password");
+    } else if let Some(crate::Error::AccountAlreadyExists) = r.find() {
+        event!(Level::WARN, "Account already exists");
+        Ok(warp::reply::with_status(
+            "Account already exists".to_string(),
+            StatusCode::CONFLICT,
+        ))
+    } else if let Some(
+        error @ (crate::Error::WrongPassword
+        | crate::Error::AccountNotFound),
+    ) = r.find()
+    {
+        // 登録されているメールアドレスかどうかを推測されないよう、同じレスポンスを返す
+        event!(Level::ERROR, "{}", error);
         Ok(warp::reply::with_status(
             "Wrong Email/Password Combination".to_string(),
             StatusCode::UNAUTHORIZED,
         ))
+    } else if let Some(error @ crate::Error::TooManyLoginAttempts(_)) =
+        r.find()
+    {
+        event!(Level::WARN, "{}", error);
+        Ok(warp::reply::with_status(
+            error.to_string(),
+            StatusCode::TOO_MANY_REQUESTS,
+        ))
+    } else if let Some(
+        error @ (crate::Error::MissingToken
+        | crate::Error::TokenExpired
+        | crate::Error::CannotDecryptToken
+        | crate::Error::TokenRevoked
+        | crate::Error::InvalidApiKey),
+    ) = r.find()
+    {
+        event!(Level::WARN, "{}", error);
+        Ok(warp::reply::with_status(
+            error.to_string(),
+            StatusCode::UNAUTHORIZED,
+        ))
     } else if let Some(crate::Error::Unauthorized) = r.find() {
         event!(Level::ERROR, "Not matching account id");
         Ok(warp::reply::with_status(
             "No permission to change underlying resource".to_string(),
             StatusCode::UNAUTHORIZED,
         ))
+    } else if let Some(
+        error @ (crate::Error::Forbidden
+        | crate::Error::EmailNotVerified
+        | crate::Error::InsufficientScope
+        | crate::Error::TokenRequired),
+    ) = r.find()
+    {
+        event!(Level::WARN, "{}", error);
+        Ok(warp::reply::with_status(
+            error.to_string(),
+            StatusCode::FORBIDDEN,
+        ))
+    } else if let Some(
+        error @ (crate::Error::InvalidSortKey(_)
+        | crate::Error::InvalidSortOrder(_)
+        | crate::Error::InvalidFilter(_, _)
+        | crate::Error::UnsupportedSearchParameter(_)
+        | crate::Error::UnsupportedCursorParameter(_)
+        | crate::Error::InvalidResetToken
+        | crate::Error::InvalidVerificationToken
+        | crate::Error::InvalidEmail(_)
+        | crate::Error::TooManyTags(_)
+        | crate::Error::InvalidTagMode(_)
+        | crate::Error::InvalidCursor),
+    ) = r.find()
+    {
+        event!(Level::WARN, "{}", error);
+        Ok(warp::reply::with_status(
+            error.to_string(),
+            StatusCode::BAD_REQUEST,
+        ))
+    } else if let Some(crate::Error::QuestionNotFound) = r.find() {
+        event!(Level::WARN, "Requested question was not found");
+        Ok(warp::reply::with_status(
+            "Question not found".to_string(),
+            StatusCode::NOT_FOUND,
+        ))
+    } else if let Some(crate::Error::AnswerNotFound) = r.find() {
+        event!(Level::WARN, "Requested answer was not found");
+        Ok(warp::reply::with_status(
+            "Answer not found".to_string(),
+            StatusCode::NOT_FOUND,
+        ))
+    } else if let Some(error @ crate::Error::TargetAccountNotFound(_)) =
+        r.find()
+    {
+        event!(Level::WARN, "{}", error);
+        Ok(warp::reply::with_status(
+            error.to_string(),
+            StatusCode::NOT_FOUND,
+        ))
+    } else if let Some(crate::Error::ApiKeyNotFound) = r.find() {
+        event!(Level::WARN, "Requested API key was not found");
+        Ok(warp::reply::with_status(
+            "API key not found".to_string(),
+            StatusCode::NOT_FOUND,
+        ))
     } else if let Some(crate::Error::ClientError(e)) = r.find() {
         event!(Level::ERROR, "{}", e);
         Ok(warp::reply::with_status(
@@ -142,6 +419,25 @@ pub async fn return_error(r: Rejection) -> Result<impl Reply, Rejection> {
             "Internal Server Error".to_string(),
             StatusCode::INTERNAL_SERVER_ERROR,
         ))
+    } else if let Some(error @ crate::Error::CircuitOpen(_)) = r.find() {
+        event!(Level::WARN, "{}", error);
+        Ok(warp::reply::with_status(
+            error.to_string(),
+            StatusCode::SERVICE_UNAVAILABLE,
+        ))
+    } else if let Some(error @ crate::Error::ProfaneContent(_)) = r.find()
+    {
+        event!(Level::WARN, "{}", error);
+        Ok(warp::reply::with_status(
+            error.to_string(),
+            StatusCode::UNPROCESSABLE_ENTITY,
+        ))
+    } else if let Some(crate::Error::MailError(e)) = r.find() {
+        event!(Level::ERROR, "{}", e);
+        Ok(warp::reply::with_status(
+            "Internal Server Error".to_string(),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ))
     } else if let Some(error) = r.find::<CorsForbidden>() {
         event!(Level::ERROR, "CORS forbidden error: {}", error);
         Ok(warp::reply::with_status(
@@ -2,7 +2,10 @@ use std::{io, io::Write, process::Command};
 
 use futures_util::future::FutureExt;
 
-use rust_web_dev::{config, handle_errors, oneshot, setup_store};
+use rust_web_dev::{
+    config, handle_errors, oneshot, setup_http_client, setup_mailer,
+    setup_profanity_filter, setup_store,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -27,7 +30,10 @@ struct QuestionAnswer {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
-struct Token(String);
+struct Token {
+    access_token: String,
+    refresh_token: String,
+}
 
 #[tokio::main]
 async fn main() -> Result<(), handler_errors::Error> {
@@ -66,7 +72,10 @@ async fn main() -> Result<(), handler_errors::Error> {
     let store = setup_store(&config).await?;
 
     // start the server and listen for a sender signal to shut it down
-    let handler = oneshot(store).await;
+    let mailer = setup_mailer(&config)?;
+    let http_client = setup_http_client(&config)?;
+    let profanity = setup_profanity_filter(&config, http_client)?;
+    let handler = oneshot(store, mailer, profanity).await;
 
     // create a test user to use throughout the tests
     let u = User {
@@ -154,7 +163,7 @@ async fn post_question(token: Token) {
     let client = reqwest::Client::new();
     let res = client
         .post("http://localhost:3030/questions")
-        .header("Authorization", token.0)
+        .header("Authorization", format!("Bearer {}", token.access_token))
         .json(&q)
         .send()
         .await
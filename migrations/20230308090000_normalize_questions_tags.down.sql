@@ -0,0 +1 @@
+-- 正規化する前のタグは残していないため元に戻さない
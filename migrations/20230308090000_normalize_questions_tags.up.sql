@@ -0,0 +1,17 @@
+-- 既存のタグを `normalize_tags` と同じく正規化する
+-- (小文字にして空白をまとめ、空のタグと重複は最初に現れた順を保って取り除く)
+-- 更新時に 400 にならないよう、`MAX_TAGS` (5個) を超えるタグは切り捨てる
+UPDATE questions
+SET tags = ARRAY(
+    SELECT normalized.tag
+    FROM (
+        SELECT lower(trim(regexp_replace(tag, '\s+', ' ', 'g'))) AS tag,
+            min(ord) AS ord
+        FROM unnest(tags) WITH ORDINALITY AS original(tag, ord)
+        WHERE tag ~ '\S'
+        GROUP BY 1
+    ) AS normalized
+    ORDER BY normalized.ord
+    LIMIT 5
+)
+WHERE tags IS NOT NULL;
@@ -0,0 +1 @@
+DROP INDEX IF EXISTS questions_created_on_id_idx;
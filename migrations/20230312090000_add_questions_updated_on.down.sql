@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS answers_question_id_idx;
+DROP INDEX IF EXISTS questions_updated_on_id_idx;
+ALTER TABLE questions
+DROP COLUMN updated_on;
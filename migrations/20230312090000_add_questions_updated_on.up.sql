@@ -0,0 +1,5 @@
+ALTER TABLE questions
+ADD COLUMN updated_on TIMESTAMP NOT NULL DEFAULT NOW();
+UPDATE questions SET updated_on = created_on;
+CREATE INDEX IF NOT EXISTS questions_updated_on_id_idx ON questions (updated_on, id);
+CREATE INDEX IF NOT EXISTS answers_question_id_idx ON answers (question_id);
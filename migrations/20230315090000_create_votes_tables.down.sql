@@ -0,0 +1,7 @@
+DROP INDEX IF EXISTS questions_score_id_idx;
+ALTER TABLE answers
+DROP COLUMN score;
+ALTER TABLE questions
+DROP COLUMN score;
+DROP TABLE IF EXISTS answer_votes;
+DROP TABLE IF EXISTS question_votes;
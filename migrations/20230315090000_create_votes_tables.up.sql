@@ -0,0 +1,22 @@
+CREATE TABLE IF NOT EXISTS question_votes (
+    question_id integer NOT NULL REFERENCES questions ON DELETE CASCADE,
+    account_id integer NOT NULL,
+    value SMALLINT NOT NULL CHECK (value IN (-1, 1)),
+    created_on TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (question_id, account_id)
+);
+
+CREATE TABLE IF NOT EXISTS answer_votes (
+    answer_id integer NOT NULL REFERENCES answers ON DELETE CASCADE,
+    account_id integer NOT NULL,
+    value SMALLINT NOT NULL CHECK (value IN (-1, 1)),
+    created_on TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (answer_id, account_id)
+);
+
+-- 一覧の取得や並び替えのたびに集計しなくて済むように、スコアを非正規化して保持する
+ALTER TABLE questions
+ADD COLUMN score BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE answers
+ADD COLUMN score BIGINT NOT NULL DEFAULT 0;
+CREATE INDEX IF NOT EXISTS questions_score_id_idx ON questions (score, id);
@@ -0,0 +1,4 @@
+-- 回答が削除された場合は未解決の状態に戻す
+ALTER TABLE questions
+ADD COLUMN accepted_answer_id integer REFERENCES answers ON DELETE SET NULL;
+CREATE INDEX IF NOT EXISTS questions_accepted_answer_id_idx ON questions (accepted_answer_id);
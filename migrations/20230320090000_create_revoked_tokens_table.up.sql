@@ -0,0 +1,8 @@
+-- ログアウトやトークンの更新で失効したトークンID
+-- 有効期限を過ぎたトークンは検証で弾かれるため、expires_on を過ぎた行は削除してよい
+CREATE TABLE IF NOT EXISTS revoked_tokens (
+    jti TEXT PRIMARY KEY,
+    expires_on TIMESTAMPTZ NOT NULL,
+    revoked_on TIMESTAMP NOT NULL DEFAULT NOW()
+);
+CREATE INDEX IF NOT EXISTS revoked_tokens_expires_on_idx ON revoked_tokens (expires_on);
@@ -0,0 +1,12 @@
+ALTER TABLE accounts
+ADD COLUMN role TEXT NOT NULL DEFAULT 'user'
+    CHECK (role IN ('user', 'moderator', 'admin'));
+
+-- モデレーターや管理者が他人のリソースに対して行った操作の記録
+CREATE TABLE IF NOT EXISTS audit_log (
+    id serial PRIMARY KEY,
+    account_id integer NOT NULL,
+    action TEXT NOT NULL,
+    target_id integer NOT NULL,
+    created_on TIMESTAMP NOT NULL DEFAULT NOW()
+);
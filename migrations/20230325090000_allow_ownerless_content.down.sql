@@ -0,0 +1,7 @@
+-- 所有者のいない行は存在しないアカウントIDに割り当てる
+UPDATE answers SET account_id = 0 WHERE account_id IS NULL;
+UPDATE questions SET account_id = 0 WHERE account_id IS NULL;
+ALTER TABLE answers
+ALTER COLUMN account_id SET NOT NULL;
+ALTER TABLE questions
+ALTER COLUMN account_id SET NOT NULL;
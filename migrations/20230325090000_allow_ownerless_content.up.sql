@@ -0,0 +1,5 @@
+-- 退会したアカウントの質問と回答は残し、所有者のいない状態にする
+ALTER TABLE questions
+ALTER COLUMN account_id DROP NOT NULL;
+ALTER TABLE answers
+ALTER COLUMN account_id DROP NOT NULL;
@@ -0,0 +1,2 @@
+-- 主キーは email のため、id を参照する外部キーを作成できるよう一意制約を追加する
+ALTER TABLE accounts ADD CONSTRAINT accounts_id_key UNIQUE (id);
@@ -0,0 +1,9 @@
+-- パスワードリセット用のトークン
+-- トークン自体は保存せず SHA-256 のハッシュのみを保持する。使用したトークンは行ごと削除する
+CREATE TABLE IF NOT EXISTS password_resets (
+    token_hash TEXT PRIMARY KEY,
+    account_id INTEGER NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    expires_on TIMESTAMPTZ NOT NULL,
+    created_on TIMESTAMP NOT NULL DEFAULT NOW()
+);
+CREATE INDEX IF NOT EXISTS password_resets_account_id_idx ON password_resets (account_id);
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS email_verifications;
+ALTER TABLE accounts DROP COLUMN IF EXISTS email_verified;
@@ -0,0 +1,13 @@
+-- 登録済みのアカウントは確認済みとして扱い、新しく登録したアカウントのみ確認を求める
+ALTER TABLE accounts ADD COLUMN IF NOT EXISTS email_verified BOOLEAN NOT NULL DEFAULT FALSE;
+UPDATE accounts SET email_verified = TRUE;
+
+-- メールアドレスの確認用トークン
+-- パスワードリセットと同じく SHA-256 のハッシュのみを保持し、使用したトークンは行ごと削除する
+CREATE TABLE IF NOT EXISTS email_verifications (
+    token_hash TEXT PRIMARY KEY,
+    account_id INTEGER NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    expires_on TIMESTAMPTZ NOT NULL,
+    created_on TIMESTAMP NOT NULL DEFAULT NOW()
+);
+CREATE INDEX IF NOT EXISTS email_verifications_account_id_idx ON email_verifications (account_id);
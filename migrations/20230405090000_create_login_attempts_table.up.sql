@@ -0,0 +1,11 @@
+-- ログインの試行の記録
+-- 総当たり攻撃への対策として失敗した回数を数えるほか、監査にも使用する
+CREATE TABLE IF NOT EXISTS login_attempts (
+    id SERIAL PRIMARY KEY,
+    email TEXT NOT NULL,
+    ip TEXT,
+    succeeded BOOLEAN NOT NULL,
+    created_on TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX IF NOT EXISTS login_attempts_email_idx ON login_attempts (email, created_on);
+CREATE INDEX IF NOT EXISTS login_attempts_ip_idx ON login_attempts (ip, created_on);
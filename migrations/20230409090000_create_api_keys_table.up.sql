@@ -0,0 +1,13 @@
+-- ボットなどのクライアント向けのAPIキー
+-- キー自体は保存せず SHA-256 のハッシュのみを保持する。失効させたキーは行ごと削除する
+CREATE TABLE IF NOT EXISTS api_keys (
+    id SERIAL PRIMARY KEY,
+    account_id INTEGER NOT NULL REFERENCES accounts (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    scope TEXT NOT NULL CHECK (scope IN ('read', 'write')),
+    prefix TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    created_on TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_used_on TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS api_keys_account_id_idx ON api_keys (account_id);
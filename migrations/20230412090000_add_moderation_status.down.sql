@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS answers_moderation_status_idx;
+DROP INDEX IF EXISTS questions_moderation_status_idx;
+ALTER TABLE answers DROP COLUMN IF EXISTS moderation_status;
+ALTER TABLE questions DROP COLUMN IF EXISTS moderation_status;
@@ -0,0 +1,12 @@
+ALTER TABLE questions
+    ADD COLUMN moderation_status TEXT NOT NULL DEFAULT 'approved'
+        CHECK (moderation_status IN ('approved', 'flagged', 'pending_moderation'));
+ALTER TABLE answers
+    ADD COLUMN moderation_status TEXT NOT NULL DEFAULT 'approved'
+        CHECK (moderation_status IN ('approved', 'flagged', 'pending_moderation'));
+
+-- モデレーターの確認待ちの投稿だけを素早く取得する
+CREATE INDEX IF NOT EXISTS questions_moderation_status_idx ON questions (id)
+    WHERE moderation_status <> 'approved';
+CREATE INDEX IF NOT EXISTS answers_moderation_status_idx ON answers (id)
+    WHERE moderation_status <> 'approved';
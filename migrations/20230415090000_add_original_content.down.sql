@@ -0,0 +1,4 @@
+ALTER TABLE answers DROP COLUMN IF EXISTS original_content;
+ALTER TABLE questions
+    DROP COLUMN IF EXISTS original_content,
+    DROP COLUMN IF EXISTS original_title;
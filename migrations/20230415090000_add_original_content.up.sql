@@ -0,0 +1,6 @@
+-- 伏せ字に置き換える前の文章 (モデレーター向け)。置き換えていない場合は NULL
+ALTER TABLE questions
+    ADD COLUMN original_title TEXT,
+    ADD COLUMN original_content TEXT;
+ALTER TABLE answers
+    ADD COLUMN original_content TEXT;
@@ -0,0 +1,4 @@
+ALTER TABLE answers
+DROP CONSTRAINT IF EXISTS answers_question_id_fkey,
+ADD CONSTRAINT answers_corresponding_question_fkey
+    FOREIGN KEY (question_id) REFERENCES questions (id);
@@ -0,0 +1,5 @@
+-- 質問を削除した場合は、回答 (と回答への投票) もまとめて削除する
+ALTER TABLE answers
+DROP CONSTRAINT IF EXISTS answers_corresponding_question_fkey,
+ADD CONSTRAINT answers_question_id_fkey
+    FOREIGN KEY (question_id) REFERENCES questions (id) ON DELETE CASCADE;
@@ -0,0 +1,6 @@
+-- パスワードの変更や退会の時点より前に発行したトークンをまとめて失効させるための時刻
+-- 退会したアカウントのトークンも失効させたままにするため、accounts は参照しない
+CREATE TABLE IF NOT EXISTS token_cutoffs (
+    account_id INTEGER PRIMARY KEY,
+    tokens_valid_after TIMESTAMPTZ NOT NULL
+);
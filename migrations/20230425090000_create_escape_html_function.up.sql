@@ -0,0 +1,10 @@
+-- 全文検索の抜粋に含まれる投稿の HTML がそのまま解釈されないよう、
+-- ts_headline に渡す前にエスケープする
+CREATE OR REPLACE FUNCTION escape_html(text) RETURNS text AS $$
+    SELECT replace(replace(replace(replace(replace($1,
+        '&', '&amp;'),
+        '<', '&lt;'),
+        '>', '&gt;'),
+        '"', '&quot;'),
+        '''', '&#39;');
+$$ LANGUAGE SQL IMMUTABLE STRICT;
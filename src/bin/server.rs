@@ -1,5 +1,8 @@
 use dotenv::dotenv;
-use rust_web_dev::{config, run, setup_store};
+use rust_web_dev::{
+    config, run, setup_http_client, setup_mailer, setup_profanity_filter,
+    setup_store,
+};
 
 #[tokio::main]
 async fn main() -> Result<(), handle_errors::Error> {
@@ -7,13 +10,16 @@ async fn main() -> Result<(), handle_errors::Error> {
 
     let config = config::Config::new().expect("Config can't be set");
     let store = setup_store(&config).await?;
+    let mailer = setup_mailer(&config)?;
+    let http_client = setup_http_client(&config)?;
+    let profanity = setup_profanity_filter(&config, http_client)?;
 
     tracing::info!(
         "Q&A service build ID {}",
         env!("RUST_WEB_DEV_VERSION")
     );
 
-    run(config, store).await;
+    run(config, store, mailer, profanity).await;
 
     Ok(())
 }
@@ -1,6 +1,67 @@
 use std::env;
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+
+use crate::types::{hashing::PasswordHashing, login::LoginPolicy};
+
+/// 起動時に選択するストアのバックエンド
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StoreBackend {
+    /// PostgreSQLに接続する
+    Postgres,
+    /// データベースを使用せずにメモリ上で保持する
+    Memory,
+}
+
+/// 起動時に選択するメールの送信手段
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MailerBackend {
+    /// SMTPサーバーを経由して送信する
+    Smtp,
+    /// 送信せずにファイルへ書き出す
+    File,
+}
+
+/// 起動時に選択する不適切な単語の検出手段
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProfanityBackend {
+    /// APILayer の Bad Words API に問い合わせる
+    ApiLayer,
+    /// 単語リストを使ってローカルで検出する
+    Wordlist,
+}
+
+/// 不適切な単語をチェックできなかった場合の投稿の扱い
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProfanityFailurePolicy {
+    /// 投稿を受け付けずにエラーを返す
+    FailClosed,
+    /// チェックせずに公開し、モデレーターの確認が必要な投稿として記録する
+    FailOpen,
+    /// 公開せずに保存し、バックグラウンドで再チェックする
+    Queue,
+}
+
+/// パスワードのハッシュ化に使用する Argon2 の種類
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Argon2Variant {
+    Argon2d,
+    Argon2i,
+    Argon2id,
+}
+
+impl From<Argon2Variant> for argon2::Variant {
+    fn from(variant: Argon2Variant) -> Self {
+        match variant {
+            Argon2Variant::Argon2d => argon2::Variant::Argon2d,
+            Argon2Variant::Argon2i => argon2::Variant::Argon2i,
+            Argon2Variant::Argon2id => argon2::Variant::Argon2id,
+        }
+    }
+}
+
+/// ログインを止める時間に指定できる上限 (1年)
+const MAX_LOCKOUT_SECONDS: u64 = 365 * 24 * 60 * 60;
 
 /// clapを使用して起動時に引数でパラメータを指定できる
 ///
@@ -36,13 +97,105 @@ pub struct Config {
     pub database_password: String,
     #[clap(short, long, default_value = "3030")]
     pub port: u16,
+    #[clap(long, value_enum, default_value = "postgres")]
+    pub store: StoreBackend,
+    #[clap(long, value_enum, default_value = "file")]
+    pub mailer: MailerBackend,
+    #[clap(long, default_value = "localhost")]
+    pub smtp_host: String,
+    #[clap(long, default_value = "1025")]
+    pub smtp_port: u16,
+    #[clap(long, default_value = "noreply@localhost")]
+    pub mail_from: String,
+    /// `--mailer file` の場合の書き出し先
+    #[clap(long, default_value = "mail.log")]
+    pub mail_file: String,
+    /// メールアドレスを確認していないアカウントによる質問と回答の投稿を禁止する
+    #[clap(long)]
+    pub require_verified_email: bool,
+    /// アカウントごとに許容するログインの連続した失敗の回数
+    #[clap(long, default_value = "5")]
+    pub login_max_attempts: u32,
+    /// IPアドレスごとに許容するログインの失敗の回数
+    #[clap(long, default_value = "50")]
+    pub login_max_attempts_per_ip: u32,
+    /// 失敗が上限に達した際に最初にログインを止める時間 (秒、1年まで)
+    #[clap(
+        long,
+        default_value = "60",
+        value_parser = clap::value_parser!(u64).range(..=MAX_LOCKOUT_SECONDS)
+    )]
+    pub login_lockout_seconds: u64,
+    /// ログインを止める時間の上限 (秒、1年まで)
+    #[clap(
+        long,
+        default_value = "3600",
+        value_parser = clap::value_parser!(u64).range(..=MAX_LOCKOUT_SECONDS)
+    )]
+    pub login_max_lockout_seconds: u64,
+    /// 既定値は `argon2::Config::default()` と同じ
+    #[clap(long, value_enum, default_value = "argon2i")]
+    pub argon2_variant: Argon2Variant,
+    /// Argon2 が使用するメモリ (KiB)
+    #[clap(long, default_value = "4096")]
+    pub argon2_memory_kib: u32,
+    /// Argon2 の反復回数
+    #[clap(long, default_value = "3")]
+    pub argon2_iterations: u32,
+    /// Argon2 の並列度
+    #[clap(long, default_value = "1")]
+    pub argon2_parallelism: u32,
+    /// `--profanity-filter api-layer` の場合は環境変数 `BAD_WORDS_API_KEY` が必要
+    #[clap(long, value_enum, default_value = "api-layer")]
+    pub profanity_filter: ProfanityBackend,
+    #[clap(long, default_value = "https://api.apilayer.com")]
+    pub api_layer_url: String,
+    /// `--profanity-filter wordlist` で使用する単語リストのファイル (省略した場合は組み込みのリスト)
+    #[clap(long)]
+    pub profanity_wordlist: Option<String>,
+    /// 不適切な単語を置き換える文字
+    #[clap(long, default_value = "*")]
+    pub censor_character: char,
+    /// 外部APIへの接続のタイムアウト (ミリ秒)
+    #[clap(long, default_value = "2000")]
+    pub http_connect_timeout_ms: u64,
+    /// 外部APIへのリクエスト全体のタイムアウト (ミリ秒)
+    #[clap(long, default_value = "5000")]
+    pub http_timeout_ms: u64,
+    /// APILayer への問い合わせを止めるまでに許容する連続した失敗の回数
+    #[clap(long, default_value = "5")]
+    pub api_layer_failure_threshold: u32,
+    /// APILayer への問い合わせを止めてから再開を試みるまでの時間 (秒)
+    #[clap(long, default_value = "30")]
+    pub api_layer_reset_seconds: u64,
+    /// 不適切な単語をチェックできなかった場合の投稿の扱い
+    #[clap(long, value_enum, default_value = "fail-closed")]
+    pub profanity_failure_policy: ProfanityFailurePolicy,
+    /// `--profanity-failure-policy queue` の場合に保留中の投稿を再チェックする間隔 (秒)
+    #[clap(
+        long,
+        default_value = "60",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub moderation_recheck_seconds: u64,
+    /// 不適切な単語のチェック結果をキャッシュする件数 (0 の場合はキャッシュしない)
+    #[clap(long, default_value = "1000")]
+    pub profanity_cache_size: usize,
+    /// チェック結果をキャッシュする時間 (秒)
+    #[clap(long, default_value = "3600")]
+    pub profanity_cache_ttl_seconds: u64,
+    /// この数より多く不適切な単語を含む投稿は伏せ字にせず拒否する (省略した場合は拒否しない)
+    #[clap(long)]
+    pub profanity_reject_threshold: Option<usize>,
 }
 
 impl Config {
     pub fn new() -> Result<Self, handle_errors::Error> {
         let config = Config::parse();
 
-        if env::var("BAD_WORDS_API_KEY").is_err() {
+        if config.profanity_filter == ProfanityBackend::ApiLayer
+            && env::var("BAD_WORDS_API_KEY").is_err()
+        {
             panic!("BadWords API KEYが設定されていない");
         }
 
@@ -64,9 +217,16 @@ impl Config {
             .unwrap_or(config.database_port.to_string());
         let db_name = env::var("POSTGRES_DB")
             .unwrap_or(config.database_name.to_owned());
-        let db_password = env::var("POSTGRES_PASSWORD").unwrap();
+        let db_password = env::var("POSTGRES_PASSWORD")
+            .unwrap_or(config.database_password.to_owned());
+        let smtp_host =
+            env::var("SMTP_HOST").unwrap_or(config.smtp_host.to_owned());
+        let smtp_port =
+            env::var("SMTP_PORT").unwrap_or(config.smtp_port.to_string());
+        let api_layer_url = env::var("API_LAYER_URL")
+            .unwrap_or(config.api_layer_url.to_owned());
 
-        Ok(Config {
+        let config = Config {
             log_level: config.log_level,
             database_user: db_user,
             database_password: db_password,
@@ -76,7 +236,64 @@ impl Config {
                 .parse::<u16>()
                 .map_err(handle_errors::Error::ParseError)?,
             port,
-        })
+            store: config.store,
+            mailer: config.mailer,
+            smtp_host,
+            smtp_port: smtp_port
+                .parse::<u16>()
+                .map_err(handle_errors::Error::ParseError)?,
+            mail_from: config.mail_from,
+            mail_file: config.mail_file,
+            require_verified_email: config.require_verified_email,
+            login_max_attempts: config.login_max_attempts,
+            login_max_attempts_per_ip: config.login_max_attempts_per_ip,
+            login_lockout_seconds: config.login_lockout_seconds,
+            login_max_lockout_seconds: config.login_max_lockout_seconds,
+            argon2_variant: config.argon2_variant,
+            argon2_memory_kib: config.argon2_memory_kib,
+            argon2_iterations: config.argon2_iterations,
+            argon2_parallelism: config.argon2_parallelism,
+            profanity_filter: config.profanity_filter,
+            api_layer_url,
+            profanity_wordlist: config.profanity_wordlist,
+            censor_character: config.censor_character,
+            http_connect_timeout_ms: config.http_connect_timeout_ms,
+            http_timeout_ms: config.http_timeout_ms,
+            api_layer_failure_threshold: config
+                .api_layer_failure_threshold,
+            api_layer_reset_seconds: config.api_layer_reset_seconds,
+            profanity_failure_policy: config.profanity_failure_policy,
+            moderation_recheck_seconds: config.moderation_recheck_seconds,
+            profanity_cache_size: config.profanity_cache_size,
+            profanity_cache_ttl_seconds: config
+                .profanity_cache_ttl_seconds,
+            profanity_reject_threshold: config.profanity_reject_threshold,
+        };
+
+        config
+            .password_hashing()
+            .validate()
+            .map_err(handle_errors::Error::InvalidPasswordHashing)?;
+
+        Ok(config)
+    }
+
+    pub fn login_policy(&self) -> LoginPolicy {
+        LoginPolicy {
+            max_attempts: self.login_max_attempts,
+            max_attempts_per_ip: self.login_max_attempts_per_ip,
+            lockout_seconds: self.login_lockout_seconds,
+            max_lockout_seconds: self.login_max_lockout_seconds,
+        }
+    }
+
+    pub fn password_hashing(&self) -> PasswordHashing {
+        PasswordHashing {
+            variant: self.argon2_variant.into(),
+            mem_cost: self.argon2_memory_kib,
+            time_cost: self.argon2_iterations,
+            lanes: self.argon2_parallelism,
+        }
     }
 }
 
@@ -99,7 +316,7 @@ mod config_test {
         // 環境変数を設定すると他のテストケースにも影響を与えてしまう
         // 簡単だけど副作用の大きい解決策は `cargo test -- --test-threads=1` で実行して直列実行
         // あるいは2つのテストケースを1つのケースで実行する
-        let result = std::panic::catch_unwind(|| Config::new());
+        let result = std::panic::catch_unwind(Config::new);
 
         assert!(result.is_err());
 
@@ -113,10 +330,61 @@ mod config_test {
             database_host: "localhost".to_string(),
             database_port: 5432,
             database_name: "rustwebdev".to_string(),
+            store: StoreBackend::Postgres,
+            mailer: MailerBackend::File,
+            smtp_host: "localhost".to_string(),
+            smtp_port: 1025,
+            mail_from: "noreply@localhost".to_string(),
+            mail_file: "mail.log".to_string(),
+            require_verified_email: false,
+            login_max_attempts: 5,
+            login_max_attempts_per_ip: 50,
+            login_lockout_seconds: 60,
+            login_max_lockout_seconds: 3600,
+            argon2_variant: Argon2Variant::Argon2i,
+            argon2_memory_kib: 4096,
+            argon2_iterations: 3,
+            argon2_parallelism: 1,
+            profanity_filter: ProfanityBackend::ApiLayer,
+            api_layer_url: "https://api.apilayer.com".to_string(),
+            profanity_wordlist: None,
+            censor_character: '*',
+            http_connect_timeout_ms: 2000,
+            http_timeout_ms: 5000,
+            api_layer_failure_threshold: 5,
+            api_layer_reset_seconds: 30,
+            profanity_failure_policy: ProfanityFailurePolicy::FailClosed,
+            moderation_recheck_seconds: 60,
+            profanity_cache_size: 1000,
+            profanity_cache_ttl_seconds: 3600,
+            profanity_reject_threshold: None,
         };
 
         let config = Config::new().unwrap();
 
         assert_eq!(config, expected);
+        assert_eq!(config.password_hashing(), PasswordHashing::default());
+    }
+
+    #[test]
+    fn reject_too_long_lockout() {
+        let result = Config::try_parse_from([
+            "rust-web-dev",
+            "--login-max-lockout-seconds",
+            &(MAX_LOCKOUT_SECONDS + 1).to_string(),
+        ]);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn reject_zero_recheck_interval() {
+        let result = Config::try_parse_from([
+            "rust-web-dev",
+            "--moderation-recheck-seconds",
+            "0",
+        ]);
+
+        assert!(result.is_err());
     }
 }
@@ -1,25 +1,53 @@
 #![warn(clippy::all)]
+// ルートを `or` で連結した型が深くなるため、コンパイラの上限を引き上げる
+#![recursion_limit = "256"]
 
 pub use handle_errors;
 
+use std::time::Duration;
+
+use reqwest_retry::{
+    policies::ExponentialBackoff, RetryTransientMiddleware,
+};
 use tokio::sync::oneshot::{self, Sender};
 use tracing_subscriber::fmt::format::FmtSpan;
 use warp::{http::Method, Filter, Reply};
 
+use crate::types::{
+    account::Role, hashing::PasswordHashing, login::LoginPolicy,
+};
+
 pub mod config;
-mod profanity;
+pub mod mailer;
+pub mod profanity;
 mod routes;
 mod store;
+#[cfg(test)]
+mod test_support;
 pub mod types;
 
 async fn build_routes(
     store: store::Store,
+    mailer: mailer::MailSender,
+    profanity: profanity::ProfanityChecker,
+    require_verified_email: bool,
+    login_policy: LoginPolicy,
+    password_hashing: PasswordHashing,
 ) -> impl Filter<Extract = impl Reply> + Clone {
-    let store_filter = warp::any().map(move || store.clone());
+    let store_filter = {
+        let store = store.clone();
+        warp::any().map(move || store.clone())
+    };
+    let mailer_filter = warp::any().map(move || mailer.clone());
+    let profanity_filter = warp::any().map(move || profanity.clone());
+    let hashing_filter = warp::any().map(move || password_hashing);
 
     let cors = warp::cors()
         .allow_any_origin()
         .allow_header("content-type")
+        .allow_header("x-pagination-envelope")
+        .allow_header("x-api-key")
+        .expose_headers(vec!["link"])
         .allow_methods(&[
             Method::PUT,
             Method::DELETE,
@@ -28,33 +56,177 @@ async fn build_routes(
         ]);
 
     // ルートハンドラー
+    let health = warp::get()
+        .and(warp::path("health"))
+        .and(warp::path::end())
+        .and(profanity_filter.clone())
+        .and_then(routes::health::health);
+
     let registration = warp::post()
         .and(warp::path("registration"))
         .and(warp::path::end())
         .and(store_filter.clone())
+        .and(mailer_filter.clone())
+        .and(hashing_filter)
         .and(warp::body::json())
         .and_then(routes::authentication::register);
 
+    let verify_email = warp::get()
+        .and(warp::path("verify"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(store_filter.clone())
+        .and_then(routes::verification::verify_email);
+
+    let resend_verification = warp::post()
+        .and(warp::path("verify"))
+        .and(warp::path("resend"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone()))
+        .and(store_filter.clone())
+        .and(mailer_filter.clone())
+        .and_then(routes::verification::resend_verification);
+
     let login = warp::post()
         .and(warp::path("login"))
         .and(warp::path::end())
         .and(store_filter.clone())
+        .and(warp::any().map(move || login_policy))
+        .and(hashing_filter)
+        .and(warp::addr::remote())
         .and(warp::body::json())
         .and_then(routes::authentication::login);
 
+    let refresh_token = warp::post()
+        .and(warp::path("token"))
+        .and(warp::path("refresh"))
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::authentication::refresh);
+
+    let logout = warp::post()
+        .and(warp::path("logout"))
+        .and(warp::path::end())
+        .and(routes::authentication::require_token(store.clone()))
+        .and(store_filter.clone())
+        .and_then(routes::authentication::logout);
+
+    let forgot_password = warp::post()
+        .and(warp::path("password"))
+        .and(warp::path("forgot"))
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and(mailer_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::password::forgot_password);
+
+    let reset_password = warp::post()
+        .and(warp::path("password"))
+        .and(warp::path("reset"))
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and(hashing_filter)
+        .and(warp::body::json())
+        .and_then(routes::password::reset_password);
+
+    // アカウント周りのハンドラー
+    let get_profile = warp::get()
+        .and(warp::path("me"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone()))
+        .and(store_filter.clone())
+        .and_then(routes::account::get_profile);
+
+    let change_password = warp::put()
+        .and(warp::path("me"))
+        .and(warp::path("password"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone()))
+        .and(store_filter.clone())
+        .and(hashing_filter)
+        .and(warp::body::json())
+        .and_then(routes::account::change_password);
+
+    let change_email = warp::put()
+        .and(warp::path("me"))
+        .and(warp::path("email"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone()))
+        .and(store_filter.clone())
+        .and(mailer_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::account::change_email);
+
+    let delete_account = warp::delete()
+        .and(warp::path("me"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone()))
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::account::delete_account);
+
+    let create_api_key = warp::post()
+        .and(warp::path("me"))
+        .and(warp::path("api-keys"))
+        .and(warp::path::end())
+        .and(routes::authentication::require_token(store.clone()))
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::api_key::create_api_key);
+
+    let get_api_keys = warp::get()
+        .and(warp::path("me"))
+        .and(warp::path("api-keys"))
+        .and(warp::path::end())
+        .and(routes::authentication::require_token(store.clone()))
+        .and(store_filter.clone())
+        .and_then(routes::api_key::get_api_keys);
+
+    let revoke_api_key = warp::delete()
+        .and(warp::path("me"))
+        .and(warp::path("api-keys"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(routes::authentication::require_token(store.clone()))
+        .and(store_filter.clone())
+        .and_then(routes::api_key::revoke_api_key);
+
     // 質問周りのハンドラー
     let get_questions = warp::get()
         .and(warp::path("questions"))
         .and(warp::path::end())
         .and(warp::query())
+        .and(warp::header::optional::<String>("x-pagination-envelope"))
         .and(store_filter.clone())
         .and_then(routes::question::get_questions);
 
+    let get_question = warp::get()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(store_filter.clone())
+        .and_then(routes::question::get_question);
+
+    let get_answers = warp::get()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("answers"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(store_filter.clone())
+        .and_then(routes::answer::get_answers);
+
     let add_question = warp::post()
         .and(warp::path("questions"))
         .and(warp::path::end())
-        .and(routes::authentication::auth())
+        .and(routes::authentication::require_verified_email(
+            store.clone(),
+            require_verified_email,
+        ))
         .and(store_filter.clone())
+        .and(profanity_filter.clone())
         .and(warp::body::json())
         .and_then(routes::question::add_question);
 
@@ -62,8 +234,9 @@ async fn build_routes(
         .and(warp::path("questions"))
         .and(warp::path::param::<i32>())
         .and(warp::path::end())
-        .and(routes::authentication::auth())
+        .and(routes::authentication::auth(store.clone()))
         .and(store_filter.clone())
+        .and(profanity_filter.clone())
         .and(warp::body::json())
         .and_then(routes::question::update_question);
 
@@ -71,25 +244,255 @@ async fn build_routes(
         .and(warp::path("questions"))
         .and(warp::path::param::<i32>())
         .and(warp::path::end())
-        .and(routes::authentication::auth())
+        .and(routes::authentication::auth(store.clone()))
         .and(store_filter.clone())
         .and_then(routes::question::delete_question);
 
+    let accept_answer = warp::post()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("accept"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone()))
+        .and(store_filter.clone())
+        .and_then(routes::question::accept_answer);
+
+    let search_answers = warp::get()
+        .and(warp::path("answers"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(store_filter.clone())
+        .and_then(routes::answer::search_answers);
+
+    let get_tags = warp::get()
+        .and(warp::path("tags"))
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and_then(routes::tag::get_tags);
+
     let add_answer = warp::post()
         .and(warp::path("answers"))
         .and(warp::path::end())
-        .and(routes::authentication::auth())
+        .and(routes::authentication::require_verified_email(
+            store.clone(),
+            require_verified_email,
+        ))
         .and(store_filter.clone())
+        .and(profanity_filter.clone())
         .and(warp::body::form())
         .and_then(routes::answer::add_answer);
 
+    let update_answer = warp::put()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone()))
+        .and(store_filter.clone())
+        .and(profanity_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::answer::update_answer);
+
+    let delete_answer = warp::delete()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone()))
+        .and(store_filter.clone())
+        .and_then(routes::answer::delete_answer);
+
+    let vote_question = warp::post()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("vote"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone()))
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::vote::vote_question);
+
+    let retract_question_vote = warp::delete()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("vote"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone()))
+        .and(store_filter.clone())
+        .and_then(routes::vote::retract_question_vote);
+
+    let vote_answer = warp::post()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("vote"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone()))
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::vote::vote_answer);
+
+    let retract_answer_vote = warp::delete()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("vote"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone()))
+        .and(store_filter.clone())
+        .and_then(routes::vote::retract_answer_vote);
+
+    // モデレーターと管理者のハンドラー
+    let moderate_question = warp::put()
+        .and(warp::path("moderation"))
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(routes::authentication::require_role(
+            store.clone(),
+            Role::Moderator,
+        ))
+        .and(store_filter.clone())
+        .and(profanity_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::moderation::moderate_question);
+
+    let get_moderated_question = warp::get()
+        .and(warp::path("moderation"))
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(routes::authentication::require_role(
+            store.clone(),
+            Role::Moderator,
+        ))
+        .and(store_filter.clone())
+        .and_then(routes::moderation::get_moderated_question);
+
+    let remove_question = warp::delete()
+        .and(warp::path("moderation"))
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(routes::authentication::require_role(
+            store.clone(),
+            Role::Moderator,
+        ))
+        .and(store_filter.clone())
+        .and_then(routes::moderation::remove_question);
+
+    let moderate_answer = warp::put()
+        .and(warp::path("moderation"))
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(routes::authentication::require_role(
+            store.clone(),
+            Role::Moderator,
+        ))
+        .and(store_filter.clone())
+        .and(profanity_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::moderation::moderate_answer);
+
+    let get_moderated_answer = warp::get()
+        .and(warp::path("moderation"))
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(routes::authentication::require_role(
+            store.clone(),
+            Role::Moderator,
+        ))
+        .and(store_filter.clone())
+        .and_then(routes::moderation::get_moderated_answer);
+
+    let remove_answer = warp::delete()
+        .and(warp::path("moderation"))
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(routes::authentication::require_role(
+            store.clone(),
+            Role::Moderator,
+        ))
+        .and(store_filter.clone())
+        .and_then(routes::moderation::remove_answer);
+
+    let get_audit_log = warp::get()
+        .and(warp::path("moderation"))
+        .and(warp::path("audit"))
+        .and(warp::path::end())
+        .and(routes::authentication::require_role(
+            store.clone(),
+            Role::Admin,
+        ))
+        .and(warp::query())
+        .and(store_filter.clone())
+        .and_then(routes::moderation::get_audit_log);
+
+    let get_moderation_queue = warp::get()
+        .and(warp::path("moderation"))
+        .and(warp::path("queue"))
+        .and(warp::path::end())
+        .and(routes::authentication::require_role(
+            store.clone(),
+            Role::Moderator,
+        ))
+        .and(store_filter.clone())
+        .and_then(routes::moderation::get_moderation_queue);
+
+    let change_role = warp::put()
+        .and(warp::path("accounts"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("role"))
+        .and(warp::path::end())
+        .and(routes::authentication::require_role(
+            store.clone(),
+            Role::Admin,
+        ))
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::moderation::change_role);
+
     get_questions
+        .or(health)
+        .or(get_question)
+        .or(get_answers)
         .or(update_question)
         .or(add_question)
         .or(delete_question)
+        .or(accept_answer)
+        .or(search_answers)
         .or(add_answer)
+        .or(update_answer)
+        .or(delete_answer)
+        .or(vote_question)
+        .or(retract_question_vote)
+        .or(vote_answer)
+        .or(retract_answer_vote)
+        .or(get_tags)
+        .or(moderate_question)
+        .or(get_moderated_question)
+        .or(remove_question)
+        .or(moderate_answer)
+        .or(get_moderated_answer)
+        .or(remove_answer)
+        .or(get_audit_log)
+        .or(get_moderation_queue)
+        .or(change_role)
         .or(registration)
+        .or(verify_email)
+        .or(resend_verification)
         .or(login)
+        .or(refresh_token)
+        .or(logout)
+        .or(forgot_password)
+        .or(reset_password)
+        .or(get_profile)
+        .or(change_password)
+        .or(change_email)
+        .or(delete_account)
+        .or(create_api_key)
+        .or(get_api_keys)
+        .or(revoke_api_key)
         .with(cors)
         .with(warp::trace::request())
         .recover(handle_errors::return_error)
@@ -98,21 +501,30 @@ async fn build_routes(
 pub async fn setup_store(
     config: &config::Config,
 ) -> Result<store::Store, handle_errors::Error> {
-    let store = store::Store::new(&format!(
-        "postgres://{}:{}@{}:{}/{}",
-        config.database_user,
-        config.database_password,
-        config.database_host,
-        config.database_port,
-        config.database_name
-    ))
-    .await
-    .map_err(handle_errors::Error::DatabaseQueryError)?;
-
-    sqlx::migrate!()
-        .run(&store.clone().connection)
-        .await
-        .map_err(handle_errors::Error::MigrationError)?;
+    let store = match config.store {
+        config::StoreBackend::Postgres => {
+            let store = store::PostgresStore::new(&format!(
+                "postgres://{}:{}@{}:{}/{}",
+                config.database_user,
+                config.database_password,
+                config.database_host,
+                config.database_port,
+                config.database_name
+            ))
+            .await
+            .map_err(handle_errors::Error::DatabaseQueryError)?;
+
+            sqlx::migrate!()
+                .run(&store.connection)
+                .await
+                .map_err(handle_errors::Error::MigrationError)?;
+
+            store::Store::new(store)
+        }
+        config::StoreBackend::Memory => {
+            store::Store::new(store::InMemoryStore::with_seed())
+        }
+    };
 
     let log_filter = std::env::var("RUST_LOG").unwrap_or_else(|_| {
         format!(
@@ -131,8 +543,141 @@ pub async fn setup_store(
     Ok(store)
 }
 
-pub async fn run(config: config::Config, store: store::Store) {
-    let routes = build_routes(store).await;
+/// 起動時の設定に従ってメールの送信手段を用意する
+///
+/// SMTPの認証情報は環境変数 `SMTP_USERNAME` と `SMTP_PASSWORD` から読み込む
+pub fn setup_mailer(
+    config: &config::Config,
+) -> Result<mailer::MailSender, handle_errors::Error> {
+    let mailer = match config.mailer {
+        config::MailerBackend::Smtp => {
+            let credentials = std::env::var("SMTP_USERNAME")
+                .ok()
+                .zip(std::env::var("SMTP_PASSWORD").ok());
+
+            mailer::MailSender::new(mailer::SmtpMailer::new(
+                &config.smtp_host,
+                config.smtp_port,
+                credentials,
+                &config.mail_from,
+            )?)
+        }
+        config::MailerBackend::File => mailer::MailSender::new(
+            mailer::FileMailer::new(&config.mail_file),
+        ),
+    };
+
+    Ok(mailer)
+}
+
+/// 外部APIへのリクエストに使用するクライアントを用意する
+///
+/// 接続を使い回せるよう、起動時に1つだけ作ってアプリケーション全体で共有する
+pub fn setup_http_client(
+    config: &config::Config,
+) -> Result<reqwest_middleware::ClientWithMiddleware, handle_errors::Error>
+{
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_millis(
+            config.http_connect_timeout_ms,
+        ))
+        .timeout(Duration::from_millis(config.http_timeout_ms))
+        .build()
+        .map_err(handle_errors::Error::ReqwestAPIError)?;
+
+    let retry_policy =
+        ExponentialBackoff::builder().build_with_max_retries(3);
+
+    Ok(reqwest_middleware::ClientBuilder::new(client)
+        .with(RetryTransientMiddleware::new_with_policy(retry_policy))
+        .build())
+}
+
+/// 起動時の設定に従って不適切な単語の検出手段を用意する
+///
+/// APILayer の APIキーは環境変数 `BAD_WORDS_API_KEY` から読み込む
+pub fn setup_profanity_filter(
+    config: &config::Config,
+    http_client: reqwest_middleware::ClientWithMiddleware,
+) -> Result<profanity::ProfanityChecker, handle_errors::Error> {
+    let filter = match config.profanity_filter {
+        config::ProfanityBackend::ApiLayer => {
+            // `Config::new` で環境変数が設定されているかどうかは検証済みなので unwrap
+            let api_key = std::env::var("BAD_WORDS_API_KEY").unwrap();
+
+            profanity::ProfanityChecker::new(
+                profanity::ApiLayerFilter::new(
+                    http_client,
+                    &config.api_layer_url,
+                    &api_key,
+                    config.censor_character,
+                    profanity::CircuitBreaker::new(
+                        "api_layer",
+                        config.api_layer_failure_threshold,
+                        Duration::from_secs(
+                            config.api_layer_reset_seconds,
+                        ),
+                    ),
+                ),
+            )
+        }
+        config::ProfanityBackend::Wordlist => {
+            let filter = match &config.profanity_wordlist {
+                Some(path) => profanity::WordlistFilter::from_file(
+                    path,
+                    config.censor_character,
+                )?,
+                None => profanity::WordlistFilter::with_default_list(
+                    config.censor_character,
+                ),
+            };
+
+            profanity::ProfanityChecker::new(filter)
+        }
+    };
+
+    let filter = if config.profanity_cache_size > 0 {
+        filter.with_cache(
+            config.profanity_cache_size,
+            Duration::from_secs(config.profanity_cache_ttl_seconds),
+        )
+    } else {
+        filter
+    };
+
+    let filter = match config.profanity_reject_threshold {
+        Some(threshold) => filter.with_reject_threshold(threshold),
+        None => filter,
+    };
+
+    Ok(filter.with_failure_policy(config.profanity_failure_policy))
+}
+
+pub async fn run(
+    config: config::Config,
+    store: store::Store,
+    mailer: mailer::MailSender,
+    profanity: profanity::ProfanityChecker,
+) {
+    if config.profanity_failure_policy
+        == config::ProfanityFailurePolicy::Queue
+    {
+        profanity::spawn_recheck_task(
+            store.clone(),
+            profanity.clone(),
+            Duration::from_secs(config.moderation_recheck_seconds),
+        );
+    }
+
+    let routes = build_routes(
+        store,
+        mailer,
+        profanity,
+        config.require_verified_email,
+        config.login_policy(),
+        config.password_hashing(),
+    )
+    .await;
     warp::serve(routes).run(([0, 0, 0, 0], config.port)).await;
 }
 
@@ -140,8 +685,23 @@ pub struct OneshotHandler {
     pub sender: Sender<i32>,
 }
 
-pub async fn oneshot(store: store::Store) -> OneshotHandler {
-    let routes = build_routes(store).await;
+/// テスト用にサーバーを起動する
+///
+/// 登録したアカウントをすぐに使えるよう、メールアドレスの確認は求めない
+pub async fn oneshot(
+    store: store::Store,
+    mailer: mailer::MailSender,
+    profanity: profanity::ProfanityChecker,
+) -> OneshotHandler {
+    let routes = build_routes(
+        store,
+        mailer,
+        profanity,
+        false,
+        LoginPolicy::default(),
+        PasswordHashing::default(),
+    )
+    .await;
     let (tx, rx) = oneshot::channel::<i32>();
 
     let socket: std::net::SocketAddr = "127.0.0.1:3030"
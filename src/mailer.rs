@@ -0,0 +1,245 @@
+use std::{fmt::Debug, ops::Deref, path::PathBuf, sync::Arc};
+
+use async_trait::async_trait;
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use tokio::io::AsyncWriteExt;
+use tracing::{event, Level};
+
+use handle_errors::Error;
+
+/// 送信するメール
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// メールの送信を担当する
+#[async_trait]
+pub trait Mailer: Debug + Send + Sync {
+    async fn send(&self, mail: Mail) -> Result<(), Error>;
+}
+
+/// ルートハンドラに渡されるメールの送信手段
+///
+/// `Store` と同じく、起動時に選択したバックエンドをトレイトオブジェクトとして保持する
+#[derive(Debug, Clone)]
+pub struct MailSender(Arc<dyn Mailer>);
+
+impl MailSender {
+    pub fn new<M: Mailer + 'static>(mailer: M) -> Self {
+        MailSender(Arc::new(mailer))
+    }
+}
+
+impl Deref for MailSender {
+    type Target = dyn Mailer;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+/// SMTPサーバーを経由してメールを送信する
+///
+/// 認証情報を指定した場合は STARTTLS を必須にする。
+/// 指定しない場合は平文で接続するため、ローカルの検証用SMTPサーバーにそのまま送信できる
+#[derive(Debug, Clone)]
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(
+        host: &str,
+        port: u16,
+        credentials: Option<(String, String)>,
+        from: &str,
+    ) -> Result<Self, Error> {
+        let builder = match credentials {
+            Some((username, password)) => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+                    .map_err(|e| Error::MailError(e.to_string()))?
+                    .credentials(Credentials::new(username, password))
+            }
+            None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                    host,
+                )
+            }
+        };
+
+        Ok(SmtpMailer {
+            transport: builder.port(port).build(),
+            from: parse_mailbox(from)?,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, mail: Mail) -> Result<(), Error> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(parse_mailbox(&mail.to)?)
+            .subject(mail.subject)
+            .body(mail.body)
+            .map_err(|e| Error::MailError(e.to_string()))?;
+
+        match self.transport.send(message).await {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                event!(Level::ERROR, "{:?}", e);
+                Err(Error::MailError(e.to_string()))
+            }
+        }
+    }
+}
+
+/// メールを送信せずにファイルへ追記し、ログにも出力する
+///
+/// ローカルでの開発やテストで使用する
+#[derive(Debug, Clone)]
+pub struct FileMailer {
+    path: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileMailer { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, mail: Mail) -> Result<(), Error> {
+        event!(
+            Level::INFO,
+            to = mail.to.as_str(),
+            subject = mail.subject.as_str(),
+            "writing mail to {}",
+            self.path.display()
+        );
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(|e| Error::MailError(e.to_string()))?;
+
+        let text = format!(
+            "To: {}\nSubject: {}\n\n{}\n\n",
+            mail.to, mail.subject, mail.body
+        );
+        file.write_all(text.as_bytes())
+            .await
+            .map_err(|e| Error::MailError(e.to_string()))
+    }
+}
+
+fn parse_mailbox(address: &str) -> Result<Mailbox, Error> {
+    address.parse().map_err(|_| {
+        Error::MailError(format!("Invalid address: {}", address))
+    })
+}
+
+#[cfg(test)]
+mod mailer_tests {
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+    };
+
+    use super::{FileMailer, Mail, Mailer, SmtpMailer};
+
+    fn mail() -> Mail {
+        Mail {
+            to: "test@example.com".to_string(),
+            subject: "Hello".to_string(),
+            body: "reset token".to_string(),
+        }
+    }
+
+    /// 1通だけ受け取って本文を返す検証用のSMTPサーバー
+    async fn fake_smtp_server() -> (u16, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let handle = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = socket.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let mut data = String::new();
+            let mut in_data = false;
+
+            writer.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                if in_data {
+                    if line == "." {
+                        in_data = false;
+                        writer.write_all(b"250 OK\r\n").await.unwrap();
+                    } else {
+                        data.push_str(&line);
+                        data.push('\n');
+                    }
+                    continue;
+                }
+
+                let command = line.to_uppercase();
+                let reply: &[u8] = if command.starts_with("DATA") {
+                    in_data = true;
+                    b"354 End data with <CR><LF>.<CR><LF>\r\n"
+                } else if command.starts_with("QUIT") {
+                    writer.write_all(b"221 Bye\r\n").await.unwrap();
+                    break;
+                } else {
+                    b"250 OK\r\n"
+                };
+                writer.write_all(reply).await.unwrap();
+            }
+
+            data
+        });
+
+        (port, handle)
+    }
+
+    #[tokio::test]
+    async fn send_mail_over_smtp() {
+        let (port, server) = fake_smtp_server().await;
+        let mailer = SmtpMailer::new(
+            "127.0.0.1",
+            port,
+            None,
+            "noreply@example.com",
+        )
+        .unwrap();
+
+        mailer.send(mail()).await.unwrap();
+
+        let data = server.await.unwrap();
+        assert!(data.contains("To: test@example.com"));
+        assert!(data.contains("Subject: Hello"));
+        assert!(data.contains("reset token"));
+    }
+
+    #[tokio::test]
+    async fn write_mail_to_file() {
+        let path = std::env::temp_dir()
+            .join(format!("mailer-test-{}.log", uuid::Uuid::new_v4()));
+        let mailer = FileMailer::new(&path);
+
+        mailer.send(mail()).await.unwrap();
+        mailer.send(mail()).await.unwrap();
+
+        let text = tokio::fs::read_to_string(&path).await.unwrap();
+        assert_eq!(text.matches("Subject: Hello").count(), 2);
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+}
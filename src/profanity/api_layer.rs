@@ -0,0 +1,243 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use reqwest_middleware::ClientWithMiddleware;
+use serde::{Deserialize, Serialize};
+use tracing::{event, Level};
+
+use crate::profanity::{
+    Censored, CircuitBreaker, CircuitState, ProfanityFilter,
+    ProfanityMatch,
+};
+use handle_errors::Error;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct APIResponse {
+    message: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct BadWord {
+    original: String,
+    word: String,
+    deviations: i64,
+    info: i64,
+    #[serde(rename = "replacedLen")]
+    replaced_len: i64,
+    start: usize,
+    end: usize,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct BadWordsResponse {
+    content: String,
+    bad_words_total: i64,
+    bad_words_list: Vec<BadWord>,
+    censored_content: String,
+}
+
+/// APILayer の Bad Words API で不適切な単語を検出する
+///
+/// 失敗が続いた場合はサーキットブレーカーを開き、APIに問い合わせずにすぐ失敗させる
+#[derive(Debug)]
+pub struct ApiLayerFilter {
+    client: ClientWithMiddleware,
+    url: String,
+    api_key: String,
+    censor_character: char,
+    breaker: CircuitBreaker,
+}
+
+impl ApiLayerFilter {
+    /// `client` はアプリケーション全体で共有するものを渡す
+    pub fn new(
+        client: ClientWithMiddleware,
+        url: &str,
+        api_key: &str,
+        censor_character: char,
+        breaker: CircuitBreaker,
+    ) -> Self {
+        ApiLayerFilter {
+            client,
+            url: url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            censor_character,
+            breaker,
+        }
+    }
+
+    async fn request(&self, content: String) -> Result<Censored, Error> {
+        let res = self
+            .client
+            .post(format!("{}/bad_words", self.url))
+            .query(&[("censor_character", self.censor_character)])
+            .header("apikey", &self.api_key)
+            .body(content.clone())
+            .send()
+            .await
+            .map_err(Error::MiddlewareReqwestAPIError)?;
+
+        if !res.status().is_success() {
+            if res.status().is_client_error() {
+                let err = transform_error(res).await;
+                return Err(Error::ClientError(err));
+            } else {
+                let err = transform_error(res).await;
+                return Err(Error::ServerError(err));
+            }
+        }
+
+        match res.json::<BadWordsResponse>().await {
+            // 不適切な単語がない場合、`censored_content` は空になる
+            Ok(res) if res.bad_words_total == 0 => Ok(Censored {
+                content,
+                matches: Vec::new(),
+            }),
+            Ok(res) => Ok(Censored {
+                content: res.censored_content,
+                matches: res
+                    .bad_words_list
+                    .into_iter()
+                    .map(|bad_word| ProfanityMatch {
+                        word: bad_word.original,
+                        start: bad_word.start,
+                        end: bad_word.end,
+                    })
+                    .collect(),
+            }),
+            Err(e) => Err(Error::ReqwestAPIError(e)),
+        }
+    }
+}
+
+#[async_trait]
+impl ProfanityFilter for ApiLayerFilter {
+    async fn censor(&self, content: String) -> Result<Censored, Error> {
+        self.breaker.try_acquire().map_err(Error::CircuitOpen)?;
+
+        let result = self.request(content).await;
+        match &result {
+            // リクエストの内容による失敗はAPIの障害として数えない
+            Err(Error::ClientError(err))
+                if err.status
+                    != StatusCode::TOO_MANY_REQUESTS.as_u16() =>
+            {
+                self.breaker.record_success()
+            }
+            Err(e) => {
+                event!(Level::ERROR, "profanity check failed: {}", e);
+                self.breaker.record_failure()
+            }
+            Ok(_) => self.breaker.record_success(),
+        }
+
+        result
+    }
+
+    fn circuit_state(&self) -> Option<CircuitState> {
+        Some(self.breaker.state())
+    }
+}
+
+async fn transform_error(
+    res: reqwest::Response,
+) -> handle_errors::APILayerError {
+    handle_errors::APILayerError {
+        status: res.status().as_u16(),
+        // 障害時はJSON以外のレスポンスが返る場合もある
+        message: res
+            .json::<APIResponse>()
+            .await
+            .map(|res| res.message)
+            .unwrap_or_else(|e| e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod api_layer_tests {
+    use std::time::Duration;
+
+    use mock_server::{MockServer, OneshotHandler};
+    use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+
+    use super::ApiLayerFilter;
+    use crate::profanity::{
+        CircuitBreaker, CircuitState, ProfanityFilter, ProfanityMatch,
+    };
+    use handle_errors::Error;
+
+    /// 失敗した場合にすぐ結果が分かるよう、再試行しないクライアントを使う
+    fn client() -> ClientWithMiddleware {
+        ClientBuilder::new(reqwest::Client::new()).build()
+    }
+
+    fn filter(url: &str) -> ApiLayerFilter {
+        ApiLayerFilter::new(
+            client(),
+            url,
+            "YES",
+            '*',
+            CircuitBreaker::new("api_layer", 2, Duration::from_secs(30)),
+        )
+    }
+
+    #[tokio::test]
+    async fn run() {
+        let handler = run_mock();
+        let filter = filter("http://127.0.0.1:3031");
+        censor_profane_words(&filter).await;
+        no_profane_words(&filter).await;
+        let _ = handler.sender.send(1);
+    }
+
+    fn run_mock() -> OneshotHandler {
+        let socket = "127.0.0.1:3031"
+            .to_string()
+            .parse()
+            .expect("Not a valid address");
+        let mock = MockServer::new(socket);
+        mock.oneshot()
+    }
+
+    async fn censor_profane_words(filter: &ApiLayerFilter) {
+        let content = "This is a shitty sentence".to_string();
+        let censored = filter.censor(content).await.unwrap();
+        assert_eq!(censored.content, "this is a ****** sentence");
+        assert_eq!(
+            censored.matches,
+            vec![ProfanityMatch {
+                word: "shitty".to_string(),
+                start: 10,
+                end: 16,
+            }]
+        );
+    }
+
+    async fn no_profane_words(filter: &ApiLayerFilter) {
+        let content = "this is a sentence".to_string();
+        let censored = filter.censor(content).await.unwrap();
+        // 空の `censored_content` ではなく元の文章を返す
+        assert_eq!(censored.content, "this is a sentence");
+        assert!(censored.matches.is_empty());
+    }
+
+    #[tokio::test]
+    async fn open_circuit_when_api_is_unreachable() {
+        // 何も待ち受けていないポートに接続する
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let filter = filter(&url);
+
+        for _ in 0..2 {
+            let result = filter.censor("content".to_string()).await;
+            assert!(matches!(
+                result,
+                Err(Error::MiddlewareReqwestAPIError(_))
+            ));
+        }
+        assert_eq!(filter.circuit_state(), Some(CircuitState::Open));
+
+        let result = filter.censor("content".to_string()).await;
+        assert!(matches!(result, Err(Error::CircuitOpen(30))));
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::profanity::{Censored, CircuitState, ProfanityFilter};
+use handle_errors::Error;
+
+/// キャッシュの利用状況
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// 現在保持している件数
+    pub entries: usize,
+}
+
+/// 同じ文章を何度もチェックしないよう、チェックした結果を保持する
+///
+/// 文章の SHA-256 ハッシュをキーとし、`capacity` を超えた場合は最も長く
+/// 使われていない結果から捨てる。`ttl` を過ぎた結果は使わない。
+/// チェックに失敗した場合は保持しない
+#[derive(Debug)]
+pub struct CachedFilter {
+    inner: Arc<dyn ProfanityFilter>,
+    capacity: usize,
+    ttl: Duration,
+    entries: Mutex<LruEntries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+type Key = [u8; 32];
+
+#[derive(Debug, Default)]
+struct LruEntries {
+    values: HashMap<Key, Entry>,
+    /// 最後に使った順序 (小さいほど古い)
+    recency: BTreeMap<u64, Key>,
+    tick: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    censored: Censored,
+    expires_at: Instant,
+    tick: u64,
+}
+
+impl CachedFilter {
+    pub fn new(
+        inner: Arc<dyn ProfanityFilter>,
+        capacity: usize,
+        ttl: Duration,
+    ) -> Self {
+        CachedFilter {
+            inner,
+            capacity,
+            ttl,
+            entries: Mutex::new(LruEntries::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn get(&self, key: &Key) -> Option<Censored> {
+        let mut entries = self.entries.lock().unwrap();
+        let entries = &mut *entries;
+        let entry = entries.values.get_mut(key)?;
+
+        entries.recency.remove(&entry.tick);
+        if entry.expires_at <= Instant::now() {
+            entries.values.remove(key);
+            return None;
+        }
+
+        entries.tick += 1;
+        entry.tick = entries.tick;
+        entries.recency.insert(entry.tick, *key);
+        Some(entry.censored.clone())
+    }
+
+    fn insert(&self, key: Key, censored: Censored) {
+        let mut entries = self.entries.lock().unwrap();
+
+        if let Some(old) = entries.values.remove(&key) {
+            entries.recency.remove(&old.tick);
+        }
+        while entries.values.len() >= self.capacity {
+            match entries.recency.pop_first() {
+                Some((_, oldest)) => entries.values.remove(&oldest),
+                None => break,
+            };
+        }
+
+        entries.tick += 1;
+        let tick = entries.tick;
+        entries.recency.insert(tick, key);
+        entries.values.insert(
+            key,
+            Entry {
+                censored,
+                expires_at: Instant::now() + self.ttl,
+                tick,
+            },
+        );
+    }
+}
+
+#[async_trait]
+impl ProfanityFilter for CachedFilter {
+    async fn censor(&self, content: String) -> Result<Censored, Error> {
+        let key: Key = Sha256::digest(content.as_bytes()).into();
+
+        if let Some(censored) = self.get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(censored);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let censored = self.inner.censor(content).await?;
+        self.insert(key, censored.clone());
+
+        Ok(censored)
+    }
+
+    fn circuit_state(&self) -> Option<CircuitState> {
+        self.inner.circuit_state()
+    }
+
+    fn cache_stats(&self) -> Option<CacheStats> {
+        Some(CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().unwrap().values.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod cache_tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+
+    use super::{CacheStats, CachedFilter};
+    use crate::profanity::{Censored, ProfanityFilter};
+    use handle_errors::Error;
+
+    /// 問い合わせた回数を数える
+    #[derive(Debug, Default)]
+    struct CountingFilter {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl ProfanityFilter for CountingFilter {
+        async fn censor(
+            &self,
+            content: String,
+        ) -> Result<Censored, Error> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(Censored {
+                content: content.to_uppercase(),
+                matches: Vec::new(),
+            })
+        }
+    }
+
+    fn cached(
+        capacity: usize,
+        ttl: Duration,
+    ) -> (Arc<CountingFilter>, CachedFilter) {
+        let inner = Arc::new(CountingFilter::default());
+        (inner.clone(), CachedFilter::new(inner, capacity, ttl))
+    }
+
+    #[tokio::test]
+    async fn reuse_results_and_evict_least_recently_used() {
+        let (inner, filter) = cached(2, Duration::from_secs(60));
+
+        for content in ["a", "b", "a", "c", "a", "b"] {
+            assert_eq!(
+                filter.censor(content.to_string()).await.unwrap().content,
+                content.to_uppercase()
+            );
+        }
+
+        // "c" を追加した時点で最も長く使われていない "b" が捨てられる
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 4);
+        assert_eq!(
+            filter.cache_stats(),
+            Some(CacheStats {
+                hits: 2,
+                misses: 4,
+                entries: 2,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn expire_results_after_ttl() {
+        let (inner, filter) = cached(10, Duration::from_millis(50));
+
+        filter.censor("a".to_string()).await.unwrap();
+        filter.censor("a".to_string()).await.unwrap();
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        filter.censor("a".to_string()).await.unwrap();
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    }
+}
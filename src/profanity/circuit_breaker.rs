@@ -0,0 +1,170 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{event, Level};
+
+/// サーキットブレーカーの状態
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// 通常どおりリクエストを送る
+    Closed,
+    /// 失敗が続いたため、リクエストを送らずにすぐ失敗させる
+    Open,
+    /// 一定時間が経過したため、次のリクエストで回復したかを確かめる
+    HalfOpen,
+}
+
+/// 外部APIへの失敗が続いた場合に、一定時間リクエストを止める
+///
+/// 連続した失敗が `failure_threshold` に達すると開き、`reset_timeout` の間は
+/// リクエストを送らない。その後に送った1件が成功すれば閉じ、失敗すれば再び開く
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    name: &'static str,
+    failure_threshold: u32,
+    reset_timeout: Duration,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(
+        name: &'static str,
+        failure_threshold: u32,
+        reset_timeout: Duration,
+    ) -> Self {
+        CircuitBreaker {
+            name,
+            failure_threshold,
+            reset_timeout,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        let state = self.state.lock().unwrap();
+
+        match state.opened_at {
+            None => CircuitState::Closed,
+            Some(opened_at)
+                if opened_at.elapsed() < self.reset_timeout =>
+            {
+                CircuitState::Open
+            }
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// リクエストを送ってよいかを確認する
+    ///
+    /// 開いている場合は、回復を確かめられるようになるまでの秒数を返す。
+    /// 半開の場合は1件だけ通し、結果が出るまで他のリクエストは止める
+    pub fn try_acquire(&self) -> Result<(), u64> {
+        let mut state = self.state.lock().unwrap();
+
+        match state.opened_at {
+            None => Ok(()),
+            Some(opened_at) => {
+                let elapsed = opened_at.elapsed();
+                if elapsed < self.reset_timeout {
+                    let remaining = self.reset_timeout - elapsed;
+                    Err(remaining.as_millis().div_ceil(1000) as u64)
+                } else {
+                    event!(
+                        Level::INFO,
+                        circuit = self.name,
+                        "circuit half-open, sending trial request"
+                    );
+                    state.opened_at = Some(Instant::now());
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        if state.opened_at.is_some() {
+            event!(Level::INFO, circuit = self.name, "circuit closed");
+        }
+        *state = BreakerState::default();
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+
+        if state.consecutive_failures >= self.failure_threshold {
+            if state.opened_at.is_none() {
+                event!(
+                    Level::WARN,
+                    circuit = self.name,
+                    failures = state.consecutive_failures,
+                    "circuit opened"
+                );
+            }
+            state.opened_at = Some(Instant::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod circuit_breaker_tests {
+    use std::time::Duration;
+
+    use super::{CircuitBreaker, CircuitState};
+
+    #[test]
+    fn open_after_repeated_failures() {
+        let breaker =
+            CircuitBreaker::new("test", 2, Duration::from_secs(30));
+
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.try_acquire().is_ok());
+
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert_eq!(breaker.try_acquire(), Err(30));
+
+        // 成功すると失敗の回数を数え直す
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn allow_single_trial_when_half_open() {
+        let breaker =
+            CircuitBreaker::new("test", 1, Duration::from_millis(50));
+
+        breaker.record_failure();
+        assert!(breaker.try_acquire().is_err());
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(breaker.try_acquire().is_ok());
+        // 試しに送ったリクエストの結果が出るまでは他のリクエストを止める
+        assert!(breaker.try_acquire().is_err());
+
+        // 試しに送ったリクエストが失敗すると再び開く
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(breaker.try_acquire().is_ok());
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+}
@@ -0,0 +1,389 @@
+use std::{fmt::Debug, ops::Deref, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use tracing::{event, Level};
+
+use crate::{
+    config::ProfanityFailurePolicy,
+    types::{
+        answer::Answer, moderation::ModerationStatus, question::Question,
+    },
+};
+use handle_errors::Error;
+pub use handle_errors::ProfanityMatch;
+
+mod api_layer;
+mod cache;
+mod circuit_breaker;
+mod recheck;
+mod wordlist;
+
+pub use api_layer::ApiLayerFilter;
+pub use cache::{CacheStats, CachedFilter};
+pub use circuit_breaker::{CircuitBreaker, CircuitState};
+pub use recheck::{recheck_pending, spawn_recheck_task};
+pub use wordlist::WordlistFilter;
+
+/// 投稿に含まれる不適切な単語の検出と伏せ字への置き換えを担当する
+#[async_trait]
+pub trait ProfanityFilter: Debug + Send + Sync {
+    /// 不適切な単語を伏せ字に置き換えた文章と、検出した単語を返す
+    async fn censor(&self, content: String) -> Result<Censored, Error>;
+
+    /// 外部のAPIを使用する場合はサーキットブレーカーの状態を返す
+    fn circuit_state(&self) -> Option<CircuitState> {
+        None
+    }
+
+    /// 結果をキャッシュする場合はその利用状況を返す
+    fn cache_stats(&self) -> Option<CacheStats> {
+        None
+    }
+}
+
+/// 不適切な単語を伏せ字に置き換えた結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Censored {
+    /// 伏せ字に置き換えた文章 (不適切な単語がなければ元の文章のまま)
+    pub content: String,
+    pub matches: Vec<ProfanityMatch>,
+}
+
+/// ルートハンドラに渡される不適切な単語のチェッカー
+///
+/// `Store` と同じく、起動時に選択したバックエンドをトレイトオブジェクトとして保持する
+#[derive(Debug, Clone)]
+pub struct ProfanityChecker {
+    filter: Arc<dyn ProfanityFilter>,
+    failure_policy: ProfanityFailurePolicy,
+    reject_threshold: Option<usize>,
+}
+
+/// チェックした結果の文章と、投稿に設定する状況
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checked {
+    pub content: String,
+    pub status: ModerationStatus,
+    /// 伏せ字に置き換えた場合は元の文章 (モデレーター向けに保存する)
+    pub original: Option<String>,
+}
+
+impl Checked {
+    /// 公開済みの質問のタイトルと本文を、チェック済みの内容として取り出す
+    ///
+    /// 公開されていない質問はチェック済みではないため `None` を返す
+    pub fn from_question(
+        question: Question,
+    ) -> Option<(Checked, Checked)> {
+        if question.moderation_status != ModerationStatus::Approved {
+            return None;
+        }
+
+        Some((
+            Checked {
+                content: question.title,
+                status: ModerationStatus::Approved,
+                original: question.original_title,
+            },
+            Checked {
+                content: question.content,
+                status: ModerationStatus::Approved,
+                original: question.original_content,
+            },
+        ))
+    }
+
+    /// 公開済みの回答の本文を、チェック済みの内容として取り出す
+    pub fn from_answer(answer: Answer) -> Option<Checked> {
+        if answer.moderation_status != ModerationStatus::Approved {
+            return None;
+        }
+
+        Some(Checked {
+            content: answer.content,
+            status: ModerationStatus::Approved,
+            original: answer.original_content,
+        })
+    }
+}
+
+impl ProfanityChecker {
+    /// チェックできなかった場合はエラーを返す (`ProfanityFailurePolicy::FailClosed`)
+    pub fn new<F: ProfanityFilter + 'static>(filter: F) -> Self {
+        ProfanityChecker {
+            filter: Arc::new(filter),
+            failure_policy: ProfanityFailurePolicy::FailClosed,
+            reject_threshold: None,
+        }
+    }
+
+    pub fn with_failure_policy(
+        self,
+        failure_policy: ProfanityFailurePolicy,
+    ) -> Self {
+        ProfanityChecker {
+            failure_policy,
+            ..self
+        }
+    }
+
+    /// チェックした結果を `capacity` 件まで `ttl` の間キャッシュする
+    pub fn with_cache(self, capacity: usize, ttl: Duration) -> Self {
+        ProfanityChecker {
+            filter: Arc::new(CachedFilter::new(
+                self.filter,
+                capacity,
+                ttl,
+            )),
+            ..self
+        }
+    }
+
+    /// 不適切な単語を `threshold` 個より多く含む投稿は伏せ字にせず拒否する
+    pub fn with_reject_threshold(self, threshold: usize) -> Self {
+        ProfanityChecker {
+            reject_threshold: Some(threshold),
+            ..self
+        }
+    }
+
+    /// 編集された投稿をチェックする
+    ///
+    /// チェック済みの内容 `stored` から変わっていない場合は問い合わせずに `stored` を返す。
+    /// 公開されていない投稿の内容はチェック済みではないため `None` を渡す
+    pub async fn check_edit(
+        &self,
+        content: String,
+        stored: Option<Checked>,
+    ) -> Result<Checked, Error> {
+        match stored {
+            Some(stored) if stored.content == content => Ok(stored),
+            _ => self.check(content).await,
+        }
+    }
+
+    /// 不適切な単語を伏せ字に置き換え、投稿に設定する状況と合わせて返す
+    ///
+    /// 外部のAPIが使えない場合は `failure_policy` に従い、
+    /// 元の文章のまま `Flagged` または `PendingModeration` として返す
+    pub async fn check(&self, content: String) -> Result<Checked, Error> {
+        let status = match self.failure_policy {
+            ProfanityFailurePolicy::FailClosed => {
+                let censored = self.censor(content.clone()).await?;
+                return self.approve(content, censored);
+            }
+            ProfanityFailurePolicy::FailOpen => ModerationStatus::Flagged,
+            ProfanityFailurePolicy::Queue => {
+                ModerationStatus::PendingModeration
+            }
+        };
+
+        match self.censor(content.clone()).await {
+            Ok(censored) => self.approve(content, censored),
+            Err(e) if is_unavailable(&e) => {
+                event!(
+                    Level::WARN,
+                    "profanity check unavailable, accepting as {}: {}",
+                    status.as_str(),
+                    e
+                );
+                Ok(Checked {
+                    content,
+                    status,
+                    original: None,
+                })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 保留中の投稿をバックグラウンドで再チェックする
+    ///
+    /// 既に受け付けた投稿は拒否できないため、`reject_threshold` を超える場合は
+    /// 伏せ字に置き換えた上で `Flagged` としてモデレーターの確認に回す
+    pub(crate) async fn recheck(
+        &self,
+        content: String,
+    ) -> Result<Checked, Error> {
+        let censored = self.censor(content.clone()).await?;
+        let status = if self.exceeds_threshold(&censored) {
+            ModerationStatus::Flagged
+        } else {
+            ModerationStatus::Approved
+        };
+
+        Ok(Checked {
+            status,
+            ..censored_content(content, censored)
+        })
+    }
+
+    fn approve(
+        &self,
+        original: String,
+        censored: Censored,
+    ) -> Result<Checked, Error> {
+        if self.exceeds_threshold(&censored) {
+            return Err(Error::ProfaneContent(censored.matches));
+        }
+
+        Ok(censored_content(original, censored))
+    }
+
+    fn exceeds_threshold(&self, censored: &Censored) -> bool {
+        self.reject_threshold
+            .is_some_and(|threshold| censored.matches.len() > threshold)
+    }
+}
+
+/// 伏せ字に置き換えた文章を、チェック済みの内容として返す
+fn censored_content(original: String, censored: Censored) -> Checked {
+    Checked {
+        original: (censored.content != original).then_some(original),
+        content: censored.content,
+        status: ModerationStatus::Approved,
+    }
+}
+
+/// 外部のAPIが使えないことによるエラーか
+fn is_unavailable(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::MiddlewareReqwestAPIError(_)
+            | Error::ReqwestAPIError(_)
+            | Error::ClientError(_)
+            | Error::ServerError(_)
+            | Error::CircuitOpen(_)
+    )
+}
+
+impl Deref for ProfanityChecker {
+    type Target = dyn ProfanityFilter;
+
+    fn deref(&self) -> &Self::Target {
+        self.filter.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod profanity_tests {
+    use super::{Checked, ProfanityChecker, WordlistFilter};
+    use crate::{
+        config::ProfanityFailurePolicy,
+        test_support::UnavailableFilter,
+        types::{
+            answer::{Answer, AnswerId},
+            moderation::ModerationStatus,
+            question::QuestionId,
+        },
+    };
+    use handle_errors::Error;
+
+    #[tokio::test]
+    async fn apply_failure_policy() {
+        let content = "damn".to_string();
+
+        let checker = ProfanityChecker::new(UnavailableFilter);
+        assert!(matches!(
+            checker.check(content.clone()).await,
+            Err(Error::CircuitOpen(30))
+        ));
+
+        let checker = ProfanityChecker::new(UnavailableFilter)
+            .with_failure_policy(ProfanityFailurePolicy::FailOpen);
+        let checked = checker.check(content.clone()).await.unwrap();
+        assert_eq!(checked.content, "damn");
+        assert_eq!(checked.status, ModerationStatus::Flagged);
+
+        let checker = ProfanityChecker::new(UnavailableFilter)
+            .with_failure_policy(ProfanityFailurePolicy::Queue);
+        let checked = checker.check(content.clone()).await.unwrap();
+        assert_eq!(checked.status, ModerationStatus::PendingModeration);
+
+        // チェックできた場合はポリシーに関係なく公開する
+        let checker =
+            ProfanityChecker::new(WordlistFilter::new(["damn"], '*'))
+                .with_failure_policy(ProfanityFailurePolicy::Queue);
+        let checked = checker.check(content).await.unwrap();
+        assert_eq!(checked.content, "****");
+        assert_eq!(checked.status, ModerationStatus::Approved);
+        assert_eq!(checked.original.as_deref(), Some("damn"));
+    }
+
+    #[tokio::test]
+    async fn reject_content_above_threshold() {
+        let checker =
+            ProfanityChecker::new(WordlistFilter::new(["damn"], '*'))
+                .with_reject_threshold(1);
+
+        let checked = checker.check("damn it".to_string()).await.unwrap();
+        assert_eq!(checked.content, "**** it");
+
+        match checker.check("damn, damn".to_string()).await {
+            Err(Error::ProfaneContent(matches)) => {
+                let spans: Vec<_> =
+                    matches.iter().map(|m| (m.start, m.end)).collect();
+                assert_eq!(spans, vec![(0, 4), (6, 10)]);
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+
+        // 不適切な単語がなければ元の文章は保存しない
+        let checked = checker.check("clean".to_string()).await.unwrap();
+        assert_eq!(checked.content, "clean");
+        assert_eq!(checked.original, None);
+    }
+
+    #[tokio::test]
+    async fn skip_unchanged_content() {
+        let checker = ProfanityChecker::new(UnavailableFilter);
+        let stored = Checked {
+            content: "**** title".to_string(),
+            status: ModerationStatus::Approved,
+            original: Some("damn title".to_string()),
+        };
+
+        // 保存済みの元の文章も引き継ぐ
+        let checked = checker
+            .check_edit("**** title".to_string(), Some(stored.clone()))
+            .await
+            .unwrap();
+        assert_eq!(checked, stored);
+
+        assert!(checker
+            .check_edit("edited".to_string(), Some(stored))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn skip_unchanged_answer() {
+        let checker = ProfanityChecker::new(UnavailableFilter);
+        let answer = Answer {
+            id: AnswerId(1),
+            content: "**** answer".to_string(),
+            question_id: QuestionId(1),
+            score: 0,
+            accepted: false,
+            moderation_status: ModerationStatus::Approved,
+            original_content: Some("damn answer".to_string()),
+        };
+
+        let checked = checker
+            .check_edit(
+                answer.content.clone(),
+                Checked::from_answer(answer.clone()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(checked.status, ModerationStatus::Approved);
+        assert_eq!(checked.original.as_deref(), Some("damn answer"));
+
+        // 公開されていない回答はチェック済みとして扱わない
+        let pending = Answer {
+            moderation_status: ModerationStatus::PendingModeration,
+            ..answer
+        };
+        assert_eq!(Checked::from_answer(pending), None);
+    }
+}
@@ -0,0 +1,168 @@
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tracing::{event, Level};
+
+use crate::{
+    profanity::ProfanityChecker,
+    store::Store,
+    types::{
+        answer::Answer, moderation::ModerationStatus, question::Question,
+    },
+};
+use handle_errors::Error;
+
+/// 保留中の投稿を一定の間隔で再チェックするタスクを起動する
+pub fn spawn_recheck_task(
+    store: Store,
+    profanity: ProfanityChecker,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match recheck_pending(&store, &profanity).await {
+                Ok(0) => {}
+                Ok(count) => {
+                    event!(Level::INFO, count, "rechecked pending posts")
+                }
+                // 外部のAPIがまだ使えない場合は次の間隔で再び試す
+                Err(e) => event!(
+                    Level::WARN,
+                    "failed to recheck pending posts: {}",
+                    e
+                ),
+            }
+        }
+    })
+}
+
+/// 保留中の投稿を再チェックし、チェックできた投稿を公開する
+///
+/// 不適切な単語が `reject_threshold` を超える投稿は、伏せ字にした上で
+/// `Flagged` としてモデレーターの確認に回す。
+/// 再チェックした件数を返す。チェックに失敗した時点で残りは次回に回す
+pub async fn recheck_pending(
+    store: &Store,
+    profanity: &ProfanityChecker,
+) -> Result<usize, Error> {
+    let queue = store.get_moderation_queue().await?;
+    let mut rechecked = 0;
+
+    for question in queue.questions.into_iter().filter(is_pending_question)
+    {
+        let (title, content) = tokio::join!(
+            profanity.recheck(question.title.clone()),
+            profanity.recheck(question.content.clone())
+        );
+        let (title, content) = (title?, content?);
+        let checked = Question {
+            moderation_status: title.status.max(content.status),
+            original_title: title.original,
+            original_content: content.original,
+            title: title.content,
+            content: content.content,
+            ..question.clone()
+        };
+
+        if store.recheck_question(&question, checked).await? {
+            rechecked += 1;
+        }
+    }
+
+    for answer in queue.answers.into_iter().filter(is_pending_answer) {
+        let content = profanity.recheck(answer.content.clone()).await?;
+        let checked = Answer {
+            moderation_status: content.status,
+            original_content: content.original,
+            content: content.content,
+            ..answer.clone()
+        };
+
+        if store.recheck_answer(&answer, checked).await? {
+            rechecked += 1;
+        }
+    }
+
+    Ok(rechecked)
+}
+
+fn is_pending_question(question: &Question) -> bool {
+    question.moderation_status == ModerationStatus::PendingModeration
+}
+
+fn is_pending_answer(answer: &Answer) -> bool {
+    answer.moderation_status == ModerationStatus::PendingModeration
+}
+
+#[cfg(test)]
+mod recheck_tests {
+    use super::recheck_pending;
+    use crate::{
+        profanity::{ProfanityChecker, WordlistFilter},
+        store::{InMemoryStore, Store},
+        types::{
+            account::AccountId, moderation::ModerationStatus,
+            question::NewQuestion,
+        },
+    };
+
+    fn pending_question(content: &str) -> NewQuestion {
+        NewQuestion {
+            title: "title".to_string(),
+            content: content.to_string(),
+            tags: None,
+            moderation_status: ModerationStatus::PendingModeration,
+            original_title: None,
+            original_content: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn approve_pending_posts() {
+        let store = Store::new(InMemoryStore::new());
+        let profanity =
+            ProfanityChecker::new(WordlistFilter::new(["damn"], '*'));
+        store
+            .add_question(pending_question("damn"), AccountId(1))
+            .await
+            .unwrap();
+
+        assert_eq!(recheck_pending(&store, &profanity).await.unwrap(), 1);
+
+        let question = store.get_question(1).await.unwrap();
+        assert_eq!(question.content, "****");
+        assert_eq!(question.original_content.as_deref(), Some("damn"));
+        assert_eq!(question.original_title, None);
+        assert_eq!(question.moderation_status, ModerationStatus::Approved);
+        assert_eq!(recheck_pending(&store, &profanity).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn flag_pending_posts_above_threshold() {
+        let store = Store::new(InMemoryStore::new());
+        let profanity =
+            ProfanityChecker::new(WordlistFilter::new(["damn"], '*'))
+                .with_reject_threshold(1);
+        store
+            .add_question(pending_question("damn, damn"), AccountId(1))
+            .await
+            .unwrap();
+
+        assert_eq!(recheck_pending(&store, &profanity).await.unwrap(), 1);
+
+        // 公開はするが伏せ字にした上でモデレーターの確認に回す
+        let question = store.get_question(1).await.unwrap();
+        assert_eq!(question.content, "****, ****");
+        assert_eq!(
+            question.original_content.as_deref(),
+            Some("damn, damn")
+        );
+        assert_eq!(question.moderation_status, ModerationStatus::Flagged);
+
+        let queue = store.get_moderation_queue().await.unwrap();
+        assert_eq!(queue.questions.len(), 1);
+        assert_eq!(recheck_pending(&store, &profanity).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,277 @@
+use std::{collections::HashSet, path::Path};
+
+use async_trait::async_trait;
+use unicode_normalization::{
+    char::is_combining_mark, UnicodeNormalization,
+};
+
+use crate::profanity::{Censored, ProfanityFilter, ProfanityMatch};
+use handle_errors::Error;
+
+/// 単語リストを指定しなかった場合に使用するリスト
+const DEFAULT_WORDLIST: &str = include_str!("../../bad_words.txt");
+
+/// 単語の一部として扱う記号 (`@ss` や `sh!t` などの置き換えに使われる)
+const LEET_SYMBOLS: &[char] = &['@', '$', '!'];
+
+/// 単語リストを使い、外部のAPIに問い合わせずに不適切な単語を検出する
+///
+/// リストは1行に1単語で、`#` から始まる行は無視する。
+/// 末尾に `*` を付けた単語はその単語から始まる全ての単語に一致する (`shit*` は `shitty` にも一致する)。
+/// 比較の前に大文字と小文字、アクセント記号、数字や記号による置き換え (`5h1t` など) を正規化する
+#[derive(Debug, Clone)]
+pub struct WordlistFilter {
+    words: HashSet<String>,
+    prefixes: Vec<String>,
+    censor_character: char,
+}
+
+impl WordlistFilter {
+    pub fn new<I, S>(entries: I, censor_character: char) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut words = HashSet::new();
+        let mut prefixes = Vec::new();
+
+        for entry in entries {
+            let entry = entry.as_ref().trim();
+            if entry.is_empty() || entry.starts_with('#') {
+                continue;
+            }
+
+            match entry.strip_suffix('*') {
+                Some(prefix) => prefixes.push(normalize(prefix)),
+                None => {
+                    words.insert(normalize(entry));
+                }
+            }
+        }
+
+        WordlistFilter {
+            words,
+            prefixes,
+            censor_character,
+        }
+    }
+
+    /// 起動時にファイルから単語リストを読み込む
+    pub fn from_file(
+        path: impl AsRef<Path>,
+        censor_character: char,
+    ) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            Error::InvalidWordlist(format!("{}: {}", path.display(), e))
+        })?;
+
+        Ok(WordlistFilter::new(text.lines(), censor_character))
+    }
+
+    /// 組み込みの単語リストを使用する
+    pub fn with_default_list(censor_character: char) -> Self {
+        WordlistFilter::new(DEFAULT_WORDLIST.lines(), censor_character)
+    }
+
+    /// 不適切な単語を伏せ字に置き換える
+    ///
+    /// 単語以外の部分 (空白や句読点) と大文字・小文字はそのまま残す
+    pub fn censor_text(&self, content: &str) -> String {
+        self.censor_content(content).content
+    }
+
+    /// 伏せ字に置き換えた文章と、検出した単語の位置を返す
+    pub fn censor_content(&self, content: &str) -> Censored {
+        let mut censored = Censored {
+            content: String::with_capacity(content.len()),
+            matches: Vec::new(),
+        };
+        // 単語の開始位置 (バイト単位と文字単位)
+        let mut word_start = None;
+
+        for (chars, (i, c)) in content.char_indices().enumerate() {
+            if is_word_char(c) {
+                word_start.get_or_insert((i, chars));
+            } else {
+                if let Some((start, start_char)) = word_start.take() {
+                    self.censor_word(
+                        &content[start..i],
+                        start_char,
+                        &mut censored,
+                    );
+                }
+                censored.content.push(c);
+            }
+        }
+
+        if let Some((start, start_char)) = word_start {
+            self.censor_word(&content[start..], start_char, &mut censored);
+        }
+
+        censored
+    }
+
+    fn censor_word(
+        &self,
+        word: &str,
+        start_char: usize,
+        censored: &mut Censored,
+    ) {
+        let is_symbol = |c: char| !c.is_alphanumeric();
+        let core = word.trim_matches(is_symbol);
+
+        if self.is_profane(word) {
+            self.push_censored(word, start_char, censored);
+        } else if core != word && self.is_profane(core) {
+            // 単語の前後の記号は句読点として残す (`damn!` は `****!` にする)
+            let start =
+                word.len() - word.trim_start_matches(is_symbol).len();
+            censored.content.push_str(&word[..start]);
+            self.push_censored(
+                core,
+                start_char + word[..start].chars().count(),
+                censored,
+            );
+            censored.content.push_str(&word[start + core.len()..]);
+        } else {
+            censored.content.push_str(word);
+        }
+    }
+
+    fn push_censored(
+        &self,
+        word: &str,
+        start_char: usize,
+        censored: &mut Censored,
+    ) {
+        let len = word.chars().filter(|c| !is_combining_mark(*c)).count();
+        censored
+            .content
+            .extend(std::iter::repeat_n(self.censor_character, len));
+        censored.matches.push(ProfanityMatch {
+            word: word.to_string(),
+            start: start_char,
+            end: start_char + word.chars().count(),
+        });
+    }
+
+    fn is_profane(&self, word: &str) -> bool {
+        let word = normalize(word);
+
+        !word.is_empty()
+            && (self.words.contains(&word)
+                || self
+                    .prefixes
+                    .iter()
+                    .any(|prefix| word.starts_with(prefix.as_str())))
+    }
+}
+
+#[async_trait]
+impl ProfanityFilter for WordlistFilter {
+    async fn censor(&self, content: String) -> Result<Censored, Error> {
+        Ok(self.censor_content(&content))
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric()
+        || is_combining_mark(c)
+        || LEET_SYMBOLS.contains(&c)
+}
+
+/// 比較のために単語を正規化する
+///
+/// アクセント記号を取り除いて小文字にし、文字の代わりに使われる数字や記号を元の文字に戻す
+fn normalize(word: &str) -> String {
+    word.nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            '0' => 'o',
+            '1' | '!' => 'i',
+            '3' => 'e',
+            '4' | '@' => 'a',
+            '5' | '$' => 's',
+            '7' => 't',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod wordlist_tests {
+    use super::WordlistFilter;
+
+    fn filter() -> WordlistFilter {
+        WordlistFilter::new(["# comment", "shit*", "damn", "", "ass"], '*')
+    }
+
+    #[test]
+    fn censor_listed_words() {
+        let filter = filter();
+
+        assert_eq!(
+            filter.censor_text("This is a shitty sentence"),
+            "This is a ****** sentence"
+        );
+        assert_eq!(filter.censor_text("Damn it, damn!"), "**** it, ****!");
+        assert_eq!(
+            filter.censor_text("this is a sentence"),
+            "this is a sentence"
+        );
+        // 前方一致を指定していない単語は他の単語の一部としては扱わない
+        assert_eq!(filter.censor_text("an assassin"), "an assassin");
+    }
+
+    #[test]
+    fn normalize_leetspeak_and_diacritics() {
+        let filter = filter();
+
+        assert_eq!(filter.censor_text("5H1T happens"), "**** happens");
+        assert_eq!(filter.censor_text("what a sh!t"), "what a ****");
+        assert_eq!(filter.censor_text("you @$$"), "you ***");
+        assert_eq!(filter.censor_text("dämn"), "****");
+        // 分解済みのアクセント記号も1文字として伏せる
+        assert_eq!(filter.censor_text("da\u{0308}mn"), "****");
+    }
+
+    #[test]
+    fn report_match_positions() {
+        let censored =
+            filter().censor_content("Oh, dämn! What a shitty day");
+
+        let matches: Vec<_> = censored
+            .matches
+            .iter()
+            .map(|m| (m.word.as_str(), m.start, m.end))
+            .collect();
+        assert_eq!(matches, vec![("dämn", 4, 8), ("shitty", 17, 23)]);
+    }
+
+    #[test]
+    fn use_configured_censor_character() {
+        let filter = WordlistFilter::new(["damn"], '#');
+
+        assert_eq!(filter.censor_text("damn"), "####");
+    }
+
+    #[test]
+    fn load_wordlist_from_file() {
+        let path = std::env::temp_dir()
+            .join(format!("wordlist-test-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "heck\nfrick*\n").unwrap();
+
+        let filter = WordlistFilter::from_file(&path, '*').unwrap();
+        assert_eq!(filter.censor_text("heck, fricking"), "****, ********");
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(WordlistFilter::from_file(&path, '*').is_err());
+        assert_eq!(
+            WordlistFilter::with_default_list('*')
+                .censor_text("This is a shitty sentence"),
+            "This is a ****** sentence"
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use chrono::Utc;
+use tracing::{event, instrument, Level};
+use warp::http::StatusCode;
+
+use crate::{
+    mailer::MailSender,
+    routes::{
+        authentication::verify_password,
+        verification::send_verification_mail,
+    },
+    store::Store,
+    types::{
+        account::{
+            validate_email, Account, AccountDeletion, AccountId,
+            EmailChange, PasswordChange, Profile, Session,
+        },
+        hashing::PasswordHashing,
+    },
+};
+
+/// ログイン中のアカウントの情報を取得する
+#[instrument]
+pub async fn get_profile(
+    session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "querying profile");
+
+    let account = store.get_account_by_id(&session.account_id).await?;
+
+    Ok(warp::reply::json(&Profile::from(account)))
+}
+
+/// パスワードを変更する
+///
+/// 使用中のトークンを含め、それまでに発行したトークンとAPIキーは全て失効させる
+pub async fn change_password(
+    session: Session,
+    store: Store,
+    hashing: PasswordHashing,
+    change: PasswordChange,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "changing password");
+
+    verify_current_password(
+        &store,
+        &session.account_id,
+        &change.current_password,
+    )
+    .await?;
+
+    let hashed_password = hashing
+        .hash(change.new_password.as_bytes())
+        .map_err(handle_errors::Error::ArgonLibraryError)?;
+    store
+        .update_password(&session.account_id, hashed_password)
+        .await?;
+    store
+        .revoke_account_tokens(&session.account_id, Utc::now())
+        .await?;
+    store.revoke_account_api_keys(&session.account_id).await?;
+
+    Ok(warp::reply::with_status("Password changed", StatusCode::OK))
+}
+
+/// メールアドレスを変更する
+///
+/// 新しいメールアドレスは未確認の状態になり、確認メールを送信する
+pub async fn change_email(
+    session: Session,
+    store: Store,
+    mailer: MailSender,
+    change: EmailChange,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "changing email");
+
+    validate_email(&change.email)?;
+    verify_current_password(&store, &session.account_id, &change.password)
+        .await?;
+
+    store
+        .update_email(&session.account_id, change.email.clone())
+        .await?;
+    send_verification_mail(
+        &store,
+        &mailer,
+        &session.account_id,
+        change.email,
+    )
+    .await?;
+
+    Ok(warp::reply::with_status("Email changed", StatusCode::OK))
+}
+
+/// アカウントを削除する
+///
+/// 質問と回答は残して所有者のいない状態にし、発行済みのトークンは全て失効させる
+pub async fn delete_account(
+    session: Session,
+    store: Store,
+    deletion: AccountDeletion,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "deleting account");
+
+    verify_current_password(
+        &store,
+        &session.account_id,
+        &deletion.password,
+    )
+    .await?;
+
+    store.delete_account(&session.account_id).await?;
+    store
+        .revoke_account_tokens(&session.account_id, Utc::now())
+        .await?;
+
+    Ok(warp::reply::with_status("Account deleted", StatusCode::OK))
+}
+
+/// 重要な変更の前に、現在のパスワードで本人であることを確認する
+async fn verify_current_password(
+    store: &Store,
+    account_id: &AccountId,
+    password: &str,
+) -> Result<Account, handle_errors::Error> {
+    let account = store.get_account_by_id(account_id).await?;
+
+    match verify_password(&account.password, password.as_bytes()) {
+        Ok(true) => Ok(account),
+        Ok(false) => Err(handle_errors::Error::WrongPassword),
+        Err(e) => Err(handle_errors::Error::ArgonLibraryError(e)),
+    }
+}
+
+#[cfg(test)]
+mod account_tests {
+    use std::env;
+
+    use warp::{hyper::body::to_bytes, Reply};
+
+    use super::{
+        change_email, change_password, delete_account, get_profile,
+    };
+    use crate::{
+        mailer::{FileMailer, MailSender},
+        routes::verification::verify_email,
+        store::{InMemoryStore, Store},
+        test_support::session,
+        types::{
+            account::{
+                Account, AccountDeletion, AccountId, EmailChange,
+                PasswordChange, Profile, Role, Session,
+            },
+            hashing::PasswordHashing,
+        },
+    };
+
+    async fn setup() -> (Store, Session) {
+        env::set_var("PASETO_KEY", "RANDOM WORDS WINTER MACINTOSH PC");
+        let store = Store::new(InMemoryStore::new());
+        store
+            .add_account(Account {
+                id: AccountId(0),
+                email: "test@example.com".to_string(),
+                password: PasswordHashing::default()
+                    .hash(b"password")
+                    .unwrap(),
+                role: Role::User,
+                email_verified: true,
+            })
+            .await
+            .unwrap();
+
+        (store, session(1, Role::User))
+    }
+
+    #[tokio::test]
+    async fn change_profile() {
+        let (store, session) = setup().await;
+
+        let wrong_password = PasswordChange {
+            current_password: "wrong".to_string(),
+            new_password: "new password".to_string(),
+        };
+        let rejection = change_password(
+            session.clone(),
+            store.clone(),
+            PasswordHashing::default(),
+            wrong_password,
+        )
+        .await
+        .err()
+        .unwrap();
+        assert!(matches!(
+            rejection.find(),
+            Some(handle_errors::Error::WrongPassword)
+        ));
+
+        let change = PasswordChange {
+            current_password: "password".to_string(),
+            new_password: "new password".to_string(),
+        };
+        change_password(
+            session.clone(),
+            store.clone(),
+            PasswordHashing::default(),
+            change,
+        )
+        .await
+        .unwrap();
+        assert!(store
+            .tokens_valid_after(&AccountId(1))
+            .await
+            .unwrap()
+            .is_some());
+
+        let path = env::temp_dir()
+            .join(format!("account-test-{}.log", uuid::Uuid::new_v4()));
+        let mailer = MailSender::new(FileMailer::new(&path));
+
+        // 現在のメールアドレスを指定しても重複にはならない
+        let unchanged = EmailChange {
+            email: "test@example.com".to_string(),
+            password: "new password".to_string(),
+        };
+        change_email(
+            session.clone(),
+            store.clone(),
+            mailer.clone(),
+            unchanged,
+        )
+        .await
+        .unwrap();
+
+        let invalid = EmailChange {
+            email: "new@example".to_string(),
+            password: "new password".to_string(),
+        };
+        let rejection = change_email(
+            session.clone(),
+            store.clone(),
+            mailer.clone(),
+            invalid,
+        )
+        .await
+        .err()
+        .unwrap();
+        assert!(matches!(
+            rejection.find(),
+            Some(handle_errors::Error::InvalidEmail(_))
+        ));
+
+        let change = EmailChange {
+            email: "new@example.com".to_string(),
+            password: "new password".to_string(),
+        };
+        change_email(session.clone(), store.clone(), mailer, change)
+            .await
+            .unwrap();
+
+        let profile = fetch_profile(session.clone(), store.clone()).await;
+        assert_eq!(profile.email, "new@example.com");
+        assert_eq!(profile.role, Role::User);
+        assert!(!profile.email_verified);
+
+        // 新しいメールアドレス宛ての確認メールで確認済みにする
+        let text = tokio::fs::read_to_string(&path).await.unwrap();
+        assert!(text.contains("To: new@example.com"));
+        let token = text
+            .rsplit("token=")
+            .next()
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap();
+        let params = [("token".to_string(), token.to_string())].into();
+        verify_email(params, store.clone()).await.unwrap();
+
+        assert!(fetch_profile(session, store).await.email_verified);
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+
+    async fn fetch_profile(session: Session, store: Store) -> Profile {
+        let res =
+            get_profile(session, store).await.unwrap().into_response();
+        let body = to_bytes(res.into_body()).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn delete_account_keeps_questions() {
+        let (store, session) = setup().await;
+        let question = store
+            .add_question(
+                crate::types::question::NewQuestion {
+                    title: "title".to_string(),
+                    content: "content".to_string(),
+                    tags: None,
+                    moderation_status: Default::default(),
+                    original_title: None,
+                    original_content: None,
+                },
+                AccountId(1),
+            )
+            .await
+            .unwrap();
+
+        let deletion = AccountDeletion {
+            password: "password".to_string(),
+        };
+        delete_account(session, store.clone(), deletion)
+            .await
+            .unwrap();
+
+        assert!(store.get_account_by_id(&AccountId(1)).await.is_err());
+        assert!(store
+            .tokens_valid_after(&AccountId(1))
+            .await
+            .unwrap()
+            .is_some());
+        assert!(store.get_question(question.id.0).await.is_ok());
+        assert!(!store
+            .is_question_owner(question.id.0, &AccountId(1))
+            .await
+            .unwrap());
+    }
+}
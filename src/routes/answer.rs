@@ -1,27 +1,92 @@
-use tracing::{event, Level};
+use std::collections::HashMap;
+use tracing::{event, instrument, Level};
 use warp::http::StatusCode;
 
 use crate::{
-    profanity::check_profanity,
+    profanity::{Checked, ProfanityChecker},
     store::Store,
-    types::{account::Session, answer::NewAnswer},
+    types::{
+        account::Session,
+        answer::{Answer, NewAnswer},
+        pagination::{
+            extract_pagination, extract_search_query, Pagination,
+        },
+    },
 };
 
+/// 質問に紐づく回答の一覧を取得する
+#[instrument]
+pub async fn get_answers(
+    question_id: i32,
+    params: HashMap<String, String>,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "querying answers");
+
+    let mut pagination = Pagination::default();
+
+    if params.contains_key("limit") || params.contains_key("offset") {
+        event!(Level::INFO, pagination = true);
+        pagination = extract_pagination(params)?;
+    }
+
+    // 存在しない質問の場合は 404 を返すために先に質問を確認する
+    store.get_question(question_id).await?;
+
+    match store
+        .get_answers(question_id, pagination.limit, pagination.offset)
+        .await
+    {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// 全ての回答を対象に全文検索する
+#[instrument]
+pub async fn search_answers(
+    params: HashMap<String, String>,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "searching answers");
+
+    let query = extract_search_query(&params)
+        .ok_or(handle_errors::Error::MissingParameters)?;
+
+    let mut pagination = Pagination::default();
+
+    if params.contains_key("limit") || params.contains_key("offset") {
+        event!(Level::INFO, pagination = true);
+        pagination = extract_pagination(params)?;
+    }
+
+    match store
+        .search_answers(query, pagination.limit, pagination.offset)
+        .await
+    {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
 pub async fn add_answer(
     session: Session,
     store: Store,
+    profanity: ProfanityChecker,
     new_answer: NewAnswer,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     event!(target: "rust-web-development", Level::INFO, "adding answers");
 
-    let content = match check_profanity(new_answer.content).await {
+    let checked = match profanity.check(new_answer.content).await {
         Ok(res) => res,
         Err(e) => return Err(warp::reject::custom(e)),
     };
 
     let answer = NewAnswer {
-        content,
+        content: checked.content,
         question_id: new_answer.question_id,
+        moderation_status: checked.status,
+        original_content: checked.original,
     };
 
     match store.add_answer(answer, session.account_id).await {
@@ -31,3 +96,59 @@ pub async fn add_answer(
         Err(e) => Err(warp::reject::custom(e)),
     }
 }
+
+pub async fn update_answer(
+    id: i32,
+    session: Session,
+    store: Store,
+    profanity: ProfanityChecker,
+    answer: Answer,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account_id = session.account_id;
+    if store.is_answer_owner(id, &account_id).await? {
+        // 変更されていない場合は改めてチェックしない
+        let stored = Checked::from_answer(store.get_answer(id).await?);
+        let checked = profanity
+            .check_edit(answer.content, stored)
+            .await
+            .map_err(warp::reject::custom)?;
+
+        let answer = Answer {
+            id: answer.id,
+            content: checked.content,
+            question_id: answer.question_id,
+            score: answer.score,
+            accepted: answer.accepted,
+            moderation_status: checked.status,
+            original_content: checked.original,
+        };
+
+        match store.update_answer(answer, id, account_id).await {
+            Ok(res) => Ok(warp::reply::json(&res)),
+            Err(e) => Err(warp::reject::custom(e)),
+        }
+    } else {
+        Err(warp::reject::custom(handle_errors::Error::Unauthorized))
+    }
+}
+
+pub async fn delete_answer(
+    id: i32,
+    session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account_id = session.account_id;
+    if !store.is_answer_owner(id, &account_id).await? {
+        return Err(warp::reject::custom(
+            handle_errors::Error::Unauthorized,
+        ));
+    }
+
+    match store.delete_answer(id, account_id).await {
+        Ok(_) => Ok(warp::reply::with_status(
+            format!("Answer {} deleted", id),
+            StatusCode::OK,
+        )),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
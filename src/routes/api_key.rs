@@ -0,0 +1,142 @@
+use tracing::{event, instrument, Level};
+use warp::http::StatusCode;
+
+use crate::{
+    routes::password::{generate_token, hash_token},
+    store::Store,
+    types::{
+        account::Session,
+        api_key::{CreatedApiKey, NewApiKey},
+    },
+};
+
+/// 一覧でキーを見分けるために保持する先頭の文字数
+const KEY_PREFIX_LENGTH: usize = 8;
+
+/// APIキーを作成する
+///
+/// キーはハッシュ化して保存するため、作成時のレスポンスでしか確認できない
+#[instrument]
+pub async fn create_api_key(
+    session: Session,
+    store: Store,
+    new_key: NewApiKey,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "creating api key");
+
+    let key = generate_token();
+    let api_key = store
+        .add_api_key(
+            &session.account_id,
+            new_key,
+            key[..KEY_PREFIX_LENGTH].to_string(),
+            hash_token(&key),
+        )
+        .await?;
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&CreatedApiKey { api_key, key }),
+        StatusCode::CREATED,
+    ))
+}
+
+/// ログイン中のアカウントのAPIキーを一覧で返す
+#[instrument]
+pub async fn get_api_keys(
+    session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "querying api keys");
+
+    let api_keys = store.get_api_keys(&session.account_id).await?;
+
+    Ok(warp::reply::json(&api_keys))
+}
+
+/// APIキーを失効させる
+#[instrument]
+pub async fn revoke_api_key(
+    id: i32,
+    session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "revoking api key");
+
+    store.revoke_api_key(id, &session.account_id).await?;
+
+    Ok(warp::reply::with_status(
+        format!("API key {} revoked", id),
+        StatusCode::OK,
+    ))
+}
+
+#[cfg(test)]
+mod api_key_tests {
+    use warp::{hyper::body::to_bytes, Reply};
+
+    use super::{create_api_key, get_api_keys, revoke_api_key};
+    use crate::{
+        store::{InMemoryStore, Store},
+        test_support::session,
+        types::{
+            account::{AccountId, Role},
+            api_key::{ApiKey, ApiKeyScope, CreatedApiKey, NewApiKey},
+        },
+    };
+    use handle_errors::Error;
+
+    #[tokio::test]
+    async fn create_list_and_revoke_api_keys() {
+        let store = Store::new(InMemoryStore::new());
+        let new_key = NewApiKey {
+            name: "bot".to_string(),
+            scope: ApiKeyScope::Write,
+        };
+
+        let res =
+            create_api_key(session(1, Role::User), store.clone(), new_key)
+                .await
+                .unwrap()
+                .into_response();
+        assert_eq!(res.status(), 201);
+        let body = to_bytes(res.into_body()).await.unwrap();
+        let created: CreatedApiKey =
+            serde_json::from_slice(&body).unwrap();
+        assert!(created.key.starts_with(&created.api_key.prefix));
+
+        let res = get_api_keys(session(1, Role::User), store.clone())
+            .await
+            .unwrap()
+            .into_response();
+        let body = to_bytes(res.into_body()).await.unwrap();
+        let api_keys: Vec<ApiKey> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(api_keys.len(), 1);
+        assert_eq!(api_keys[0].name, "bot");
+        // 一覧にはキー自体を含めない
+        assert!(!String::from_utf8_lossy(&body).contains(&created.key));
+
+        // 他のアカウントのキーは失効させられない
+        let rejection = revoke_api_key(
+            created.api_key.id,
+            session(2, Role::User),
+            store.clone(),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert!(matches!(rejection.find(), Some(Error::ApiKeyNotFound)));
+
+        revoke_api_key(
+            created.api_key.id,
+            session(1, Role::User),
+            store.clone(),
+        )
+        .await
+        .unwrap();
+        assert!(store
+            .get_api_keys(&AccountId(1))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
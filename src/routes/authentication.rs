@@ -1,77 +1,239 @@
-use std::{env, future};
+use std::{env, net::SocketAddr};
 
-use argon2::Config;
 use chrono::prelude::*;
 use hyper::StatusCode;
-use rand::Rng;
-use warp::Filter;
+use paseto::errors::GenericError;
+use tracing::{event, Level};
+use warp::{http::Method, Filter};
 
 use crate::{
+    mailer::MailSender,
+    routes::{password::hash_token, verification::send_verification_mail},
     store::Store,
-    types::account::{Account, AccountId, AuthAccount, Session},
+    types::{
+        account::{
+            validate_email, Account, AccountId, AuthAccount, RefreshToken,
+            Role, Session, TokenKind, TokenPair, API_KEY_JTI_PREFIX,
+        },
+        hashing::PasswordHashing,
+        login::{LoginAttempt, LoginPolicy},
+    },
 };
 
+/// アクセストークンの有効期間 (分)
+const ACCESS_TOKEN_MINUTES: i64 = 60;
+/// リフレッシュトークンの有効期間 (日)
+const REFRESH_TOKEN_DAYS: i64 = 30;
+
+/// アカウントを登録し、メールアドレスの確認メールを送信する
+///
+/// 確認メールは `POST /verify/resend` で送り直せるため、
+/// 送信に失敗してもアカウントは作成済みとして成功を返す
 pub async fn register(
     store: Store,
+    mailer: MailSender,
+    hashing: PasswordHashing,
     account: Account,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let hashed_password = hash_password(account.password.as_bytes());
+    validate_email(&account.email)?;
+    let hashed_password = hashing
+        .hash(account.password.as_bytes())
+        .map_err(handle_errors::Error::ArgonLibraryError)?;
 
     let account = Account {
         id: account.id,
         email: account.email,
         password: hashed_password,
+        role: Role::User,
+        email_verified: false,
     };
+    let email = account.email.clone();
 
-    match store.add_account(account).await {
-        Ok(_) => {
-            Ok(warp::reply::with_status("Account Added", StatusCode::OK))
-        }
-        Err(e) => Err(warp::reject::custom(e)),
+    store.add_account(account).await?;
+
+    let account = store.get_account(email).await?;
+    if let Err(e) =
+        send_verification_mail(&store, &mailer, &account.id, account.email)
+            .await
+    {
+        event!(Level::ERROR, "failed to send verification mail: {}", e);
     }
-}
 
-pub fn hash_password(password: &[u8]) -> String {
-    let salt = rand::thread_rng().gen::<[u8; 32]>();
-    let config = Config::default();
-    argon2::hash_encoded(password, &salt, &config).unwrap()
+    Ok(warp::reply::with_status("Account Added", StatusCode::OK))
 }
 
+/// ログインしてトークンの組を発行する
+///
+/// アカウントごととIPアドレスごとに失敗した回数を数え、
+/// 上限に達した場合はしばらくの間 429 を返す。
+/// ハッシュが現在の設定と異なるパラメータで作られていた場合は作り直す
 pub async fn login(
     store: Store,
+    policy: LoginPolicy,
+    hashing: PasswordHashing,
+    remote: Option<SocketAddr>,
     login: AuthAccount,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    match store.get_account(login.email).await {
-        Ok(account) => match verify_password(
-            &account.password,
-            login.password.as_bytes(),
-        ) {
-            Ok(verified) => {
-                if verified {
-                    Ok(warp::reply::json(&issue_token(account.id)))
-                } else {
-                    Err(warp::reject::custom(
-                        handle_errors::Error::WrongPassword,
-                    ))
+    let ip = remote.map(|addr| addr.ip().to_string());
+    check_login_attempts(&store, &policy, &login.email, ip.as_deref())
+        .await?;
+
+    let account = match store.get_account(login.email.clone()).await {
+        Ok(account) => account,
+        Err(e) => {
+            record_login_attempt(&store, login.email, ip, false).await?;
+            return Err(warp::reject::custom(e));
+        }
+    };
+
+    match verify_password(&account.password, login.password.as_bytes()) {
+        Ok(verified) => {
+            record_login_attempt(&store, login.email, ip, verified)
+                .await?;
+            if verified {
+                if hashing.needs_rehash(&account.password) {
+                    rehash_password(
+                        &store,
+                        &hashing,
+                        &account.id,
+                        login.password.as_bytes(),
+                    )
+                    .await;
                 }
+                Ok(warp::reply::json(&issue_tokens(
+                    account.id,
+                    account.role,
+                )))
+            } else {
+                Err(warp::reject::custom(
+                    handle_errors::Error::WrongPassword,
+                ))
             }
-            Err(e) => Err(warp::reject::custom(
-                handle_errors::Error::ArgonLibraryError(e),
-            )),
-        },
-        Err(e) => Err(warp::reject::custom(e)),
+        }
+        Err(e) => Err(warp::reject::custom(
+            handle_errors::Error::ArgonLibraryError(e),
+        )),
+    }
+}
+
+/// 現在のパラメータでハッシュを作り直して保存する
+///
+/// 失敗しても以前のハッシュでログインできるため、ログインは止めない
+async fn rehash_password(
+    store: &Store,
+    hashing: &PasswordHashing,
+    account_id: &AccountId,
+    password: &[u8],
+) {
+    let hashed_password = match hashing.hash(password) {
+        Ok(hashed_password) => hashed_password,
+        Err(e) => {
+            event!(Level::ERROR, "cannot upgrade password hash: {}", e);
+            return;
+        }
+    };
+
+    match store.update_password(account_id, hashed_password).await {
+        Ok(_) => event!(Level::INFO, "password hash upgraded"),
+        Err(e) => {
+            event!(Level::ERROR, "cannot upgrade password hash: {}", e)
+        }
+    }
+}
+
+/// 失敗した回数が上限に達している場合は `Error::TooManyLoginAttempts` を返す
+async fn check_login_attempts(
+    store: &Store,
+    policy: &LoginPolicy,
+    email: &str,
+    ip: Option<&str>,
+) -> Result<(), handle_errors::Error> {
+    let now = Utc::now();
+    let since = policy.window_start(now);
+
+    let failed = store.failed_logins_for_account(email, since).await?;
+    let mut retry_after =
+        policy.retry_after(&failed, policy.max_attempts, now);
+
+    if let Some(ip) = ip {
+        let failed = store.failed_logins_from_ip(ip, since).await?;
+        retry_after = retry_after.max(policy.retry_after(
+            &failed,
+            policy.max_attempts_per_ip,
+            now,
+        ));
+    }
+
+    match retry_after {
+        Some(seconds) => {
+            event!(Level::WARN, email, ip, "login is locked out");
+            Err(handle_errors::Error::TooManyLoginAttempts(seconds))
+        }
+        None => Ok(()),
     }
 }
 
-fn verify_password(
+async fn record_login_attempt(
+    store: &Store,
+    email: String,
+    ip: Option<String>,
+    succeeded: bool,
+) -> Result<bool, handle_errors::Error> {
+    store
+        .record_login_attempt(LoginAttempt {
+            email,
+            ip,
+            succeeded,
+        })
+        .await
+}
+
+pub fn verify_password(
     hash: &str,
     password: &[u8],
 ) -> Result<bool, argon2::Error> {
     argon2::verify_encoded(hash, password)
 }
 
-pub fn verify_token(
+/// リフレッシュトークンを検証し、新しいトークンの組を発行する
+///
+/// 使用したリフレッシュトークンは失効させるため、同じトークンは一度しか使えない
+pub async fn refresh(
+    store: Store,
+    refresh: RefreshToken,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let session =
+        verify_token(refresh.refresh_token, TokenKind::Refresh, &store)
+            .await?;
+
+    // 同じトークンで同時に更新された場合は、先に失効させた方だけを有効にする
+    if !store.revoke_token(session.jti, revocation_expiry()).await? {
+        return Err(warp::reject::custom(
+            handle_errors::Error::TokenRevoked,
+        ));
+    }
+
+    // 権限が変更されている場合に備えて、最新のアカウント情報から発行する
+    let account = store.get_account_by_id(&session.account_id).await?;
+
+    Ok(warp::reply::json(&issue_tokens(account.id, account.role)))
+}
+
+/// ログアウトし、アクセストークンとリフレッシュトークンを失効させる
+pub async fn logout(
+    session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    store.revoke_token(session.jti, revocation_expiry()).await?;
+
+    Ok(warp::reply::with_status("Logged out", StatusCode::OK))
+}
+
+/// トークンを復号し、種類と失効していないことを確認する
+pub async fn verify_token(
     token: String,
+    kind: TokenKind,
+    store: &Store,
 ) -> Result<Session, handle_errors::Error> {
     let key = env::var("PASETO_KEY").unwrap();
     let token = paseto::tokens::validate_local_token(
@@ -80,35 +242,234 @@ pub fn verify_token(
         key.as_bytes(),
         &paseto::tokens::TimeBackend::Chrono,
     )
-    .map_err(|_| handle_errors::Error::CannotDecryptToken)?;
+    .map_err(|e| match e.downcast_ref::<GenericError>() {
+        Some(GenericError::ExpiredToken {}) => {
+            handle_errors::Error::TokenExpired
+        }
+        _ => handle_errors::Error::CannotDecryptToken,
+    })?;
+
+    let issued_at = token
+        .get("iat")
+        .cloned()
+        .and_then(|iat| serde_json::from_value::<DateTime<Utc>>(iat).ok());
+    let session = serde_json::from_value::<Session>(token)
+        .map_err(|_| handle_errors::Error::CannotDecryptToken)?;
+
+    if session.kind != kind {
+        return Err(handle_errors::Error::CannotDecryptToken);
+    }
+
+    if store.is_token_revoked(&session.jti).await? {
+        return Err(handle_errors::Error::TokenRevoked);
+    }
+
+    // パスワードの変更や退会より前に発行したトークンは使えない
+    // `iat` を持たない以前のトークンも、その時点で失効させる
+    if let Some(valid_after) =
+        store.tokens_valid_after(&session.account_id).await?
+    {
+        if issued_at.is_none_or(|issued_at| issued_at < valid_after) {
+            return Err(handle_errors::Error::TokenRevoked);
+        }
+    }
 
-    serde_json::from_value::<Session>(token)
-        .map_err(|_| handle_errors::Error::CannotDecryptToken)
+    Ok(session)
 }
 
-fn issue_token(account_id: AccountId) -> String {
-    let key = env::var("PASETO_KEY").unwrap();
+/// 失効させたトークンIDを保持しておく期限
+///
+/// トークンIDはリフレッシュトークンと共有しているため、その有効期間だけ保持する
+pub(crate) fn revocation_expiry() -> DateTime<Utc> {
+    Utc::now() + chrono::Duration::days(REFRESH_TOKEN_DAYS)
+}
 
-    let current_date_time = Utc::now();
-    let dt = current_date_time + chrono::Duration::days(1);
+/// アクセストークンとリフレッシュトークンを同じトークンIDで発行する
+fn issue_tokens(account_id: AccountId, role: Role) -> TokenPair {
+    let jti = uuid::Uuid::new_v4().to_string();
+    let now = Utc::now();
+
+    TokenPair {
+        access_token: issue_token(
+            &account_id,
+            role,
+            &jti,
+            TokenKind::Access,
+            now + chrono::Duration::minutes(ACCESS_TOKEN_MINUTES),
+        ),
+        refresh_token: issue_token(
+            &account_id,
+            role,
+            &jti,
+            TokenKind::Refresh,
+            now + chrono::Duration::days(REFRESH_TOKEN_DAYS),
+        ),
+        expires_in: ACCESS_TOKEN_MINUTES * 60,
+    }
+}
+
+fn issue_token(
+    account_id: &AccountId,
+    role: Role,
+    jti: &str,
+    kind: TokenKind,
+    expiration: DateTime<Utc>,
+) -> String {
+    let key = env::var("PASETO_KEY").unwrap();
 
     paseto::tokens::PasetoBuilder::new()
         .set_encryption_key(&Vec::from(key.as_bytes()))
-        .set_expiration(&dt)
+        .set_expiration(&expiration)
+        .set_issued_at(None)
+        .set_jti(jti)
         .set_claim("account_id", serde_json::json!(account_id))
+        .set_claim("kind", serde_json::json!(kind))
+        .set_claim("role", serde_json::json!(role))
         .build()
         .expect("Failed to construct paseto token w/ builder!")
 }
 
+/// `Authorization` ヘッダーからトークンを取り出す
+///
+/// `Bearer <token>` の形式で送るが、以前のクライアントとの互換性のために
+/// スキームを付けずにトークンだけを送った場合も受け付ける
+fn bearer_token(
+    header: Option<String>,
+) -> Result<String, handle_errors::Error> {
+    let header = header.unwrap_or_default();
+    let header = header.trim();
+
+    match header.split_once(' ') {
+        Some((scheme, token)) if scheme.eq_ignore_ascii_case("bearer") => {
+            match token.trim() {
+                "" => Err(handle_errors::Error::MissingToken),
+                token => Ok(token.to_string()),
+            }
+        }
+        Some(_) => Err(handle_errors::Error::CannotDecryptToken),
+        None if header.is_empty()
+            || header.eq_ignore_ascii_case("bearer") =>
+        {
+            Err(handle_errors::Error::MissingToken)
+        }
+        None => Ok(header.to_string()),
+    }
+}
+
+/// `X-API-Key` ヘッダーのAPIキーを検証し、トークンと同じ `Session` を返す
+///
+/// 読み取り用のキーでは `GET` と `HEAD` 以外のリクエストを受け付けない
+async fn verify_api_key(
+    key: String,
+    method: &Method,
+    store: &Store,
+) -> Result<Session, handle_errors::Error> {
+    let api_key =
+        store.authenticate_api_key(&hash_token(key.trim())).await?;
+
+    if !api_key.scope.allows(method) {
+        return Err(handle_errors::Error::InsufficientScope);
+    }
+
+    // 権限が変更されている場合に備えて、最新のアカウント情報を使う
+    let account = store.get_account_by_id(&api_key.account_id).await?;
+
+    Ok(Session {
+        exp: Utc::now() + chrono::Duration::minutes(ACCESS_TOKEN_MINUTES),
+        account_id: account.id,
+        jti: format!("{}{}", API_KEY_JTI_PREFIX, api_key.id),
+        kind: TokenKind::Access,
+        role: account.role,
+    })
+}
+
+/// 認証が必要なルートで使用するフィルター
+///
+/// `X-API-Key` ヘッダーがある場合はAPIキーで、無い場合は `Authorization` ヘッダーの
+/// トークンで認証する。
+/// 失敗した場合は `return_error` で 401 と `WWW-Authenticate` ヘッダーを返す
 pub fn auth(
+    store: Store,
 ) -> impl Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
-    warp::header::<String>("Authorization").and_then(|token: String| {
-        let token = match verify_token(token) {
-            Ok(t) => t,
-            Err(_) => return future::ready(Err(warp::reject::reject())),
-        };
+    warp::header::optional::<String>("Authorization")
+        .and(warp::header::optional::<String>("X-API-Key"))
+        .and(warp::method())
+        .and_then(
+            move |header: Option<String>,
+                  api_key: Option<String>,
+                  method: Method| {
+                let store = store.clone();
+                async move {
+                    let session = match api_key {
+                        Some(key) => {
+                            verify_api_key(key, &method, &store).await?
+                        }
+                        None => {
+                            let token = bearer_token(header)?;
+                            verify_token(token, TokenKind::Access, &store)
+                                .await?
+                        }
+                    };
+                    Ok::<_, warp::Rejection>(session)
+                }
+            },
+        )
+}
 
-        future::ready(Ok(token))
+/// トークンで認証したリクエストのみを通すフィルター
+///
+/// ログアウトやAPIキーの管理はAPIキーでは行えないため、その場合は 403 を返す
+pub fn require_token(
+    store: Store,
+) -> impl Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
+    auth(store).and_then(|session: Session| async move {
+        if session.is_api_key() {
+            Err(warp::reject::custom(handle_errors::Error::TokenRequired))
+        } else {
+            Ok(session)
+        }
+    })
+}
+
+/// 指定した権限以上のアカウントのみを通すフィルター
+///
+/// 権限が足りない場合は 403 を返す
+pub fn require_role(
+    store: Store,
+    role: Role,
+) -> impl Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
+    auth(store).and_then(move |session: Session| async move {
+        if session.role >= role {
+            Ok(session)
+        } else {
+            Err(warp::reject::custom(handle_errors::Error::Forbidden))
+        }
+    })
+}
+
+/// 設定で求められている場合、メールアドレスを確認済みのアカウントのみを通すフィルター
+///
+/// 未確認の場合は 403 を返す
+pub fn require_verified_email(
+    store: Store,
+    required: bool,
+) -> impl Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
+    auth(store.clone()).and_then(move |session: Session| {
+        let store = store.clone();
+        async move {
+            if required
+                && !store
+                    .get_account_by_id(&session.account_id)
+                    .await?
+                    .email_verified
+            {
+                return Err(warp::reject::custom(
+                    handle_errors::Error::EmailNotVerified,
+                ));
+            }
+
+            Ok(session)
+        }
     })
 }
 
@@ -116,20 +477,588 @@ pub fn auth(
 mod authentication_tests {
     use std::env;
 
-    use super::{auth, issue_token};
-    use crate::types::account::AccountId;
+    use warp::{hyper::body::to_bytes, Reply};
+
+    use super::{
+        auth, bearer_token, issue_token, issue_tokens, login, logout,
+        refresh, register, require_role, require_token,
+        require_verified_email,
+    };
+    use crate::{
+        mailer::{FileMailer, MailSender},
+        routes::{moderation::change_role, password::hash_token},
+        store::{InMemoryStore, Store},
+        types::{
+            account::{
+                Account, AccountId, AuthAccount, NewRole, RefreshToken,
+                Role, TokenKind, TokenPair,
+            },
+            api_key::{ApiKeyScope, NewApiKey},
+            hashing::PasswordHashing,
+            login::LoginPolicy,
+        },
+    };
+    use handle_errors::Error;
+
+    fn setup() -> Store {
+        env::set_var("PASETO_KEY", "RANDOM WORDS WINTER MACINTOSH PC");
+        Store::new(InMemoryStore::new())
+    }
 
     #[tokio::test]
     async fn post_questions_auth() {
-        env::set_var("PASETO_KEY", "RANDOM WORDS WINTER MACINTOSH PC");
-        let token = issue_token(AccountId(3));
+        let store = setup();
+        let tokens = issue_tokens(AccountId(3), Role::User);
 
-        let filter = auth();
+        let filter = auth(store);
 
         let res = warp::test::request()
-            .header("Authorization", token)
+            .header(
+                "Authorization",
+                format!("Bearer {}", tokens.access_token),
+            )
             .filter(&filter);
 
         assert_eq!(res.await.unwrap().account_id, AccountId(3));
     }
+
+    #[test]
+    fn parse_authorization_header() {
+        let token = |header: &str| bearer_token(Some(header.to_string()));
+
+        assert_eq!(token("Bearer abc").unwrap(), "abc");
+        assert_eq!(token("bearer  abc ").unwrap(), "abc");
+        // 以前のクライアントはスキームを付けずに送っている
+        assert_eq!(token("abc").unwrap(), "abc");
+
+        assert!(matches!(bearer_token(None), Err(Error::MissingToken)));
+        assert!(matches!(token("Bearer "), Err(Error::MissingToken)));
+        assert!(matches!(
+            token("Basic dXNlcjpwYXNz"),
+            Err(Error::CannotDecryptToken)
+        ));
+    }
+
+    #[tokio::test]
+    async fn distinguish_token_errors() {
+        let store = setup();
+        let filter = auth(store);
+
+        let missing =
+            warp::test::request().filter(&filter).await.unwrap_err();
+        assert!(matches!(missing.find(), Some(Error::MissingToken)));
+
+        let malformed = warp::test::request()
+            .header("Authorization", "Bearer v2.local.broken")
+            .filter(&filter)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            malformed.find(),
+            Some(Error::CannotDecryptToken)
+        ));
+
+        let expired = issue_token(
+            &AccountId(3),
+            Role::User,
+            "expired",
+            TokenKind::Access,
+            chrono::Utc::now() - chrono::Duration::minutes(1),
+        );
+        let expired = warp::test::request()
+            .header("Authorization", format!("Bearer {}", expired))
+            .filter(&filter)
+            .await
+            .unwrap_err();
+        assert!(matches!(expired.find(), Some(Error::TokenExpired)));
+    }
+
+    #[tokio::test]
+    async fn authenticate_with_api_key() {
+        let store = setup();
+        store
+            .add_account(Account {
+                id: AccountId(0),
+                email: "bot@example.com".to_string(),
+                password: "password".to_string(),
+                role: Role::User,
+                email_verified: true,
+            })
+            .await
+            .unwrap();
+        let new_key = |scope| NewApiKey {
+            name: "bot".to_string(),
+            scope,
+        };
+        let read_key = store
+            .add_api_key(
+                &AccountId(1),
+                new_key(ApiKeyScope::Read),
+                "read".to_string(),
+                hash_token("read-key"),
+            )
+            .await
+            .unwrap();
+        store
+            .add_api_key(
+                &AccountId(1),
+                new_key(ApiKeyScope::Write),
+                "write".to_string(),
+                hash_token("write-key"),
+            )
+            .await
+            .unwrap();
+        let filter = auth(store.clone());
+        let request = |method: &str, key: &str| {
+            warp::test::request()
+                .method(method)
+                .header("X-API-Key", key)
+        };
+
+        let session =
+            request("GET", "read-key").filter(&filter).await.unwrap();
+        assert_eq!(session.account_id, AccountId(1));
+        assert_eq!(session.kind, TokenKind::Access);
+
+        let rejection = request("POST", "read-key")
+            .filter(&filter)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            rejection.find(),
+            Some(Error::InsufficientScope)
+        ));
+        assert!(request("POST", "write-key")
+            .filter(&filter)
+            .await
+            .is_ok());
+
+        let rejection =
+            request("GET", "unknown").filter(&filter).await.unwrap_err();
+        assert!(matches!(rejection.find(), Some(Error::InvalidApiKey)));
+
+        // ログアウトやキーの管理はAPIキーでは行えない
+        let rejection = request("POST", "write-key")
+            .filter(&require_token(store.clone()))
+            .await
+            .unwrap_err();
+        assert!(matches!(rejection.find(), Some(Error::TokenRequired)));
+
+        // 失効させたキーは使えない
+        store
+            .revoke_api_key(read_key.id, &AccountId(1))
+            .await
+            .unwrap();
+        let rejection = request("GET", "read-key")
+            .filter(&filter)
+            .await
+            .unwrap_err();
+        assert!(matches!(rejection.find(), Some(Error::InvalidApiKey)));
+    }
+
+    #[tokio::test]
+    async fn refresh_token_is_not_access_token() {
+        let store = setup();
+        let tokens = issue_tokens(AccountId(3), Role::User);
+
+        let res = warp::test::request()
+            .header("Authorization", tokens.refresh_token)
+            .filter(&auth(store))
+            .await;
+
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    async fn refresh_rotates_tokens() {
+        let store = setup();
+        store
+            .add_account(Account {
+                id: AccountId(0),
+                email: "test@example.com".to_string(),
+                password: "password".to_string(),
+                role: Role::Moderator,
+                email_verified: false,
+            })
+            .await
+            .unwrap();
+        let tokens = issue_tokens(AccountId(1), Role::User);
+        let request = RefreshToken {
+            refresh_token: tokens.refresh_token,
+        };
+
+        let res = refresh(store.clone(), request.clone())
+            .await
+            .unwrap()
+            .into_response();
+        let body = to_bytes(res.into_body()).await.unwrap();
+        let refreshed: TokenPair = serde_json::from_slice(&body).unwrap();
+
+        let session = warp::test::request()
+            .header("Authorization", refreshed.access_token)
+            .filter(&auth(store.clone()))
+            .await
+            .unwrap();
+        assert_eq!(session.account_id, AccountId(1));
+        // 権限はトークンではなくアカウントの最新の値を使う
+        assert_eq!(session.role, Role::Moderator);
+
+        // 使用済みのリフレッシュトークンと、同時に発行したアクセストークンは使えない
+        assert!(refresh(store.clone(), request).await.is_err());
+        let res = warp::test::request()
+            .header("Authorization", tokens.access_token)
+            .filter(&auth(store))
+            .await;
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    async fn logout_revokes_tokens() {
+        let store = setup();
+        let tokens = issue_tokens(AccountId(3), Role::User);
+        let filter = auth(store.clone());
+
+        let session = warp::test::request()
+            .header("Authorization", tokens.access_token.clone())
+            .filter(&filter)
+            .await
+            .unwrap();
+        logout(session, store.clone()).await.unwrap();
+
+        let res = warp::test::request()
+            .header("Authorization", tokens.access_token)
+            .filter(&filter)
+            .await;
+        assert!(res.is_err());
+
+        let request = RefreshToken {
+            refresh_token: tokens.refresh_token,
+        };
+        assert!(refresh(store, request).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn revoke_tokens_issued_before_cutoff() {
+        let store = setup();
+        let old = issue_tokens(AccountId(3), Role::User);
+        let other = issue_tokens(AccountId(4), Role::User);
+        store
+            .revoke_account_tokens(&AccountId(3), chrono::Utc::now())
+            .await
+            .unwrap();
+        let new = issue_tokens(AccountId(3), Role::User);
+        let filter = auth(store.clone());
+        let request = |token: &str| {
+            warp::test::request()
+                .header("Authorization", format!("Bearer {}", token))
+        };
+
+        let rejection = request(&old.access_token)
+            .filter(&filter)
+            .await
+            .unwrap_err();
+        assert!(matches!(rejection.find(), Some(Error::TokenRevoked)));
+        let old_refresh = RefreshToken {
+            refresh_token: old.refresh_token,
+        };
+        assert!(refresh(store, old_refresh).await.is_err());
+
+        // 後から発行したトークンと、他のアカウントのトークンは使える
+        assert!(request(&new.access_token).filter(&filter).await.is_ok());
+        assert!(request(&other.access_token)
+            .filter(&filter)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn require_moderator_role() {
+        let store = setup();
+        let filter = require_role(store, Role::Moderator);
+        let request = |role| {
+            let tokens = issue_tokens(AccountId(3), role);
+            warp::test::request().header(
+                "Authorization",
+                format!("Bearer {}", tokens.access_token),
+            )
+        };
+
+        let rejection =
+            request(Role::User).filter(&filter).await.unwrap_err();
+        assert!(matches!(rejection.find(), Some(Error::Forbidden)));
+
+        assert!(request(Role::Moderator).filter(&filter).await.is_ok());
+        assert!(request(Role::Admin).filter(&filter).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn demoted_account_loses_role() {
+        let store = setup();
+        store
+            .add_account(Account {
+                id: AccountId(0),
+                email: "admin@example.com".to_string(),
+                password: "password".to_string(),
+                role: Role::Admin,
+                email_verified: true,
+            })
+            .await
+            .unwrap();
+        let tokens = issue_tokens(AccountId(1), Role::Admin);
+        let filter = require_role(store.clone(), Role::Moderator);
+        let request = || {
+            warp::test::request().header(
+                "Authorization",
+                format!("Bearer {}", tokens.access_token),
+            )
+        };
+        let session = request().filter(&filter).await.unwrap();
+
+        change_role(
+            1,
+            session,
+            store.clone(),
+            NewRole { role: Role::User },
+        )
+        .await
+        .unwrap();
+
+        // 降格前の権限を含むトークンは使えない
+        let rejection = request().filter(&filter).await.unwrap_err();
+        assert!(matches!(rejection.find(), Some(Error::TokenRevoked)));
+        let old_refresh = RefreshToken {
+            refresh_token: tokens.refresh_token.clone(),
+        };
+        assert!(refresh(store, old_refresh).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn require_verified_email_when_configured() {
+        let store = setup();
+        store
+            .add_account(Account {
+                id: AccountId(0),
+                email: "test@example.com".to_string(),
+                password: "password".to_string(),
+                role: Role::User,
+                email_verified: false,
+            })
+            .await
+            .unwrap();
+        let tokens = issue_tokens(AccountId(1), Role::User);
+        let request = || {
+            warp::test::request().header(
+                "Authorization",
+                format!("Bearer {}", tokens.access_token),
+            )
+        };
+
+        let filter = require_verified_email(store.clone(), true);
+        let rejection = request().filter(&filter).await.unwrap_err();
+        assert!(matches!(rejection.find(), Some(Error::EmailNotVerified)));
+
+        let filter = require_verified_email(store, false);
+        assert!(request().filter(&filter).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn lock_out_after_failed_logins() {
+        let store = setup();
+        store
+            .add_account(Account {
+                id: AccountId(0),
+                email: "test@example.com".to_string(),
+                password: PasswordHashing::default()
+                    .hash(b"password")
+                    .unwrap(),
+                role: Role::User,
+                email_verified: true,
+            })
+            .await
+            .unwrap();
+        let policy = LoginPolicy {
+            max_attempts: 2,
+            ..LoginPolicy::default()
+        };
+        let remote = Some(([127, 0, 0, 1], 8080).into());
+        let attempt = |password: &str| {
+            login(
+                store.clone(),
+                policy,
+                PasswordHashing::default(),
+                remote,
+                AuthAccount {
+                    email: "test@example.com".to_string(),
+                    password: password.to_string(),
+                },
+            )
+        };
+
+        // 成功すると失敗の回数は数え直す
+        assert!(attempt("wrong").await.is_err());
+        assert!(attempt("password").await.is_ok());
+
+        for _ in 0..2 {
+            let rejection = attempt("wrong").await.err().unwrap();
+            assert!(matches!(
+                rejection.find(),
+                Some(Error::WrongPassword)
+            ));
+        }
+
+        // ロック中は正しいパスワードでもログインできない
+        let rejection = attempt("password").await.err().unwrap();
+        assert!(matches!(
+            rejection.find(),
+            Some(Error::TooManyLoginAttempts(60))
+        ));
+
+        let res = handle_errors::return_error(rejection)
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(res.status(), 429);
+        assert_eq!(res.headers()["Retry-After"], "60");
+    }
+
+    #[tokio::test]
+    async fn rehash_outdated_password_on_login() {
+        let store = setup();
+        store
+            .add_account(Account {
+                id: AccountId(0),
+                email: "test@example.com".to_string(),
+                password: PasswordHashing::default()
+                    .hash(b"password")
+                    .unwrap(),
+                role: Role::User,
+                email_verified: true,
+            })
+            .await
+            .unwrap();
+        let hashing = PasswordHashing {
+            variant: argon2::Variant::Argon2id,
+            ..PasswordHashing::default()
+        };
+
+        login(
+            store.clone(),
+            LoginPolicy::default(),
+            hashing,
+            None,
+            AuthAccount {
+                email: "test@example.com".to_string(),
+                password: "password".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+
+        let account = store
+            .get_account("test@example.com".to_string())
+            .await
+            .unwrap();
+        assert!(!hashing.needs_rehash(&account.password));
+        assert!(super::verify_password(&account.password, b"password")
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn map_account_errors_to_status_codes() {
+        let store = setup();
+        let path = env::temp_dir().join(format!(
+            "authentication-test-{}.log",
+            uuid::Uuid::new_v4()
+        ));
+        let mailer = MailSender::new(FileMailer::new(&path));
+        let account = Account {
+            id: AccountId(0),
+            email: "test@example.com".to_string(),
+            password: "password".to_string(),
+            role: Role::User,
+            email_verified: false,
+        };
+        let status = |rejection| async move {
+            handle_errors::return_error(rejection)
+                .await
+                .unwrap()
+                .into_response()
+                .status()
+        };
+
+        register(
+            store.clone(),
+            mailer.clone(),
+            PasswordHashing::default(),
+            account.clone(),
+        )
+        .await
+        .unwrap();
+        let rejection = register(
+            store.clone(),
+            mailer,
+            PasswordHashing::default(),
+            account,
+        )
+        .await
+        .err()
+        .unwrap();
+        assert!(matches!(
+            rejection.find(),
+            Some(Error::AccountAlreadyExists)
+        ));
+        assert_eq!(status(rejection).await, 409);
+
+        let rejection = login(
+            store,
+            LoginPolicy::default(),
+            PasswordHashing::default(),
+            None,
+            AuthAccount {
+                email: "unknown@example.com".to_string(),
+                password: "password".to_string(),
+            },
+        )
+        .await
+        .err()
+        .unwrap();
+        assert!(matches!(rejection.find(), Some(Error::AccountNotFound)));
+        assert_eq!(status(rejection).await, 401);
+
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn register_even_if_verification_mail_fails() {
+        let store = setup();
+        let path = env::temp_dir()
+            .join(uuid::Uuid::new_v4().to_string())
+            .join("mail.log");
+        let mailer = MailSender::new(FileMailer::new(&path));
+        let account = Account {
+            id: AccountId(0),
+            email: "test@example.com".to_string(),
+            password: "password".to_string(),
+            role: Role::User,
+            email_verified: false,
+        };
+
+        let res = register(
+            store.clone(),
+            mailer,
+            PasswordHashing::default(),
+            account,
+        )
+        .await
+        .unwrap()
+        .into_response();
+        assert_eq!(res.status(), 200);
+        assert!(!path.exists());
+
+        let account = store
+            .get_account("test@example.com".to_string())
+            .await
+            .unwrap();
+        assert!(!account.email_verified);
+    }
 }
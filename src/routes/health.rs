@@ -0,0 +1,74 @@
+use crate::{
+    profanity::{CircuitState, ProfanityChecker},
+    types::health::{Health, HealthStatus},
+};
+
+/// サービスの状態を返す
+///
+/// 外部APIへの問い合わせを止めている間は `degraded` を返す
+pub async fn health(
+    profanity: ProfanityChecker,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let profanity_circuit = profanity.circuit_state();
+    let status = match profanity_circuit {
+        Some(CircuitState::Open) => HealthStatus::Degraded,
+        _ => HealthStatus::Ok,
+    };
+
+    Ok(warp::reply::json(&Health {
+        status,
+        profanity_circuit,
+        profanity_cache: profanity.cache_stats(),
+    }))
+}
+
+#[cfg(test)]
+mod health_tests {
+    use std::time::Duration;
+
+    use warp::{hyper::body::to_bytes, Reply};
+
+    use super::health;
+    use crate::{
+        profanity::{
+            CacheStats, CircuitState, ProfanityChecker, WordlistFilter,
+        },
+        test_support::UnavailableFilter,
+        types::health::{Health, HealthStatus},
+    };
+
+    async fn fetch_health(profanity: ProfanityChecker) -> Health {
+        let res = health(profanity).await.unwrap().into_response();
+        let body = to_bytes(res.into_body()).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn report_circuit_state() {
+        let wordlist =
+            ProfanityChecker::new(WordlistFilter::new(["damn"], '*'));
+        assert_eq!(
+            fetch_health(wordlist).await,
+            Health {
+                status: HealthStatus::Ok,
+                profanity_circuit: None,
+                profanity_cache: None,
+            }
+        );
+
+        let unavailable = ProfanityChecker::new(UnavailableFilter)
+            .with_cache(10, Duration::from_secs(60));
+        assert_eq!(
+            fetch_health(unavailable).await,
+            Health {
+                status: HealthStatus::Degraded,
+                profanity_circuit: Some(CircuitState::Open),
+                profanity_cache: Some(CacheStats {
+                    hits: 0,
+                    misses: 0,
+                    entries: 0,
+                }),
+            }
+        );
+    }
+}
@@ -1,3 +1,11 @@
+pub mod account;
 pub mod answer;
+pub mod api_key;
 pub mod authentication;
+pub mod health;
+pub mod moderation;
+pub mod password;
 pub mod question;
+pub mod tag;
+pub mod verification;
+pub mod vote;
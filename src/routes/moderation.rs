@@ -0,0 +1,354 @@
+use chrono::Utc;
+use std::collections::HashMap;
+use tracing::{event, instrument, Level};
+use warp::http::StatusCode;
+
+use crate::{
+    profanity::{Checked, ProfanityChecker},
+    store::Store,
+    types::{
+        account::{AccountId, NewRole, Session},
+        answer::Answer,
+        moderation::{ModeratedAnswer, ModeratedQuestion, ModeratedQueue},
+        pagination::{extract_pagination, Pagination},
+        question::Question,
+        tag::normalize_tags,
+    },
+};
+
+/// モデレーターが質問を編集する
+///
+/// 所有者でなくても編集でき、操作は監査ログに記録される
+#[instrument]
+pub async fn moderate_question(
+    id: i32,
+    session: Session,
+    store: Store,
+    profanity: ProfanityChecker,
+    question: Question,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "moderating question");
+
+    // 変更されていない部分は改めてチェックしない
+    let (stored_title, stored_content) =
+        Checked::from_question(store.get_moderated_question(id).await?)
+            .unzip();
+    let title = profanity.check_edit(question.title, stored_title);
+    let content = profanity.check_edit(question.content, stored_content);
+
+    let (title, content) = tokio::join!(title, content);
+    let (title, content) = (title?, content?);
+
+    let question = Question {
+        id: question.id,
+        title: title.content,
+        content: content.content,
+        tags: normalize_tags(question.tags)?,
+        score: question.score,
+        moderation_status: title.status.max(content.status),
+        original_title: title.original,
+        original_content: content.original,
+    };
+
+    match store
+        .moderate_question(question, id, session.account_id)
+        .await
+    {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// 伏せ字に置き換える前の内容を含めて質問を取得する
+#[instrument]
+pub async fn get_moderated_question(
+    id: i32,
+    _session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(
+        target: "rust-web-development",
+        Level::INFO,
+        "querying question for moderation"
+    );
+
+    match store.get_moderated_question(id).await {
+        Ok(res) => Ok(warp::reply::json(&ModeratedQuestion::from(res))),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// モデレーターが質問を削除する
+#[instrument]
+pub async fn remove_question(
+    id: i32,
+    session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "removing question");
+
+    match store.remove_question(id, session.account_id).await {
+        Ok(_) => Ok(warp::reply::with_status(
+            format!("Question {} deleted", id),
+            StatusCode::OK,
+        )),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// モデレーターが回答を編集する
+#[instrument]
+pub async fn moderate_answer(
+    id: i32,
+    session: Session,
+    store: Store,
+    profanity: ProfanityChecker,
+    answer: Answer,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "moderating answer");
+
+    // 変更されていない場合は改めてチェックしない
+    let stored = Checked::from_answer(store.get_answer(id).await?);
+    let checked = profanity.check_edit(answer.content, stored).await?;
+    let answer = Answer {
+        content: checked.content,
+        moderation_status: checked.status,
+        original_content: checked.original,
+        ..answer
+    };
+
+    match store.moderate_answer(answer, id, session.account_id).await {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// 伏せ字に置き換える前の内容を含めて回答を取得する
+#[instrument]
+pub async fn get_moderated_answer(
+    id: i32,
+    _session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(
+        target: "rust-web-development",
+        Level::INFO,
+        "querying answer for moderation"
+    );
+
+    match store.get_answer(id).await {
+        Ok(res) => Ok(warp::reply::json(&ModeratedAnswer::from(res))),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// モデレーターが回答を削除する
+#[instrument]
+pub async fn remove_answer(
+    id: i32,
+    session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "removing answer");
+
+    match store.remove_answer(id, session.account_id).await {
+        Ok(_) => Ok(warp::reply::with_status(
+            format!("Answer {} deleted", id),
+            StatusCode::OK,
+        )),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// 不適切な単語のチェックが済んでいない質問と回答を取得する
+///
+/// チェックせずに公開した投稿と、再チェックを待っている投稿が含まれる
+#[instrument]
+pub async fn get_moderation_queue(
+    _session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(
+        target: "rust-web-development",
+        Level::INFO,
+        "querying moderation queue"
+    );
+
+    match store.get_moderation_queue().await {
+        Ok(res) => Ok(warp::reply::json(&ModeratedQueue::from(res))),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// 管理者がアカウントの権限を変更する
+///
+/// 変更前の権限を含むトークンを使えないよう、対象のアカウントの発行済みのトークンを
+/// 無効にする。変更後の権限は再びログインした時点から有効になる
+#[instrument]
+pub async fn change_role(
+    account_id: i32,
+    session: Session,
+    store: Store,
+    new_role: NewRole,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "changing role");
+
+    match store
+        .change_role(account_id, new_role.role, session.account_id)
+        .await
+    {
+        Ok(_) => {
+            store
+                .revoke_account_tokens(&AccountId(account_id), Utc::now())
+                .await?;
+            Ok(warp::reply::with_status(
+                format!("Role of account {} changed", account_id),
+                StatusCode::OK,
+            ))
+        }
+        // ログインでは存在を推測されないよう 401 を返すが、管理者には 404 を返す
+        Err(handle_errors::Error::AccountNotFound) => {
+            Err(warp::reject::custom(
+                handle_errors::Error::TargetAccountNotFound(account_id),
+            ))
+        }
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// 監査ログを新しい順に取得する
+#[instrument]
+pub async fn get_audit_log(
+    _session: Session,
+    params: HashMap<String, String>,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "querying audit log");
+
+    let mut pagination = Pagination::default();
+
+    if params.contains_key("limit") || params.contains_key("offset") {
+        event!(Level::INFO, pagination = true);
+        pagination = extract_pagination(params)?;
+    }
+
+    match store
+        .get_audit_log(pagination.limit, pagination.offset)
+        .await
+    {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+#[cfg(test)]
+mod moderation_tests {
+    use std::collections::HashMap;
+
+    use warp::{hyper::body::to_bytes, Reply};
+
+    use super::{change_role, get_audit_log, get_moderation_queue};
+    use crate::{
+        routes::question::get_question,
+        store::{InMemoryStore, Store},
+        test_support::session,
+        types::{
+            account::{Account, AccountId, NewRole, Role},
+            moderation::ModerationStatus,
+            question::NewQuestion,
+        },
+    };
+
+    #[tokio::test]
+    async fn expose_originals_only_to_moderators() {
+        let store = Store::new(InMemoryStore::new());
+        store
+            .add_question(
+                NewQuestion {
+                    title: "title".to_string(),
+                    content: "****".to_string(),
+                    tags: None,
+                    moderation_status: ModerationStatus::Flagged,
+                    original_title: None,
+                    original_content: Some("damn".to_string()),
+                },
+                AccountId(1),
+            )
+            .await
+            .unwrap();
+        let json = |res: warp::reply::Response| async move {
+            let body = to_bytes(res.into_body()).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+
+        let res = get_question(1, HashMap::new(), store.clone())
+            .await
+            .unwrap()
+            .into_response();
+        let question = json(res).await;
+        assert_eq!(question["content"], "****");
+        assert!(question.get("moderation_status").is_none());
+        assert!(question.get("original_content").is_none());
+
+        let session = session(9, Role::Moderator);
+        let res = get_moderation_queue(session, store)
+            .await
+            .unwrap()
+            .into_response();
+        let queue = json(res).await;
+        assert_eq!(queue["questions"][0]["moderation_status"], "flagged");
+        assert_eq!(queue["questions"][0]["original_content"], "damn");
+    }
+
+    #[tokio::test]
+    async fn change_role_of_unknown_account() {
+        let store = Store::new(InMemoryStore::new());
+        store
+            .add_account(Account {
+                id: AccountId(0),
+                email: "test@example.com".to_string(),
+                password: "password".to_string(),
+                role: Role::User,
+                email_verified: true,
+            })
+            .await
+            .unwrap();
+        let session = session(9, Role::Admin);
+        let new_role = NewRole {
+            role: Role::Moderator,
+        };
+
+        let res = change_role(1, session.clone(), store.clone(), new_role)
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(res.status(), 200);
+        let account =
+            store.get_account_by_id(&AccountId(1)).await.unwrap();
+        assert_eq!(account.role, Role::Moderator);
+
+        let new_role = NewRole { role: Role::Admin };
+        let rejection = change_role(2, session, store, new_role)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(
+            rejection.find(),
+            Some(handle_errors::Error::TargetAccountNotFound(2))
+        ));
+    }
+
+    #[tokio::test]
+    async fn get_audit_log_ignores_unrelated_params() {
+        let store = Store::new(InMemoryStore::new());
+        let session = session(9, Role::Admin);
+        let params =
+            HashMap::from([("page".to_string(), "1".to_string())]);
+
+        let res = get_audit_log(session, params, store)
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(res.status(), 200);
+    }
+}
@@ -0,0 +1,226 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use tracing::{event, Level};
+use warp::http::StatusCode;
+
+use crate::{
+    mailer::{Mail, MailSender},
+    store::Store,
+    types::{
+        account::{ForgotPassword, ResetPassword},
+        hashing::PasswordHashing,
+    },
+};
+
+/// パスワードリセット用トークンの有効期間 (分)
+const RESET_TOKEN_MINUTES: i64 = 60;
+
+/// パスワードリセット用のトークンをメールで送信する
+///
+/// 登録されているメールアドレスかどうかを推測されないよう、
+/// アカウントが存在しない場合も同じレスポンスを返す
+pub async fn forgot_password(
+    store: Store,
+    mailer: MailSender,
+    request: ForgotPassword,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(
+        target: "rust-web-development",
+        Level::INFO,
+        "password reset requested"
+    );
+
+    let reply = warp::reply::with_status(
+        "If the account exists, a password reset email has been sent",
+        StatusCode::ACCEPTED,
+    );
+
+    let account = match store.get_account(request.email).await {
+        Ok(account) => account,
+        Err(handle_errors::Error::AccountNotFound) => return Ok(reply),
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    let token = generate_token();
+    let expires_on =
+        Utc::now() + chrono::Duration::minutes(RESET_TOKEN_MINUTES);
+    store
+        .add_password_reset(&account.id, hash_token(&token), expires_on)
+        .await?;
+
+    mailer
+        .send(Mail {
+            to: account.email,
+            subject: "Reset your password".to_string(),
+            body: format!(
+                "Use the following token to reset your password:\n\n{}\n\n\
+                The token expires in {} minutes and can only be used once.",
+                token, RESET_TOKEN_MINUTES
+            ),
+        })
+        .await?;
+
+    Ok(reply)
+}
+
+/// メールで受け取ったトークンを使ってパスワードを再設定する
+///
+/// それまでに発行したトークンとAPIキーは全て失効させる
+pub async fn reset_password(
+    store: Store,
+    hashing: PasswordHashing,
+    request: ResetPassword,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "resetting password");
+
+    let hashed_password = hashing
+        .hash(request.password.as_bytes())
+        .map_err(handle_errors::Error::ArgonLibraryError)?;
+    let account_id = store
+        .reset_password(&hash_token(&request.token), hashed_password)
+        .await?;
+    store.revoke_account_tokens(&account_id, Utc::now()).await?;
+    store.revoke_account_api_keys(&account_id).await?;
+
+    Ok(warp::reply::with_status(
+        "Password has been reset",
+        StatusCode::OK,
+    ))
+}
+
+/// メールで送信するトークンを生成する
+pub(crate) fn generate_token() -> String {
+    let bytes = rand::thread_rng().gen::<[u8; 32]>();
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// トークンはハッシュ化した値のみを保存する
+pub(crate) fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod password_tests {
+    use super::{forgot_password, hash_token, reset_password};
+    use crate::{
+        mailer::{FileMailer, MailSender},
+        routes::authentication::{auth, verify_password},
+        store::{InMemoryStore, Store},
+        types::{
+            account::{
+                Account, AccountId, ForgotPassword, ResetPassword, Role,
+            },
+            api_key::{ApiKeyScope, NewApiKey},
+            hashing::PasswordHashing,
+        },
+    };
+
+    /// ファイルに書き出されたメールからトークンを取り出す
+    async fn reset_token_from(path: &std::path::Path) -> String {
+        let text = tokio::fs::read_to_string(path).await.unwrap();
+        let body = text.split("password:\n\n").nth(1).unwrap();
+        body.lines().next().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn reset_password_with_emailed_token() {
+        let store = Store::new(InMemoryStore::new());
+        store
+            .add_account(Account {
+                id: AccountId(0),
+                email: "test@example.com".to_string(),
+                password: PasswordHashing::default()
+                    .hash(b"password")
+                    .unwrap(),
+                role: Role::User,
+                email_verified: false,
+            })
+            .await
+            .unwrap();
+
+        // 盗まれたセッションで作られたキーも再設定後は使えなくする
+        store
+            .add_api_key(
+                &AccountId(1),
+                NewApiKey {
+                    name: "bot".to_string(),
+                    scope: ApiKeyScope::Write,
+                },
+                "old-key".to_string(),
+                hash_token("old-key"),
+            )
+            .await
+            .unwrap();
+
+        let path = std::env::temp_dir()
+            .join(format!("password-test-{}.log", uuid::Uuid::new_v4()));
+        let mailer = MailSender::new(FileMailer::new(&path));
+
+        // 存在しないアカウントにはメールを送らない
+        let request = ForgotPassword {
+            email: "unknown@example.com".to_string(),
+        };
+        forgot_password(store.clone(), mailer.clone(), request)
+            .await
+            .unwrap();
+        assert!(!path.exists());
+
+        let request = ForgotPassword {
+            email: "test@example.com".to_string(),
+        };
+        forgot_password(store.clone(), mailer, request)
+            .await
+            .unwrap();
+        let token = reset_token_from(&path).await;
+        assert_ne!(token, hash_token(&token));
+
+        let request = ResetPassword {
+            token: token.clone(),
+            password: "new password".to_string(),
+        };
+        reset_password(
+            store.clone(),
+            PasswordHashing::default(),
+            request.clone(),
+        )
+        .await
+        .unwrap();
+
+        let account = store
+            .get_account("test@example.com".to_string())
+            .await
+            .unwrap();
+        assert!(
+            verify_password(&account.password, b"new password").unwrap()
+        );
+        assert!(store
+            .tokens_valid_after(&account.id)
+            .await
+            .unwrap()
+            .is_some());
+        let rejection = warp::test::request()
+            .header("X-API-Key", "old-key")
+            .filter(&auth(store.clone()))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            rejection.find(),
+            Some(handle_errors::Error::InvalidApiKey)
+        ));
+
+        // 使用済みのトークンは再利用できない
+        let rejection =
+            reset_password(store, PasswordHashing::default(), request)
+                .await
+                .err()
+                .unwrap();
+        assert!(matches!(
+            rejection.find(),
+            Some(handle_errors::Error::InvalidResetToken)
+        ));
+
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+}
@@ -1,66 +1,178 @@
 use std::collections::HashMap;
 use tracing::{event, instrument, Level};
-use warp::http::StatusCode;
+use warp::{http::StatusCode, Reply};
 
 use crate::{
-    profanity::check_profanity,
+    profanity::{Checked, ProfanityChecker},
     store::Store,
     types::{
         account::Session,
-        pagination::{extract_pagination, Pagination},
-        question::{NewQuestion, Question},
+        filter::extract_question_filter,
+        pagination::{
+            check_cursor_params, check_search_params,
+            extract_cursor_pagination, extract_pagination,
+            extract_search_query, link_header, wants_envelope, Page,
+            Pagination,
+        },
+        question::{NewQuestion, Question, QuestionWithAnswers},
+        sort::extract_sort,
+        tag::normalize_tags,
     },
 };
 
+/// 質問の一覧を取得する
+///
+/// `tag` は複数指定できるため、クエリパラメータは重複を許す形で受け取る。
+/// `unanswered` と `accepted` で回答の状況による絞り込みもできる。
+/// オフセットモードでは `Link` ヘッダーを付与し、
+/// 指定があれば総件数を含むエンベロープで返す。
+/// `q` による全文検索は絞り込み・並べ替え・カーソル・エンベロープと、
+/// `cursor` は並べ替えと併用できず、併せて指定した場合は 400 を返す
 #[instrument]
 pub async fn get_questions(
-    params: HashMap<String, String>,
+    query: Vec<(String, String)>,
+    envelope_header: Option<String>,
     store: Store,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     event!(target: "rust-web-development", Level::INFO, "querying questions");
 
+    let filter = extract_question_filter(&query)?;
+    let params: HashMap<String, String> = query.iter().cloned().collect();
+    let sort = extract_sort(&params)?;
+    let search = extract_search_query(&params);
+    if search.is_some() {
+        check_search_params(&params, envelope_header.as_deref())?;
+    }
+
+    // `cursor` が指定された場合はキーセットページネーションで返す
+    // カーソルは `(created_on, id)` をキーにしているため `sort` と併用できない
+    if let Some(cursor) = extract_cursor_pagination(&params)? {
+        event!(Level::INFO, cursor = true);
+        check_cursor_params(&params)?;
+        return match store.get_questions_after(cursor, filter).await {
+            Ok(res) => Ok(warp::reply::json(&res).into_response()),
+            Err(e) => Err(warp::reject::custom(e)),
+        };
+    }
+
     let mut pagination = Pagination::default();
 
-    if !params.is_empty() {
+    if params.contains_key("limit") || params.contains_key("offset") {
         event!(Level::INFO, pagination = true);
-        pagination = extract_pagination(params)?;
+        pagination = extract_pagination(params.clone())?;
     }
 
-    match store
-        .get_questions(pagination.limit, pagination.offset)
-        .await
-    {
-        Ok(res) => Ok(warp::reply::json(&res)),
-        Err(e) => Err(warp::reject::custom(e)),
+    // `q` が指定された場合は全文検索の結果を関連度順に返す
+    // `Link` ヘッダーは付与せず、`limit` と `offset` のみ適用する
+    if let Some(search) = search {
+        event!(Level::INFO, search = true);
+        return match store
+            .search_questions(search, pagination.limit, pagination.offset)
+            .await
+        {
+            Ok(res) => Ok(warp::reply::json(&res).into_response()),
+            Err(e) => Err(warp::reject::custom(e)),
+        };
+    }
+
+    let envelope = wants_envelope(&params, envelope_header.as_deref());
+
+    // 総件数は `Link` ヘッダーかエンベロープが必要な場合のみ数える
+    let total = if envelope || pagination.limit.is_some() {
+        Some(store.count_questions(filter.clone()).await?)
+    } else {
+        None
+    };
+
+    let questions = store
+        .get_questions(pagination.limit, pagination.offset, filter, sort)
+        .await?;
+
+    let mut res = if envelope {
+        warp::reply::json(&Page {
+            items: questions,
+            total: total.unwrap_or_default(),
+            limit: pagination.limit,
+            offset: pagination.offset,
+        })
+        .into_response()
+    } else {
+        warp::reply::json(&questions).into_response()
+    };
+
+    if let Some(link) = total.and_then(|total| {
+        link_header("/questions", &query, &pagination, total)
+    }) {
+        if let Ok(value) = link.parse() {
+            res.headers_mut().insert("Link", value);
+        }
     }
+
+    Ok(res)
+}
+
+/// 質問を回答と併せて取得する
+///
+/// クエリパラメータに `limit` と `offset` を指定すると回答をページングする
+#[instrument]
+pub async fn get_question(
+    id: i32,
+    params: HashMap<String, String>,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "querying question");
+
+    let mut pagination = Pagination::default();
+
+    if params.contains_key("limit") || params.contains_key("offset") {
+        event!(Level::INFO, pagination = true);
+        pagination = extract_pagination(params)?;
+    }
+
+    let question = store.get_question(id).await?;
+    let answers = store
+        .get_answers(id, pagination.limit, pagination.offset)
+        .await?;
+
+    Ok(warp::reply::json(&QuestionWithAnswers {
+        question,
+        answers,
+    }))
 }
 
 pub async fn update_question(
     id: i32,
     session: Session,
     store: Store,
+    profanity: ProfanityChecker,
     question: Question,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     let account_id = session.account_id;
     if store.is_question_owner(id, &account_id).await? {
-        let title = check_profanity(question.title);
-        let content = check_profanity(question.content);
+        // 変更されていない部分は改めてチェックしない
+        // チェックを待っている自分の質問も編集できるよう、公開前の質問も取得する
+        let (stored_title, stored_content) = Checked::from_question(
+            store.get_moderated_question(id).await?,
+        )
+        .unzip();
+        let title = profanity.check_edit(question.title, stored_title);
+        let content =
+            profanity.check_edit(question.content, stored_content);
 
         let (title, content) = tokio::join!(title, content);
 
-        if title.is_err() {
-            return Err(warp::reject::custom(title.unwrap_err()));
-        }
-
-        if content.is_err() {
-            return Err(warp::reject::custom(content.unwrap_err()));
-        }
+        let title = title.map_err(warp::reject::custom)?;
+        let content = content.map_err(warp::reject::custom)?;
 
         let question = Question {
             id: question.id,
-            title: title.unwrap(),
-            content: content.unwrap(),
-            tags: question.tags,
+            moderation_status: title.status.max(content.status),
+            title: title.content,
+            content: content.content,
+            tags: normalize_tags(question.tags)?,
+            score: question.score,
+            original_title: title.original,
+            original_content: content.original,
         };
 
         match store.update_question(question, id, account_id).await {
@@ -72,6 +184,28 @@ pub async fn update_question(
     }
 }
 
+/// 質問に対する回答を採用する
+///
+/// 採用できるのは質問の投稿者のみ
+pub async fn accept_answer(
+    id: i32,
+    answer_id: i32,
+    session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account_id = session.account_id;
+    if !store.is_question_owner(id, &account_id).await? {
+        return Err(warp::reject::custom(
+            handle_errors::Error::Unauthorized,
+        ));
+    }
+
+    match store.accept_answer(id, answer_id, account_id).await {
+        Ok(answer) => Ok(warp::reply::json(&answer)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
 pub async fn delete_question(
     id: i32,
     session: Session,
@@ -89,22 +223,27 @@ pub async fn delete_question(
 pub async fn add_question(
     session: Session,
     store: Store,
+    profanity: ProfanityChecker,
     new_question: NewQuestion,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let title = match check_profanity(new_question.title).await {
+    let title = match profanity.check(new_question.title).await {
         Ok(res) => res,
         Err(e) => return Err(warp::reject::custom(e)),
     };
 
-    let content = match check_profanity(new_question.content).await {
+    let content = match profanity.check(new_question.content).await {
         Ok(res) => res,
         Err(e) => return Err(warp::reject::custom(e)),
     };
 
+    // タイトルと本文のうち、確認が必要な方の状況を質問に設定する
     let question = NewQuestion {
-        title,
-        content,
-        tags: new_question.tags,
+        moderation_status: title.status.max(content.status),
+        title: title.content,
+        content: content.content,
+        tags: normalize_tags(new_question.tags)?,
+        original_title: title.original,
+        original_content: content.original,
     };
 
     match store.add_question(question, session.account_id).await {
@@ -112,3 +251,197 @@ pub async fn add_question(
         Err(e) => Err(warp::reject::custom(e)),
     }
 }
+
+#[cfg(test)]
+mod question_tests {
+    use std::collections::HashMap;
+
+    use warp::{hyper::body::to_bytes, Reply};
+
+    use super::{get_question, get_questions};
+    use crate::{
+        store::{InMemoryStore, Store},
+        types::{
+            account::AccountId,
+            moderation::ModerationStatus,
+            pagination::Page,
+            question::{NewQuestion, Question, QuestionWithAnswers},
+        },
+    };
+
+    #[tokio::test]
+    async fn reject_search_with_other_params() {
+        let store = Store::new(InMemoryStore::with_seed());
+        let query = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        let res =
+            get_questions(query(&[("q", "how")]), None, store.clone())
+                .await
+                .unwrap()
+                .into_response();
+        assert_eq!(res.status(), 200);
+
+        for pairs in [
+            [("q", "how"), ("tag", "rust")],
+            [("q", "how"), ("sort", "votes")],
+            [("q", "how"), ("cursor", "")],
+        ] {
+            let rejection =
+                get_questions(query(&pairs), None, store.clone())
+                    .await
+                    .err()
+                    .unwrap();
+            let res = handle_errors::return_error(rejection)
+                .await
+                .unwrap()
+                .into_response();
+            assert_eq!(res.status(), 400);
+        }
+
+        let rejection = get_questions(
+            query(&[("q", "how")]),
+            Some("true".into()),
+            store,
+        )
+        .await
+        .err()
+        .unwrap();
+        assert!(matches!(
+            rejection.find(),
+            Some(handle_errors::Error::UnsupportedSearchParameter(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn reject_sort_with_cursor() {
+        let store = Store::new(InMemoryStore::with_seed());
+
+        for (key, value) in [("sort", "votes"), ("order", "asc")] {
+            let query = vec![
+                ("cursor".to_string(), String::new()),
+                (key.to_string(), value.to_string()),
+            ];
+            let rejection = get_questions(query, None, store.clone())
+                .await
+                .err()
+                .unwrap();
+            assert!(matches!(
+                rejection.find(),
+                Some(handle_errors::Error::UnsupportedCursorParameter(_))
+            ));
+            let res = handle_errors::return_error(rejection)
+                .await
+                .unwrap()
+                .into_response();
+            assert_eq!(res.status(), 400);
+        }
+    }
+
+    #[tokio::test]
+    async fn get_questions_from_memory_store() {
+        let store = Store::new(InMemoryStore::with_seed());
+
+        let res = get_questions(Vec::new(), None, store)
+            .await
+            .unwrap()
+            .into_response();
+        let body = to_bytes(res.into_body()).await.unwrap();
+        let questions: Vec<Question> =
+            serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(questions.len(), 1);
+        assert_eq!(questions[0].title, "How?");
+    }
+
+    #[tokio::test]
+    async fn get_question_with_answers() {
+        let store = Store::new(InMemoryStore::with_seed());
+
+        let res = get_question(1, HashMap::new(), store.clone())
+            .await
+            .unwrap()
+            .into_response();
+        let body = to_bytes(res.into_body()).await.unwrap();
+        let question: QuestionWithAnswers =
+            serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(question.question.title, "How?");
+        assert!(question.answers.is_empty());
+
+        // ページングに関係のないクエリパラメータは無視する
+        let params = [("foo".to_string(), "1".to_string())].into();
+        assert!(get_question(1, params, store.clone()).await.is_ok());
+
+        let rejection =
+            get_question(2, HashMap::new(), store).await.err().unwrap();
+        assert!(matches!(
+            rejection.find(),
+            Some(handle_errors::Error::QuestionNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn hide_pending_questions() {
+        let store = Store::new(InMemoryStore::with_seed());
+        store
+            .add_question(
+                NewQuestion {
+                    title: "unchecked".to_string(),
+                    content: "unchecked".to_string(),
+                    tags: None,
+                    moderation_status: ModerationStatus::PendingModeration,
+                    original_title: None,
+                    original_content: None,
+                },
+                AccountId(1),
+            )
+            .await
+            .unwrap();
+
+        let res = get_questions(Vec::new(), None, store.clone())
+            .await
+            .unwrap()
+            .into_response();
+        let body = to_bytes(res.into_body()).await.unwrap();
+        let questions: Vec<Question> =
+            serde_json::from_slice(&body).unwrap();
+        assert_eq!(questions.len(), 1);
+        assert_eq!(questions[0].title, "How?");
+
+        assert!(get_question(2, HashMap::new(), store.clone())
+            .await
+            .is_err());
+
+        // モデレーターからは確認できる
+        let queue = store.get_moderation_queue().await.unwrap();
+        assert_eq!(queue.questions.len(), 1);
+        assert_eq!(queue.questions[0].title, "unchecked");
+    }
+
+    #[tokio::test]
+    async fn get_questions_with_envelope() {
+        let store = Store::new(InMemoryStore::with_seed());
+        let query = vec![
+            ("limit".to_string(), "1".to_string()),
+            ("offset".to_string(), "0".to_string()),
+        ];
+
+        let res = get_questions(query, Some("true".to_string()), store)
+            .await
+            .unwrap()
+            .into_response();
+        let link = res.headers().get("Link").unwrap().to_str().unwrap();
+        assert!(link.contains(r#"rel="last""#));
+        assert!(!link.contains(r#"rel="next""#));
+
+        let body = to_bytes(res.into_body()).await.unwrap();
+        let page: Page<Question> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.items.len(), 1);
+    }
+}
@@ -0,0 +1,16 @@
+use tracing::{event, instrument, Level};
+
+use crate::store::Store;
+
+/// 使用されているタグと、それぞれの質問数を取得する
+#[instrument]
+pub async fn get_tags(
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "querying tags");
+
+    match store.get_tags().await {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
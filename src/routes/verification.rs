@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use tracing::{event, Level};
+use warp::http::StatusCode;
+
+use crate::{
+    mailer::{Mail, MailSender},
+    routes::password::{generate_token, hash_token},
+    store::Store,
+    types::account::{AccountId, Session},
+};
+
+/// メールアドレスの確認用トークンの有効期間 (時間)
+const VERIFICATION_TOKEN_HOURS: i64 = 24;
+
+/// 確認用のトークンを発行してメールで送信する
+pub(crate) async fn send_verification_mail(
+    store: &Store,
+    mailer: &MailSender,
+    account_id: &AccountId,
+    email: String,
+) -> Result<(), handle_errors::Error> {
+    let token = generate_token();
+    let expires_on =
+        Utc::now() + chrono::Duration::hours(VERIFICATION_TOKEN_HOURS);
+    store
+        .add_email_verification(account_id, hash_token(&token), expires_on)
+        .await?;
+
+    mailer
+        .send(Mail {
+            to: email,
+            subject: "Verify your email address".to_string(),
+            body: format!(
+                "Open the following URL to verify your email address:\n\n\
+                /verify?token={}\n\n\
+                The link expires in {} hours.",
+                token, VERIFICATION_TOKEN_HOURS
+            ),
+        })
+        .await
+}
+
+/// メールで受け取ったトークンでメールアドレスを確認済みにする
+/// # サンプルクエリ
+/// `/verify?token=...`
+pub async fn verify_email(
+    params: HashMap<String, String>,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "verifying email");
+
+    let token = params
+        .get("token")
+        .ok_or(handle_errors::Error::MissingParameters)?;
+    store.verify_email(&hash_token(token)).await?;
+
+    Ok(warp::reply::with_status("Email verified", StatusCode::OK))
+}
+
+/// 確認メールを送り直す
+pub async fn resend_verification(
+    session: Session,
+    store: Store,
+    mailer: MailSender,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account = store.get_account_by_id(&session.account_id).await?;
+    if account.email_verified {
+        return Ok(warp::reply::with_status(
+            "Email already verified",
+            StatusCode::OK,
+        ));
+    }
+
+    send_verification_mail(&store, &mailer, &account.id, account.email)
+        .await?;
+
+    Ok(warp::reply::with_status(
+        "Verification email sent",
+        StatusCode::ACCEPTED,
+    ))
+}
@@ -0,0 +1,76 @@
+use tracing::{event, instrument, Level};
+
+use crate::{
+    store::Store,
+    types::{
+        account::Session,
+        vote::{NewVote, VoteScore},
+    },
+};
+
+/// 質問に投票する
+///
+/// 既に投票済みの場合は投票の方向を変更する
+#[instrument]
+pub async fn vote_question(
+    id: i32,
+    session: Session,
+    store: Store,
+    vote: NewVote,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "voting question");
+
+    let score = store
+        .vote_question(id, session.account_id, vote.direction)
+        .await?;
+
+    Ok(warp::reply::json(&VoteScore { score }))
+}
+
+/// 質問への投票を取り消す
+#[instrument]
+pub async fn retract_question_vote(
+    id: i32,
+    session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "retracting vote");
+
+    let score =
+        store.retract_question_vote(id, session.account_id).await?;
+
+    Ok(warp::reply::json(&VoteScore { score }))
+}
+
+/// 回答に投票する
+///
+/// 既に投票済みの場合は投票の方向を変更する
+#[instrument]
+pub async fn vote_answer(
+    id: i32,
+    session: Session,
+    store: Store,
+    vote: NewVote,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "voting answer");
+
+    let score = store
+        .vote_answer(id, session.account_id, vote.direction)
+        .await?;
+
+    Ok(warp::reply::json(&VoteScore { score }))
+}
+
+/// 回答への投票を取り消す
+#[instrument]
+pub async fn retract_answer_vote(
+    id: i32,
+    session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(target: "rust-web-development", Level::INFO, "retracting vote");
+
+    let score = store.retract_answer_vote(id, session.account_id).await?;
+
+    Ok(warp::reply::json(&VoteScore { score }))
+}
@@ -0,0 +1,2492 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc,
+    },
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, SubsecRound, Utc};
+use tokio::sync::RwLock;
+
+use crate::{
+    store::{
+        AccountStore, AnswerStore, ApiKeyStore, EmailVerificationStore,
+        LoginAttemptStore, ModerationStore, PasswordResetStore,
+        QuestionStore, TokenStore, VoteStore,
+    },
+    types::{
+        account::{Account, AccountId, Role},
+        answer::{Answer, AnswerId, AnswerSearchResult, NewAnswer},
+        api_key::{ApiKey, NewApiKey},
+        audit::{AuditAction, AuditRecord},
+        filter::QuestionFilter,
+        login::{FailedLogins, LoginAttempt},
+        moderation::{ModerationQueue, ModerationStatus},
+        pagination::{Cursor, CursorPage, CursorPagination},
+        question::{
+            NewQuestion, Question, QuestionId, QuestionSearchResult,
+        },
+        sort::{Sort, SortKey, SortOrder},
+        tag::{TagCount, TagMode},
+        vote::VoteDirection,
+    },
+};
+use handle_errors::Error;
+
+/// データベースを用意せずに動かせるインメモリのストア
+///
+/// 主にテストやローカルでの動作確認で使用する。
+/// 複数のロックを同時に取る場合は、デッドロックしないよう
+/// `questions`、`question_meta`、`answers`、`answer_owners` や投票の順に取る
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryStore {
+    pub questions: Arc<RwLock<HashMap<QuestionId, Question>>>,
+    pub answers: Arc<RwLock<HashMap<AnswerId, Answer>>>,
+    pub accounts: Arc<RwLock<HashMap<String, Account>>>,
+    question_ids: Sequence,
+    answer_ids: Sequence,
+    account_ids: Sequence,
+    api_key_ids: Sequence,
+    audit_ids: Sequence,
+    question_meta: Arc<RwLock<HashMap<QuestionId, QuestionMeta>>>,
+    answer_owners: Arc<RwLock<HashMap<AnswerId, AccountId>>>,
+    question_votes: Arc<RwLock<HashMap<(QuestionId, AccountId), i16>>>,
+    answer_votes: Arc<RwLock<HashMap<(AnswerId, AccountId), i16>>>,
+    revoked_tokens: Arc<RwLock<HashMap<String, DateTime<Utc>>>>,
+    token_cutoffs: Arc<RwLock<HashMap<AccountId, DateTime<Utc>>>>,
+    password_resets: Arc<RwLock<HashMap<String, PendingToken>>>,
+    email_verifications: Arc<RwLock<HashMap<String, PendingToken>>>,
+    login_attempts: Arc<RwLock<Vec<LoginRecord>>>,
+    /// キーのハッシュをキーにする
+    api_keys: Arc<RwLock<HashMap<String, ApiKey>>>,
+    audit_log: Arc<RwLock<Vec<AuditRecord>>>,
+}
+
+/// PostgreSQLの SERIAL に相当するIDの採番
+///
+/// 削除した行のIDを再利用すると、投票や監査ログが無関係な行を指してしまうため、
+/// 最後に採番したIDから増やし続ける
+#[derive(Debug, Clone, Default)]
+struct Sequence(Arc<AtomicI32>);
+
+impl Sequence {
+    fn next(&self) -> i32 {
+        self.0.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// 初期データを読み込んだ場合に、そのIDの続きから採番する
+    fn skip_to(&self, last: i32) {
+        self.0.fetch_max(last, Ordering::SeqCst);
+    }
+}
+
+/// `Question` には含まれない、テーブルのカラムに相当する情報
+#[derive(Debug, Clone)]
+struct QuestionMeta {
+    /// 所有者のいない質問 (初期データや退会したアカウントの質問) は `None`
+    account_id: Option<AccountId>,
+    created_on: NaiveDateTime,
+    updated_on: NaiveDateTime,
+    accepted_answer_id: Option<AnswerId>,
+}
+
+impl QuestionMeta {
+    fn new(account_id: Option<AccountId>) -> Self {
+        let now = now();
+        QuestionMeta {
+            account_id,
+            created_on: now,
+            updated_on: now,
+            accepted_answer_id: None,
+        }
+    }
+}
+
+/// `login_attempts` テーブルの行に相当する情報
+#[derive(Debug, Clone)]
+struct LoginRecord {
+    attempt: LoginAttempt,
+    created_on: DateTime<Utc>,
+}
+
+/// `password_resets` と `email_verifications` テーブルの行に相当する情報
+#[derive(Debug, Clone)]
+struct PendingToken {
+    account_id: AccountId,
+    expires_on: DateTime<Utc>,
+}
+
+/// PostgreSQLの TIMESTAMP と同じくマイクロ秒の精度にそろえた現在時刻
+fn now() -> NaiveDateTime {
+    Utc::now().naive_utc().trunc_subsecs(6)
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        InMemoryStore::default()
+    }
+
+    /// `questions.json` の内容を初期データとして読み込む
+    pub fn with_seed() -> Self {
+        let store = InMemoryStore::new();
+        let questions = Self::init();
+
+        if let Some(last) = questions.keys().map(|id| id.0).max() {
+            store.question_ids.skip_to(last);
+        }
+
+        // 初期データはどのアカウントにも属さない
+        *store.question_meta.try_write().unwrap() = questions
+            .keys()
+            .map(|id| (id.clone(), QuestionMeta::new(None)))
+            .collect();
+        *store.questions.try_write().unwrap() = questions;
+        store
+    }
+
+    fn init() -> HashMap<QuestionId, Question> {
+        let file = include_str!("../../questions.json");
+        serde_json::from_str(file).expect("can't read questions")
+    }
+
+    /// 質問を削除し、削除できたかを返す
+    ///
+    /// PostgreSQLの ON DELETE CASCADE と同様に、回答と投票もまとめて削除する
+    async fn remove_question_entry(&self, id: &QuestionId) -> bool {
+        let removed = self.questions.write().await.remove(id).is_some();
+        self.question_meta.write().await.remove(id);
+
+        let mut answer_ids = HashSet::new();
+        self.answers.write().await.retain(|answer_id, answer| {
+            if &answer.question_id == id {
+                answer_ids.insert(answer_id.clone());
+                false
+            } else {
+                true
+            }
+        });
+        self.answer_owners
+            .write()
+            .await
+            .retain(|answer_id, _| !answer_ids.contains(answer_id));
+        self.question_votes
+            .write()
+            .await
+            .retain(|(question_id, _), _| question_id != id);
+        self.answer_votes
+            .write()
+            .await
+            .retain(|(answer_id, _), _| !answer_ids.contains(answer_id));
+
+        removed
+    }
+
+    /// 回答を削除し、削除できたかを返す
+    ///
+    /// PostgreSQLの ON DELETE CASCADE と同様に、投票もまとめて削除する
+    async fn remove_answer_entry(&self, id: &AnswerId) -> bool {
+        self.answer_owners.write().await.remove(id);
+        self.answer_votes
+            .write()
+            .await
+            .retain(|(answer_id, _), _| answer_id != id);
+
+        // PostgreSQLの ON DELETE SET NULL と同様に採用を取り消す
+        for meta in self.question_meta.write().await.values_mut() {
+            if meta.accepted_answer_id.as_ref() == Some(id) {
+                meta.accepted_answer_id = None;
+            }
+        }
+
+        self.answers.write().await.remove(id).is_some()
+    }
+
+    async fn record_audit(
+        &self,
+        account_id: AccountId,
+        action: AuditAction,
+        target_id: i32,
+    ) {
+        let mut audit_log = self.audit_log.write().await;
+        let id = self.audit_ids.next();
+        audit_log.push(AuditRecord {
+            id,
+            account_id,
+            action,
+            target_id,
+            created_on: now(),
+        });
+    }
+
+    /// 絞り込み条件に一致する公開済みの質問を返す (順序は不定)
+    async fn filter_questions(
+        &self,
+        filter: &QuestionFilter,
+    ) -> Vec<Question> {
+        let answered: HashSet<QuestionId> = self
+            .answers
+            .read()
+            .await
+            .values()
+            .filter(|a| is_public(&a.moderation_status))
+            .map(|a| a.question_id.clone())
+            .collect();
+        // `questions` より先に `question_meta` をロックしないよう、先に取り出しておく
+        let accepted: HashSet<QuestionId> = self
+            .question_meta
+            .read()
+            .await
+            .iter()
+            .filter(|(_, meta)| meta.accepted_answer_id.is_some())
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        self.questions
+            .read()
+            .await
+            .values()
+            .filter(|q| is_public(&q.moderation_status))
+            .filter(|q| has_tags(q, filter))
+            .filter(|q| match filter.unanswered {
+                Some(unanswered) => unanswered != answered.contains(&q.id),
+                None => true,
+            })
+            .filter(|q| match filter.accepted {
+                Some(wanted) => wanted == accepted.contains(&q.id),
+                None => true,
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+fn not_found() -> Error {
+    Error::DatabaseQueryError(sqlx::Error::RowNotFound)
+}
+
+/// チェックを待っている投稿は公開しない
+fn is_public(status: &ModerationStatus) -> bool {
+    status != &ModerationStatus::PendingModeration
+}
+
+fn has_tags(question: &Question, filter: &QuestionFilter) -> bool {
+    let filter = match &filter.tags {
+        Some(filter) => filter,
+        None => return true,
+    };
+
+    let question_tags = question.tags.as_deref().unwrap_or(&[]);
+    let mut wanted = filter.tags.iter();
+    match filter.mode {
+        TagMode::Any => wanted.any(|t| question_tags.contains(t)),
+        TagMode::All => wanted.all(|t| question_tags.contains(t)),
+    }
+}
+
+fn paginate<T>(items: Vec<T>, limit: Option<u32>, offset: u32) -> Vec<T> {
+    let items = items.into_iter().skip(offset as usize);
+    match limit {
+        Some(limit) => items.take(limit as usize).collect(),
+        None => items.collect(),
+    }
+}
+
+/// 検索クエリを小文字の単語に分割する
+fn search_terms(query: &str) -> Vec<String> {
+    query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// 検索語と一致した単語の数を返す
+fn count_matches(text: &str, terms: &[String]) -> usize {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| terms.contains(&word.to_lowercase()))
+        .count()
+}
+
+/// PostgreSQLの `ts_headline` と同様に、一致した単語を `<b>` で囲む
+///
+/// 投稿に含まれるHTMLがそのまま解釈されないよう、単語以外の文字はエスケープする
+fn highlight(text: &str, terms: &[String]) -> String {
+    let mut highlighted = String::with_capacity(text.len());
+    let mut word = String::new();
+
+    let flush = |word: &mut String, highlighted: &mut String| {
+        if terms.contains(&word.to_lowercase()) {
+            highlighted.push_str(&format!("<b>{}</b>", word));
+        } else {
+            highlighted.push_str(word);
+        }
+        word.clear();
+    };
+
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut highlighted);
+            match c {
+                '&' => highlighted.push_str("&amp;"),
+                '<' => highlighted.push_str("&lt;"),
+                '>' => highlighted.push_str("&gt;"),
+                '"' => highlighted.push_str("&quot;"),
+                '\'' => highlighted.push_str("&#39;"),
+                _ => highlighted.push(c),
+            }
+        }
+    }
+    flush(&mut word, &mut highlighted);
+
+    highlighted
+}
+
+#[async_trait]
+impl QuestionStore for InMemoryStore {
+    async fn get_questions(
+        &self,
+        limit: Option<u32>,
+        offset: u32,
+        filter: QuestionFilter,
+        sort: Sort,
+    ) -> Result<Vec<Question>, Error> {
+        let mut questions = self.filter_questions(&filter).await;
+
+        let meta = self.question_meta.read().await;
+        let mut answer_counts: HashMap<QuestionId, usize> = HashMap::new();
+        for answer in self
+            .answers
+            .read()
+            .await
+            .values()
+            .filter(|a| is_public(&a.moderation_status))
+        {
+            *answer_counts
+                .entry(answer.question_id.clone())
+                .or_default() += 1;
+        }
+        let dates = |q: &Question| {
+            meta.get(&q.id)
+                .map(|m| (m.created_on, m.updated_on))
+                .unwrap_or_default()
+        };
+
+        questions.sort_by(|a, b| {
+            let ordering = match sort.key {
+                SortKey::CreatedOn => dates(a).0.cmp(&dates(b).0),
+                SortKey::UpdatedOn => dates(a).1.cmp(&dates(b).1),
+                SortKey::Votes => a.score.cmp(&b.score),
+                SortKey::Answers => {
+                    answer_counts.get(&a.id).cmp(&answer_counts.get(&b.id))
+                }
+            }
+            .then(a.id.0.cmp(&b.id.0));
+
+            match sort.order {
+                SortOrder::Asc => ordering,
+                SortOrder::Desc => ordering.reverse(),
+            }
+        });
+
+        Ok(paginate(questions, limit, offset))
+    }
+
+    async fn count_questions(
+        &self,
+        filter: QuestionFilter,
+    ) -> Result<i64, Error> {
+        Ok(self.filter_questions(&filter).await.len() as i64)
+    }
+
+    async fn get_questions_after(
+        &self,
+        pagination: CursorPagination,
+        filter: QuestionFilter,
+    ) -> Result<CursorPage<Question>, Error> {
+        let questions = self.filter_questions(&filter).await;
+        let meta = self.question_meta.read().await;
+        let cursor_of = |question: &Question| Cursor {
+            created_on: meta
+                .get(&question.id)
+                .map(|m| m.created_on)
+                .unwrap_or_default(),
+            id: question.id.0,
+        };
+
+        let mut questions: Vec<(Question, Cursor)> = questions
+            .into_iter()
+            .map(|q| {
+                let cursor = cursor_of(&q);
+                (q, cursor)
+            })
+            .filter(|(_, cursor)| match &pagination.cursor {
+                Some(after) => {
+                    (cursor.created_on, cursor.id)
+                        > (after.created_on, after.id)
+                }
+                None => true,
+            })
+            .collect();
+        questions.sort_by(|(_, a), (_, b)| {
+            (a.created_on, a.id).cmp(&(b.created_on, b.id))
+        });
+
+        let has_next = questions.len() > pagination.limit as usize;
+        questions.truncate(pagination.limit as usize);
+        let next_cursor = if has_next {
+            questions.last().map(|(_, cursor)| cursor.encode())
+        } else {
+            None
+        };
+
+        Ok(CursorPage {
+            items: questions.into_iter().map(|(q, _)| q).collect(),
+            next_cursor,
+        })
+    }
+
+    async fn get_tags(&self) -> Result<Vec<TagCount>, Error> {
+        let mut counts: HashMap<String, i64> = HashMap::new();
+        for question in self
+            .questions
+            .read()
+            .await
+            .values()
+            .filter(|q| is_public(&q.moderation_status))
+        {
+            for tag in question.tags.iter().flatten() {
+                *counts.entry(tag.clone()).or_default() += 1;
+            }
+        }
+
+        let mut tags: Vec<TagCount> = counts
+            .into_iter()
+            .map(|(name, count)| TagCount { name, count })
+            .collect();
+        tags.sort_by(|a, b| {
+            b.count.cmp(&a.count).then(a.name.cmp(&b.name))
+        });
+
+        Ok(tags)
+    }
+
+    async fn search_questions(
+        &self,
+        query: String,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<QuestionSearchResult>, Error> {
+        let terms = search_terms(&query);
+
+        let mut results: Vec<QuestionSearchResult> = self
+            .questions
+            .read()
+            .await
+            .values()
+            .filter(|q| is_public(&q.moderation_status))
+            .filter_map(|question| {
+                // タイトルでの一致を本文よりも重く評価する
+                let matches = count_matches(&question.title, &terms) * 2
+                    + count_matches(&question.content, &terms);
+                (matches > 0).then(|| QuestionSearchResult {
+                    question: question.clone(),
+                    rank: matches as f32,
+                    title_highlight: highlight(&question.title, &terms),
+                    content_highlight: highlight(
+                        &question.content,
+                        &terms,
+                    ),
+                })
+            })
+            .collect();
+        results.sort_by(|a, b| {
+            b.rank
+                .total_cmp(&a.rank)
+                .then(a.question.id.0.cmp(&b.question.id.0))
+        });
+
+        Ok(paginate(results, limit, offset))
+    }
+
+    async fn get_question(
+        &self,
+        question_id: i32,
+    ) -> Result<Question, Error> {
+        self.questions
+            .read()
+            .await
+            .get(&QuestionId(question_id))
+            .filter(|q| is_public(&q.moderation_status))
+            .cloned()
+            .ok_or(Error::QuestionNotFound)
+    }
+
+    async fn add_question(
+        &self,
+        new_question: NewQuestion,
+        account_id: AccountId,
+    ) -> Result<Question, Error> {
+        let mut questions = self.questions.write().await;
+        let id = QuestionId(self.question_ids.next());
+
+        let question = Question {
+            id: id.clone(),
+            title: new_question.title,
+            content: new_question.content,
+            tags: new_question.tags,
+            score: 0,
+            moderation_status: new_question.moderation_status,
+            original_title: new_question.original_title,
+            original_content: new_question.original_content,
+        };
+        questions.insert(id.clone(), question.clone());
+        self.question_meta
+            .write()
+            .await
+            .insert(id, QuestionMeta::new(Some(account_id)));
+
+        Ok(question)
+    }
+
+    async fn update_question(
+        &self,
+        question: Question,
+        question_id: i32,
+        account_id: AccountId,
+    ) -> Result<Question, Error> {
+        if !self.is_question_owner(question_id, &account_id).await? {
+            return Err(not_found());
+        }
+
+        let id = QuestionId(question_id);
+        let mut questions = self.questions.write().await;
+        let stored = questions.get_mut(&id).ok_or_else(not_found)?;
+        stored.title = question.title;
+        stored.content = question.content;
+        stored.tags = question.tags;
+        stored.moderation_status = question.moderation_status;
+        stored.original_title = question.original_title;
+        stored.original_content = question.original_content;
+
+        if let Some(meta) = self.question_meta.write().await.get_mut(&id) {
+            meta.updated_on = now();
+        }
+
+        Ok(stored.clone())
+    }
+
+    async fn delete_question(
+        &self,
+        question_id: i32,
+        account_id: AccountId,
+    ) -> Result<bool, Error> {
+        if self.is_question_owner(question_id, &account_id).await? {
+            self.remove_question_entry(&QuestionId(question_id)).await;
+        }
+
+        Ok(true)
+    }
+
+    async fn is_question_owner(
+        &self,
+        question_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        Ok(self
+            .question_meta
+            .read()
+            .await
+            .get(&QuestionId(question_id))
+            .and_then(|meta| meta.account_id.as_ref())
+            == Some(account_id))
+    }
+
+    async fn accept_answer(
+        &self,
+        question_id: i32,
+        answer_id: i32,
+        account_id: AccountId,
+    ) -> Result<Answer, Error> {
+        let id = QuestionId(question_id);
+        let questions = self.questions.read().await;
+        if !questions
+            .get(&id)
+            .is_some_and(|q| is_public(&q.moderation_status))
+        {
+            return Err(Error::AnswerNotFound);
+        }
+
+        let mut meta = self.question_meta.write().await;
+        let meta = match meta.get_mut(&id) {
+            Some(meta)
+                if meta.account_id.as_ref() == Some(&account_id) =>
+            {
+                meta
+            }
+            _ => return Err(Error::AnswerNotFound),
+        };
+
+        let mut answers = self.answers.write().await;
+        if !answers.get(&AnswerId(answer_id)).is_some_and(|a| {
+            a.question_id == id && is_public(&a.moderation_status)
+        }) {
+            return Err(Error::AnswerNotFound);
+        }
+
+        // 採用できる回答は1つだけなので、以前に採用した回答は解除する
+        for answer in answers.values_mut().filter(|a| a.question_id == id)
+        {
+            answer.accepted = answer.id.0 == answer_id;
+        }
+        meta.accepted_answer_id = Some(AnswerId(answer_id));
+
+        Ok(answers[&AnswerId(answer_id)].clone())
+    }
+}
+
+#[async_trait]
+impl AnswerStore for InMemoryStore {
+    async fn get_answer(&self, answer_id: i32) -> Result<Answer, Error> {
+        self.answers
+            .read()
+            .await
+            .get(&AnswerId(answer_id))
+            .cloned()
+            .ok_or(Error::AnswerNotFound)
+    }
+
+    async fn get_answers(
+        &self,
+        question_id: i32,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<Answer>, Error> {
+        let mut answers: Vec<Answer> = self
+            .answers
+            .read()
+            .await
+            .values()
+            .filter(|a| a.question_id.0 == question_id)
+            .filter(|a| is_public(&a.moderation_status))
+            .cloned()
+            .collect();
+        answers.sort_by_key(|a| a.id.0);
+
+        Ok(paginate(answers, limit, offset))
+    }
+
+    async fn search_answers(
+        &self,
+        query: String,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<AnswerSearchResult>, Error> {
+        let terms = search_terms(&query);
+        // 公開前の質問への回答も返さないよう、先に質問のロックを取る
+        let hidden: HashSet<QuestionId> = self
+            .questions
+            .read()
+            .await
+            .values()
+            .filter(|q| !is_public(&q.moderation_status))
+            .map(|q| q.id.clone())
+            .collect();
+
+        let mut results: Vec<AnswerSearchResult> = self
+            .answers
+            .read()
+            .await
+            .values()
+            .filter(|a| is_public(&a.moderation_status))
+            .filter(|a| !hidden.contains(&a.question_id))
+            .filter_map(|answer| {
+                let matches = count_matches(&answer.content, &terms);
+                (matches > 0).then(|| AnswerSearchResult {
+                    answer: answer.clone(),
+                    rank: matches as f32,
+                    content_highlight: highlight(&answer.content, &terms),
+                })
+            })
+            .collect();
+        results.sort_by(|a, b| {
+            b.rank
+                .total_cmp(&a.rank)
+                .then(a.answer.id.0.cmp(&b.answer.id.0))
+        });
+
+        Ok(paginate(results, limit, offset))
+    }
+
+    async fn add_answer(
+        &self,
+        new_answer: NewAnswer,
+        account_id: AccountId,
+    ) -> Result<Answer, Error> {
+        if !self
+            .questions
+            .read()
+            .await
+            .get(&new_answer.question_id)
+            .is_some_and(|q| is_public(&q.moderation_status))
+        {
+            return Err(Error::QuestionNotFound);
+        }
+
+        let mut answers = self.answers.write().await;
+        let id = AnswerId(self.answer_ids.next());
+
+        let answer = Answer {
+            id: id.clone(),
+            content: new_answer.content,
+            question_id: new_answer.question_id,
+            score: 0,
+            accepted: false,
+            moderation_status: new_answer.moderation_status,
+            original_content: new_answer.original_content,
+        };
+        answers.insert(id.clone(), answer.clone());
+        self.answer_owners.write().await.insert(id, account_id);
+
+        Ok(answer)
+    }
+
+    async fn update_answer(
+        &self,
+        answer: Answer,
+        answer_id: i32,
+        account_id: AccountId,
+    ) -> Result<Answer, Error> {
+        if !self.is_answer_owner(answer_id, &account_id).await? {
+            return Err(not_found());
+        }
+
+        let mut answers = self.answers.write().await;
+        let stored = answers
+            .get_mut(&AnswerId(answer_id))
+            .ok_or_else(not_found)?;
+        stored.content = answer.content;
+        stored.moderation_status = answer.moderation_status;
+        stored.original_content = answer.original_content;
+
+        Ok(stored.clone())
+    }
+
+    async fn delete_answer(
+        &self,
+        answer_id: i32,
+        account_id: AccountId,
+    ) -> Result<bool, Error> {
+        if self.is_answer_owner(answer_id, &account_id).await? {
+            self.remove_answer_entry(&AnswerId(answer_id)).await;
+        }
+
+        Ok(true)
+    }
+
+    async fn is_answer_owner(
+        &self,
+        answer_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        Ok(self.answer_owners.read().await.get(&AnswerId(answer_id))
+            == Some(account_id))
+    }
+}
+
+/// 投票を反映し、その対象への投票の合計値を返す
+///
+/// `direction` が `None` の場合は投票を取り消す
+fn apply_vote<K: Eq + std::hash::Hash + Clone>(
+    votes: &mut HashMap<(K, AccountId), i16>,
+    id: &K,
+    account_id: AccountId,
+    direction: Option<VoteDirection>,
+) -> i64 {
+    let key = (id.clone(), account_id);
+    match direction {
+        Some(direction) => votes.insert(key, direction.value()),
+        None => votes.remove(&key),
+    };
+
+    votes
+        .iter()
+        .filter(|((target, _), _)| target == id)
+        .map(|(_, value)| i64::from(*value))
+        .sum()
+}
+
+impl InMemoryStore {
+    async fn set_question_vote(
+        &self,
+        question_id: i32,
+        account_id: AccountId,
+        direction: Option<VoteDirection>,
+    ) -> Result<i64, Error> {
+        let id = QuestionId(question_id);
+        let mut questions = self.questions.write().await;
+        let question = questions
+            .get_mut(&id)
+            .filter(|q| is_public(&q.moderation_status))
+            .ok_or(Error::QuestionNotFound)?;
+
+        let mut votes = self.question_votes.write().await;
+        question.score =
+            apply_vote(&mut votes, &id, account_id, direction);
+
+        Ok(question.score)
+    }
+
+    async fn set_answer_vote(
+        &self,
+        answer_id: i32,
+        account_id: AccountId,
+        direction: Option<VoteDirection>,
+    ) -> Result<i64, Error> {
+        let id = AnswerId(answer_id);
+        let mut answers = self.answers.write().await;
+        let answer = answers
+            .get_mut(&id)
+            .filter(|a| is_public(&a.moderation_status))
+            .ok_or(Error::AnswerNotFound)?;
+
+        let mut votes = self.answer_votes.write().await;
+        answer.score = apply_vote(&mut votes, &id, account_id, direction);
+
+        Ok(answer.score)
+    }
+}
+
+#[async_trait]
+impl VoteStore for InMemoryStore {
+    async fn vote_question(
+        &self,
+        question_id: i32,
+        account_id: AccountId,
+        direction: VoteDirection,
+    ) -> Result<i64, Error> {
+        self.set_question_vote(question_id, account_id, Some(direction))
+            .await
+    }
+
+    async fn retract_question_vote(
+        &self,
+        question_id: i32,
+        account_id: AccountId,
+    ) -> Result<i64, Error> {
+        self.set_question_vote(question_id, account_id, None).await
+    }
+
+    async fn vote_answer(
+        &self,
+        answer_id: i32,
+        account_id: AccountId,
+        direction: VoteDirection,
+    ) -> Result<i64, Error> {
+        self.set_answer_vote(answer_id, account_id, Some(direction))
+            .await
+    }
+
+    async fn retract_answer_vote(
+        &self,
+        answer_id: i32,
+        account_id: AccountId,
+    ) -> Result<i64, Error> {
+        self.set_answer_vote(answer_id, account_id, None).await
+    }
+}
+
+#[async_trait]
+impl AccountStore for InMemoryStore {
+    async fn add_account(&self, account: Account) -> Result<bool, Error> {
+        let mut accounts = self.accounts.write().await;
+
+        if accounts.contains_key(&account.email) {
+            return Err(Error::AccountAlreadyExists);
+        }
+
+        let id = AccountId(self.account_ids.next());
+        accounts.insert(
+            account.email.clone(),
+            Account {
+                id,
+                email: account.email,
+                password: account.password,
+                role: account.role,
+                email_verified: account.email_verified,
+            },
+        );
+
+        Ok(true)
+    }
+
+    async fn get_account(&self, email: String) -> Result<Account, Error> {
+        self.accounts
+            .read()
+            .await
+            .get(&email)
+            .cloned()
+            .ok_or(Error::AccountNotFound)
+    }
+
+    async fn get_account_by_id(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Account, Error> {
+        self.accounts
+            .read()
+            .await
+            .values()
+            .find(|account| &account.id == account_id)
+            .cloned()
+            .ok_or(Error::AccountNotFound)
+    }
+
+    async fn update_password(
+        &self,
+        account_id: &AccountId,
+        password: String,
+    ) -> Result<bool, Error> {
+        let mut accounts = self.accounts.write().await;
+        let account = accounts
+            .values_mut()
+            .find(|account| &account.id == account_id)
+            .ok_or(Error::AccountNotFound)?;
+        account.password = password;
+
+        Ok(true)
+    }
+
+    async fn update_email(
+        &self,
+        account_id: &AccountId,
+        email: String,
+    ) -> Result<bool, Error> {
+        let mut accounts = self.accounts.write().await;
+
+        // PostgreSQLと同じく、現在のメールアドレスを指定した場合は重複として扱わない
+        if accounts
+            .get(&email)
+            .is_some_and(|account| &account.id != account_id)
+        {
+            return Err(Error::AccountAlreadyExists);
+        }
+
+        // メールアドレスをキーにしているため、入れ替えて登録し直す
+        let old_email = accounts
+            .values()
+            .find(|account| &account.id == account_id)
+            .map(|account| account.email.clone())
+            .ok_or(Error::AccountNotFound)?;
+        let mut account =
+            accounts.remove(&old_email).ok_or(Error::AccountNotFound)?;
+        account.email = email.clone();
+        account.email_verified = false;
+        accounts.insert(email, account);
+
+        Ok(true)
+    }
+
+    async fn delete_account(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        {
+            let mut accounts = self.accounts.write().await;
+            let email = accounts
+                .values()
+                .find(|account| &account.id == account_id)
+                .map(|account| account.email.clone())
+                .ok_or(Error::AccountNotFound)?;
+            accounts.remove(&email);
+        }
+
+        for meta in self.question_meta.write().await.values_mut() {
+            if meta.account_id.as_ref() == Some(account_id) {
+                meta.account_id = None;
+            }
+        }
+        self.answer_owners
+            .write()
+            .await
+            .retain(|_, owner| owner != account_id);
+        self.password_resets
+            .write()
+            .await
+            .retain(|_, reset| &reset.account_id != account_id);
+        self.email_verifications
+            .write()
+            .await
+            .retain(|_, pending| &pending.account_id != account_id);
+        self.api_keys
+            .write()
+            .await
+            .retain(|_, api_key| &api_key.account_id != account_id);
+
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl ModerationStore for InMemoryStore {
+    async fn get_moderated_question(
+        &self,
+        question_id: i32,
+    ) -> Result<Question, Error> {
+        self.questions
+            .read()
+            .await
+            .get(&QuestionId(question_id))
+            .cloned()
+            .ok_or(Error::QuestionNotFound)
+    }
+
+    async fn moderate_question(
+        &self,
+        question: Question,
+        question_id: i32,
+        moderator: AccountId,
+    ) -> Result<Question, Error> {
+        let id = QuestionId(question_id);
+        let updated = {
+            let mut questions = self.questions.write().await;
+            let stored =
+                questions.get_mut(&id).ok_or(Error::QuestionNotFound)?;
+            stored.title = question.title;
+            stored.content = question.content;
+            stored.tags = question.tags;
+            stored.moderation_status = question.moderation_status;
+            stored.original_title = question.original_title;
+            stored.original_content = question.original_content;
+            stored.clone()
+        };
+        if let Some(meta) = self.question_meta.write().await.get_mut(&id) {
+            meta.updated_on = now();
+        }
+
+        self.record_audit(
+            moderator,
+            AuditAction::UpdateQuestion,
+            question_id,
+        )
+        .await;
+
+        Ok(updated)
+    }
+
+    async fn remove_question(
+        &self,
+        question_id: i32,
+        moderator: AccountId,
+    ) -> Result<bool, Error> {
+        if !self.remove_question_entry(&QuestionId(question_id)).await {
+            return Err(Error::QuestionNotFound);
+        }
+
+        self.record_audit(
+            moderator,
+            AuditAction::DeleteQuestion,
+            question_id,
+        )
+        .await;
+
+        Ok(true)
+    }
+
+    async fn moderate_answer(
+        &self,
+        answer: Answer,
+        answer_id: i32,
+        moderator: AccountId,
+    ) -> Result<Answer, Error> {
+        let updated = {
+            let mut answers = self.answers.write().await;
+            let stored = answers
+                .get_mut(&AnswerId(answer_id))
+                .ok_or(Error::AnswerNotFound)?;
+            stored.content = answer.content;
+            stored.moderation_status = answer.moderation_status;
+            stored.original_content = answer.original_content;
+            stored.clone()
+        };
+
+        self.record_audit(moderator, AuditAction::UpdateAnswer, answer_id)
+            .await;
+
+        Ok(updated)
+    }
+
+    async fn remove_answer(
+        &self,
+        answer_id: i32,
+        moderator: AccountId,
+    ) -> Result<bool, Error> {
+        if !self.remove_answer_entry(&AnswerId(answer_id)).await {
+            return Err(Error::AnswerNotFound);
+        }
+
+        self.record_audit(moderator, AuditAction::DeleteAnswer, answer_id)
+            .await;
+
+        Ok(true)
+    }
+
+    async fn change_role(
+        &self,
+        account_id: i32,
+        role: Role,
+        admin: AccountId,
+    ) -> Result<bool, Error> {
+        {
+            let mut accounts = self.accounts.write().await;
+            let account = accounts
+                .values_mut()
+                .find(|account| account.id.0 == account_id)
+                .ok_or(Error::AccountNotFound)?;
+            account.role = role;
+        }
+
+        self.record_audit(admin, AuditAction::ChangeRole, account_id)
+            .await;
+
+        Ok(true)
+    }
+
+    async fn get_audit_log(
+        &self,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<AuditRecord>, Error> {
+        let records =
+            self.audit_log.read().await.iter().rev().cloned().collect();
+
+        Ok(paginate(records, limit, offset))
+    }
+
+    async fn get_moderation_queue(
+        &self,
+    ) -> Result<ModerationQueue, Error> {
+        let mut questions: Vec<Question> = self
+            .questions
+            .read()
+            .await
+            .values()
+            .filter(|q| q.moderation_status != ModerationStatus::Approved)
+            .cloned()
+            .collect();
+        questions.sort_by_key(|q| q.id.0);
+
+        let mut answers: Vec<Answer> = self
+            .answers
+            .read()
+            .await
+            .values()
+            .filter(|a| a.moderation_status != ModerationStatus::Approved)
+            .cloned()
+            .collect();
+        answers.sort_by_key(|a| a.id.0);
+
+        Ok(ModerationQueue { questions, answers })
+    }
+
+    async fn recheck_question(
+        &self,
+        original: &Question,
+        checked: Question,
+    ) -> Result<bool, Error> {
+        let mut questions = self.questions.write().await;
+        match questions.get_mut(&original.id) {
+            Some(stored)
+                if stored.moderation_status
+                    == ModerationStatus::PendingModeration
+                    && stored.title == original.title
+                    && stored.content == original.content =>
+            {
+                stored.title = checked.title;
+                stored.content = checked.content;
+                stored.moderation_status = checked.moderation_status;
+                stored.original_title = checked.original_title;
+                stored.original_content = checked.original_content;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn recheck_answer(
+        &self,
+        original: &Answer,
+        checked: Answer,
+    ) -> Result<bool, Error> {
+        let mut answers = self.answers.write().await;
+        match answers.get_mut(&original.id) {
+            Some(stored)
+                if stored.moderation_status
+                    == ModerationStatus::PendingModeration
+                    && stored.content == original.content =>
+            {
+                stored.content = checked.content;
+                stored.moderation_status = checked.moderation_status;
+                stored.original_content = checked.original_content;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+#[async_trait]
+impl TokenStore for InMemoryStore {
+    async fn revoke_token(
+        &self,
+        jti: String,
+        expires_on: DateTime<Utc>,
+    ) -> Result<bool, Error> {
+        let mut revoked = self.revoked_tokens.write().await;
+        let now = Utc::now();
+        revoked.retain(|_, expires_on| *expires_on >= now);
+
+        Ok(revoked.insert(jti, expires_on).is_none())
+    }
+
+    async fn is_token_revoked(&self, jti: &str) -> Result<bool, Error> {
+        Ok(self.revoked_tokens.read().await.contains_key(jti))
+    }
+
+    async fn revoke_account_tokens(
+        &self,
+        account_id: &AccountId,
+        issued_before: DateTime<Utc>,
+    ) -> Result<bool, Error> {
+        let mut cutoffs = self.token_cutoffs.write().await;
+        let cutoff =
+            cutoffs.entry(account_id.clone()).or_insert(issued_before);
+        *cutoff = (*cutoff).max(issued_before);
+
+        Ok(true)
+    }
+
+    async fn tokens_valid_after(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Option<DateTime<Utc>>, Error> {
+        Ok(self.token_cutoffs.read().await.get(account_id).copied())
+    }
+}
+
+#[async_trait]
+impl PasswordResetStore for InMemoryStore {
+    async fn add_password_reset(
+        &self,
+        account_id: &AccountId,
+        token_hash: String,
+        expires_on: DateTime<Utc>,
+    ) -> Result<bool, Error> {
+        let mut resets = self.password_resets.write().await;
+        let now = Utc::now();
+        resets.retain(|_, reset| reset.expires_on >= now);
+
+        resets.insert(
+            token_hash,
+            PendingToken {
+                account_id: account_id.clone(),
+                expires_on,
+            },
+        );
+
+        Ok(true)
+    }
+
+    async fn reset_password(
+        &self,
+        token_hash: &str,
+        password: String,
+    ) -> Result<AccountId, Error> {
+        let mut resets = self.password_resets.write().await;
+        let reset = resets
+            .remove(token_hash)
+            .filter(|reset| reset.expires_on > Utc::now())
+            .ok_or(Error::InvalidResetToken)?;
+        resets.retain(|_, other| other.account_id != reset.account_id);
+
+        self.update_password(&reset.account_id, password).await?;
+
+        Ok(reset.account_id)
+    }
+}
+
+#[async_trait]
+impl EmailVerificationStore for InMemoryStore {
+    async fn add_email_verification(
+        &self,
+        account_id: &AccountId,
+        token_hash: String,
+        expires_on: DateTime<Utc>,
+    ) -> Result<bool, Error> {
+        // 以前に発行したトークンは無効にする
+        let mut verifications = self.email_verifications.write().await;
+        let now = Utc::now();
+        verifications.retain(|_, pending| {
+            &pending.account_id != account_id && pending.expires_on >= now
+        });
+
+        verifications.insert(
+            token_hash,
+            PendingToken {
+                account_id: account_id.clone(),
+                expires_on,
+            },
+        );
+
+        Ok(true)
+    }
+
+    async fn verify_email(
+        &self,
+        token_hash: &str,
+    ) -> Result<AccountId, Error> {
+        let pending = self
+            .email_verifications
+            .write()
+            .await
+            .remove(token_hash)
+            .filter(|pending| pending.expires_on > Utc::now())
+            .ok_or(Error::InvalidVerificationToken)?;
+
+        let mut accounts = self.accounts.write().await;
+        let account = accounts
+            .values_mut()
+            .find(|account| account.id == pending.account_id)
+            .ok_or(Error::InvalidVerificationToken)?;
+        account.email_verified = true;
+
+        Ok(pending.account_id)
+    }
+}
+
+#[async_trait]
+impl LoginAttemptStore for InMemoryStore {
+    async fn record_login_attempt(
+        &self,
+        attempt: LoginAttempt,
+    ) -> Result<bool, Error> {
+        self.login_attempts.write().await.push(LoginRecord {
+            attempt,
+            created_on: Utc::now(),
+        });
+
+        Ok(true)
+    }
+
+    async fn failed_logins_for_account(
+        &self,
+        email: &str,
+        since: DateTime<Utc>,
+    ) -> Result<FailedLogins, Error> {
+        let records = self.login_attempts.read().await;
+        let records = records
+            .iter()
+            .filter(|record| record.attempt.email == email);
+
+        // 最後に成功した後の失敗のみを数える
+        let last_succeeded_on = records
+            .clone()
+            .filter(|record| record.attempt.succeeded)
+            .map(|record| record.created_on)
+            .max();
+        let since =
+            last_succeeded_on.map_or(since, |last| last.max(since));
+
+        Ok(count_failed(records, since))
+    }
+
+    async fn failed_logins_from_ip(
+        &self,
+        ip: &str,
+        since: DateTime<Utc>,
+    ) -> Result<FailedLogins, Error> {
+        let records = self.login_attempts.read().await;
+        let records = records
+            .iter()
+            .filter(|record| record.attempt.ip.as_deref() == Some(ip));
+
+        Ok(count_failed(records, since))
+    }
+}
+
+#[async_trait]
+impl ApiKeyStore for InMemoryStore {
+    async fn add_api_key(
+        &self,
+        account_id: &AccountId,
+        new_key: NewApiKey,
+        prefix: String,
+        key_hash: String,
+    ) -> Result<ApiKey, Error> {
+        let mut api_keys = self.api_keys.write().await;
+        let api_key = ApiKey {
+            id: self.api_key_ids.next(),
+            account_id: account_id.clone(),
+            name: new_key.name,
+            scope: new_key.scope,
+            prefix,
+            created_on: now(),
+            last_used_on: None,
+        };
+        api_keys.insert(key_hash, api_key.clone());
+
+        Ok(api_key)
+    }
+
+    async fn get_api_keys(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Vec<ApiKey>, Error> {
+        let mut api_keys: Vec<ApiKey> = self
+            .api_keys
+            .read()
+            .await
+            .values()
+            .filter(|api_key| &api_key.account_id == account_id)
+            .cloned()
+            .collect();
+        api_keys.sort_by_key(|api_key| api_key.id);
+
+        Ok(api_keys)
+    }
+
+    async fn revoke_api_key(
+        &self,
+        key_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        let mut api_keys = self.api_keys.write().await;
+        let key_hash = api_keys
+            .iter()
+            .find(|(_, api_key)| {
+                api_key.id == key_id && &api_key.account_id == account_id
+            })
+            .map(|(key_hash, _)| key_hash.clone())
+            .ok_or(Error::ApiKeyNotFound)?;
+        api_keys.remove(&key_hash);
+
+        Ok(true)
+    }
+
+    async fn revoke_account_api_keys(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<u64, Error> {
+        let mut api_keys = self.api_keys.write().await;
+        let before = api_keys.len();
+        api_keys.retain(|_, api_key| &api_key.account_id != account_id);
+
+        Ok((before - api_keys.len()) as u64)
+    }
+
+    async fn authenticate_api_key(
+        &self,
+        key_hash: &str,
+    ) -> Result<ApiKey, Error> {
+        let mut api_keys = self.api_keys.write().await;
+        let api_key =
+            api_keys.get_mut(key_hash).ok_or(Error::InvalidApiKey)?;
+        api_key.last_used_on = Some(now());
+
+        Ok(api_key.clone())
+    }
+}
+
+fn count_failed<'a>(
+    records: impl Iterator<Item = &'a LoginRecord>,
+    since: DateTime<Utc>,
+) -> FailedLogins {
+    records
+        .filter(|record| {
+            !record.attempt.succeeded && record.created_on > since
+        })
+        .fold(FailedLogins::default(), |failed, record| FailedLogins {
+            count: failed.count + 1,
+            last_failed_on: failed
+                .last_failed_on
+                .max(Some(record.created_on)),
+        })
+}
+
+#[cfg(test)]
+mod memory_tests {
+    use super::InMemoryStore;
+    use crate::{
+        store::{
+            AccountStore, AnswerStore, ModerationStore, QuestionStore,
+            VoteStore,
+        },
+        types::{
+            account::{Account, AccountId, Role},
+            answer::NewAnswer,
+            audit::AuditAction,
+            filter::QuestionFilter,
+            moderation::ModerationStatus,
+            pagination::{Cursor, CursorPagination},
+            question::{NewQuestion, Question, QuestionId},
+            sort::{Sort, SortKey, SortOrder},
+            tag::{TagCount, TagFilter, TagMode},
+            vote::VoteDirection,
+        },
+    };
+    use handle_errors::Error;
+
+    fn new_question(title: &str) -> NewQuestion {
+        NewQuestion {
+            title: title.to_string(),
+            content: "content".to_string(),
+            tags: None,
+            moderation_status: ModerationStatus::Approved,
+            original_title: None,
+            original_content: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn seed_questions() {
+        let store = InMemoryStore::with_seed();
+
+        let questions = store
+            .get_questions(
+                None,
+                0,
+                QuestionFilter::default(),
+                Sort::default(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(questions.len(), 1);
+        assert_eq!(questions[0].id, QuestionId(1));
+    }
+
+    #[tokio::test]
+    async fn paginate_questions() {
+        let store = InMemoryStore::new();
+        for title in ["first", "second", "third"] {
+            store
+                .add_question(new_question(title), AccountId(1))
+                .await
+                .unwrap();
+        }
+
+        let questions = store
+            .get_questions(
+                Some(1),
+                1,
+                QuestionFilter::default(),
+                Sort::default(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(questions.len(), 1);
+        assert_eq!(questions[0].title, "second");
+    }
+
+    #[tokio::test]
+    async fn sort_by_answers() {
+        let store = InMemoryStore::new();
+        for title in ["first", "second", "third"] {
+            store
+                .add_question(new_question(title), AccountId(1))
+                .await
+                .unwrap();
+        }
+        store
+            .add_answer(
+                NewAnswer {
+                    content: "answer".to_string(),
+                    question_id: QuestionId(2),
+                    moderation_status: ModerationStatus::Approved,
+                    original_content: None,
+                },
+                AccountId(1),
+            )
+            .await
+            .unwrap();
+
+        let sort = Sort {
+            key: SortKey::Answers,
+            order: SortOrder::Desc,
+        };
+        let questions = store
+            .get_questions(None, 0, QuestionFilter::default(), sort)
+            .await
+            .unwrap();
+
+        // 回答数が同じ質問は id の降順に並ぶ
+        let ids: Vec<i32> = questions.iter().map(|q| q.id.0).collect();
+        assert_eq!(ids, vec![2, 3, 1]);
+    }
+
+    #[tokio::test]
+    async fn ignore_pending_answers_in_listings() {
+        let store = InMemoryStore::new();
+        for title in ["first", "second"] {
+            store
+                .add_question(new_question(title), AccountId(1))
+                .await
+                .unwrap();
+        }
+        store
+            .add_answer(
+                NewAnswer {
+                    content: "answer".to_string(),
+                    question_id: QuestionId(1),
+                    moderation_status: ModerationStatus::PendingModeration,
+                    original_content: None,
+                },
+                AccountId(1),
+            )
+            .await
+            .unwrap();
+
+        // チェックを待っている回答しかない質問は未回答として扱う
+        let filter = QuestionFilter {
+            unanswered: Some(true),
+            ..QuestionFilter::default()
+        };
+        assert_eq!(store.count_questions(filter).await.unwrap(), 2);
+
+        let sort = Sort {
+            key: SortKey::Answers,
+            order: SortOrder::Desc,
+        };
+        let questions = store
+            .get_questions(None, 0, QuestionFilter::default(), sort)
+            .await
+            .unwrap();
+        let ids: Vec<i32> = questions.iter().map(|q| q.id.0).collect();
+        assert_eq!(ids, vec![2, 1]);
+    }
+
+    #[tokio::test]
+    async fn cursor_pagination() {
+        let store = InMemoryStore::new();
+        for title in ["first", "second", "third"] {
+            store
+                .add_question(new_question(title), AccountId(1))
+                .await
+                .unwrap();
+        }
+
+        let first = store
+            .get_questions_after(
+                CursorPagination {
+                    cursor: None,
+                    limit: 2,
+                },
+                QuestionFilter::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(first.items.len(), 2);
+        assert_eq!(first.items[0].title, "first");
+
+        let cursor = Cursor::decode(&first.next_cursor.unwrap()).unwrap();
+        let second = store
+            .get_questions_after(
+                CursorPagination {
+                    cursor: Some(cursor),
+                    limit: 2,
+                },
+                QuestionFilter::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(second.items.len(), 1);
+        assert_eq!(second.items[0].title, "third");
+        assert!(second.next_cursor.is_none());
+    }
+
+    #[tokio::test]
+    async fn only_owner_can_change_question() {
+        let store = InMemoryStore::new();
+        let question = store
+            .add_question(new_question("title"), AccountId(1))
+            .await
+            .unwrap();
+
+        assert!(store
+            .is_question_owner(question.id.0, &AccountId(1))
+            .await
+            .unwrap());
+        assert!(!store
+            .is_question_owner(question.id.0, &AccountId(2))
+            .await
+            .unwrap());
+        assert!(store
+            .update_question(question.clone(), question.id.0, AccountId(2))
+            .await
+            .is_err());
+
+        store
+            .delete_question(question.id.0, AccountId(2))
+            .await
+            .unwrap();
+        assert_eq!(
+            store
+                .get_questions(
+                    None,
+                    0,
+                    QuestionFilter::default(),
+                    Sort::default()
+                )
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        store
+            .delete_question(question.id.0, AccountId(1))
+            .await
+            .unwrap();
+        assert!(store
+            .get_questions(
+                None,
+                0,
+                QuestionFilter::default(),
+                Sort::default()
+            )
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn answers_of_question() {
+        let store = InMemoryStore::with_seed();
+        for content in ["first", "second"] {
+            store
+                .add_answer(
+                    NewAnswer {
+                        content: content.to_string(),
+                        question_id: QuestionId(1),
+                        moderation_status: ModerationStatus::Approved,
+                        original_content: None,
+                    },
+                    AccountId(1),
+                )
+                .await
+                .unwrap();
+        }
+
+        let answers = store.get_answers(1, Some(1), 1).await.unwrap();
+        assert_eq!(answers.len(), 1);
+        assert_eq!(answers[0].content, "second");
+
+        assert!(store.get_answers(2, None, 0).await.unwrap().is_empty());
+        assert!(matches!(
+            store.get_question(2).await,
+            Err(handle_errors::Error::QuestionNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn only_owner_can_change_answer() {
+        let store = InMemoryStore::with_seed();
+        let mut answer = store
+            .add_answer(
+                NewAnswer {
+                    content: "answer".to_string(),
+                    question_id: QuestionId(1),
+                    moderation_status: ModerationStatus::Approved,
+                    original_content: None,
+                },
+                AccountId(1),
+            )
+            .await
+            .unwrap();
+
+        assert!(!store
+            .is_answer_owner(answer.id.0, &AccountId(2))
+            .await
+            .unwrap());
+        assert!(store
+            .update_answer(answer.clone(), answer.id.0, AccountId(2))
+            .await
+            .is_err());
+
+        answer.content = "updated".to_string();
+        let updated = store
+            .update_answer(answer.clone(), answer.id.0, AccountId(1))
+            .await
+            .unwrap();
+        assert_eq!(updated.content, "updated");
+
+        store
+            .delete_answer(answer.id.0, AccountId(1))
+            .await
+            .unwrap();
+        assert!(store.get_answers(1, None, 0).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn remove_answer_with_votes() {
+        let store = InMemoryStore::with_seed();
+        let new_answer = || NewAnswer {
+            content: "answer".to_string(),
+            question_id: QuestionId(1),
+            moderation_status: ModerationStatus::Approved,
+            original_content: None,
+        };
+        let answer =
+            store.add_answer(new_answer(), AccountId(1)).await.unwrap();
+        store
+            .vote_answer(answer.id.0, AccountId(2), VoteDirection::Up)
+            .await
+            .unwrap();
+
+        store
+            .delete_answer(answer.id.0, AccountId(1))
+            .await
+            .unwrap();
+
+        // 削除した回答のIDは再利用せず、投票も残さない
+        let next =
+            store.add_answer(new_answer(), AccountId(1)).await.unwrap();
+        assert_ne!(next.id, answer.id);
+        assert_eq!(store.get_answer(next.id.0).await.unwrap().score, 0);
+        assert!(store.answer_votes.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn vote_on_question() {
+        let store = InMemoryStore::with_seed();
+
+        let score = store
+            .vote_question(1, AccountId(1), VoteDirection::Up)
+            .await
+            .unwrap();
+        assert_eq!(score, 1);
+
+        // 同じアカウントの投票は1票として扱い、方向だけを変更する
+        let score = store
+            .vote_question(1, AccountId(1), VoteDirection::Down)
+            .await
+            .unwrap();
+        assert_eq!(score, -1);
+
+        store
+            .vote_question(1, AccountId(2), VoteDirection::Down)
+            .await
+            .unwrap();
+        let score =
+            store.retract_question_vote(1, AccountId(1)).await.unwrap();
+        assert_eq!(score, -1);
+        assert_eq!(store.get_question(1).await.unwrap().score, -1);
+
+        let sort = Sort {
+            key: SortKey::Votes,
+            order: SortOrder::Desc,
+        };
+        store
+            .add_question(new_question("new"), AccountId(1))
+            .await
+            .unwrap();
+        let questions = store
+            .get_questions(None, 0, QuestionFilter::default(), sort)
+            .await
+            .unwrap();
+        assert_eq!(questions[0].title, "new");
+
+        assert!(matches!(
+            store
+                .vote_question(99, AccountId(1), VoteDirection::Up)
+                .await,
+            Err(Error::QuestionNotFound)
+        ));
+        assert!(matches!(
+            store.retract_answer_vote(99, AccountId(1)).await,
+            Err(Error::AnswerNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn search_questions_by_rank() {
+        let store = InMemoryStore::new();
+        store
+            .add_question(
+                NewQuestion {
+                    title: "Async closures".to_string(),
+                    content: "How do I write rust closures?".to_string(),
+                    tags: None,
+                    moderation_status: ModerationStatus::Approved,
+                    original_title: None,
+                    original_content: None,
+                },
+                AccountId(1),
+            )
+            .await
+            .unwrap();
+        store
+            .add_question(
+                NewQuestion {
+                    title: "Rust lifetimes".to_string(),
+                    content: "Why does rust need lifetimes?".to_string(),
+                    tags: None,
+                    moderation_status: ModerationStatus::Approved,
+                    original_title: None,
+                    original_content: None,
+                },
+                AccountId(1),
+            )
+            .await
+            .unwrap();
+
+        let results = store
+            .search_questions("Rust".to_string(), None, 0)
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].question.id, QuestionId(2));
+        assert_eq!(results[0].title_highlight, "<b>Rust</b> lifetimes");
+        assert_eq!(
+            results[1].content_highlight,
+            "How do I write <b>rust</b> closures?"
+        );
+    }
+
+    #[tokio::test]
+    async fn search_escapes_html_and_hides_pending_questions() {
+        let store = InMemoryStore::new();
+        for content in ["<script>rust</script>", "pending"] {
+            store
+                .add_question(
+                    NewQuestion {
+                        title: "title".to_string(),
+                        content: content.to_string(),
+                        tags: None,
+                        moderation_status: ModerationStatus::Approved,
+                        original_title: None,
+                        original_content: None,
+                    },
+                    AccountId(1),
+                )
+                .await
+                .unwrap();
+        }
+        for id in [1, 2] {
+            store
+                .add_answer(
+                    NewAnswer {
+                        content: "rust & warp".to_string(),
+                        question_id: QuestionId(id),
+                        moderation_status: ModerationStatus::Approved,
+                        original_content: None,
+                    },
+                    AccountId(1),
+                )
+                .await
+                .unwrap();
+        }
+        // 回答された後の編集でチェック待ちになった質問
+        store
+            .questions
+            .write()
+            .await
+            .get_mut(&QuestionId(2))
+            .unwrap()
+            .moderation_status = ModerationStatus::PendingModeration;
+
+        let questions = store
+            .search_questions("rust".to_string(), None, 0)
+            .await
+            .unwrap();
+        assert_eq!(
+            questions[0].content_highlight,
+            "&lt;script&gt;<b>rust</b>&lt;/script&gt;"
+        );
+
+        // 公開前の質問への回答は返さない
+        let answers = store
+            .search_answers("rust".to_string(), None, 0)
+            .await
+            .unwrap();
+        assert_eq!(answers.len(), 1);
+        assert_eq!(answers[0].answer.question_id, QuestionId(1));
+        assert_eq!(answers[0].content_highlight, "<b>rust</b> &amp; warp");
+    }
+
+    #[tokio::test]
+    async fn filter_and_count_tags() {
+        let store = InMemoryStore::new();
+        for tags in [vec!["rust", "warp"], vec!["rust"], vec!["sqlx"]] {
+            store
+                .add_question(
+                    NewQuestion {
+                        title: "title".to_string(),
+                        content: "content".to_string(),
+                        tags: Some(
+                            tags.into_iter().map(String::from).collect(),
+                        ),
+                        moderation_status: ModerationStatus::Approved,
+                        original_title: None,
+                        original_content: None,
+                    },
+                    AccountId(1),
+                )
+                .await
+                .unwrap();
+        }
+
+        let filter = |mode| QuestionFilter {
+            tags: Some(TagFilter {
+                tags: vec!["warp".to_string(), "sqlx".to_string()],
+                mode,
+            }),
+            ..QuestionFilter::default()
+        };
+        let any = store
+            .get_questions(None, 0, filter(TagMode::Any), Sort::default())
+            .await
+            .unwrap();
+        let all = store
+            .get_questions(None, 0, filter(TagMode::All), Sort::default())
+            .await
+            .unwrap();
+        assert_eq!(any.len(), 2);
+        assert!(all.is_empty());
+
+        let tags = store.get_tags().await.unwrap();
+        assert_eq!(
+            tags[0],
+            TagCount {
+                name: "rust".to_string(),
+                count: 2
+            }
+        );
+        assert_eq!(tags.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn accept_answer() {
+        let store = InMemoryStore::new();
+        for title in ["answered", "accepted", "unanswered"] {
+            store
+                .add_question(new_question(title), AccountId(1))
+                .await
+                .unwrap();
+        }
+        let mut answer_ids = Vec::new();
+        for question_id in [1, 2, 2] {
+            let answer = store
+                .add_answer(
+                    NewAnswer {
+                        content: "answer".to_string(),
+                        question_id: QuestionId(question_id),
+                        moderation_status: ModerationStatus::Approved,
+                        original_content: None,
+                    },
+                    AccountId(2),
+                )
+                .await
+                .unwrap();
+            answer_ids.push(answer.id.0);
+        }
+
+        // 別の質問の回答は採用できない
+        assert!(matches!(
+            store.accept_answer(2, answer_ids[0], AccountId(1)).await,
+            Err(Error::AnswerNotFound)
+        ));
+
+        store
+            .accept_answer(2, answer_ids[1], AccountId(1))
+            .await
+            .unwrap();
+        let accepted = store
+            .accept_answer(2, answer_ids[2], AccountId(1))
+            .await
+            .unwrap();
+        assert!(accepted.accepted);
+        let answers = store.get_answers(2, None, 0).await.unwrap();
+        let flags: Vec<bool> =
+            answers.iter().map(|a| a.accepted).collect();
+        assert_eq!(flags, vec![false, true]);
+
+        let titles = |filter| async {
+            store
+                .get_questions(None, 0, filter, Sort::default())
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|q| q.title)
+                .collect::<Vec<_>>()
+        };
+        let unresolved = titles(QuestionFilter {
+            accepted: Some(false),
+            ..QuestionFilter::default()
+        })
+        .await;
+        assert_eq!(unresolved, vec!["answered", "unanswered"]);
+
+        let unanswered = titles(QuestionFilter {
+            unanswered: Some(true),
+            ..QuestionFilter::default()
+        })
+        .await;
+        assert_eq!(unanswered, vec!["unanswered"]);
+
+        // 採用された回答を削除すると未解決に戻る
+        store
+            .delete_answer(answer_ids[2], AccountId(2))
+            .await
+            .unwrap();
+        let count = store
+            .count_questions(QuestionFilter {
+                accepted: Some(false),
+                ..QuestionFilter::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(count, 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_reads_and_writes() {
+        let store = InMemoryStore::new();
+
+        let tasks: Vec<_> = (0..200)
+            .map(|i| {
+                let store = store.clone();
+                tokio::spawn(async move {
+                    let filter = QuestionFilter {
+                        accepted: Some(false),
+                        ..QuestionFilter::default()
+                    };
+                    match i % 3 {
+                        0 => {
+                            store
+                                .add_question(
+                                    new_question("title"),
+                                    AccountId(1),
+                                )
+                                .await
+                                .unwrap();
+                        }
+                        1 => {
+                            store.count_questions(filter).await.unwrap();
+                        }
+                        _ => {
+                            store
+                                .get_questions(
+                                    None,
+                                    0,
+                                    filter,
+                                    Sort::default(),
+                                )
+                                .await
+                                .unwrap();
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        // ロックの順序が食い違っているとデッドロックして終わらない
+        tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            for task in tasks {
+                task.await.unwrap();
+            }
+        })
+        .await
+        .expect("store operations deadlocked");
+
+        assert_eq!(
+            store
+                .count_questions(QuestionFilter::default())
+                .await
+                .unwrap(),
+            67
+        );
+    }
+
+    #[tokio::test]
+    async fn moderator_changes_are_audited() {
+        let store = InMemoryStore::with_seed();
+        let answer = store
+            .add_answer(
+                NewAnswer {
+                    content: "answer".to_string(),
+                    question_id: QuestionId(1),
+                    moderation_status: ModerationStatus::Approved,
+                    original_content: None,
+                },
+                AccountId(1),
+            )
+            .await
+            .unwrap();
+
+        let mut question = store.get_question(1).await.unwrap();
+        question.title = "moderated".to_string();
+        let moderated = store
+            .moderate_question(question, 1, AccountId(9))
+            .await
+            .unwrap();
+        assert_eq!(moderated.title, "moderated");
+
+        store
+            .remove_answer(answer.id.0, AccountId(9))
+            .await
+            .unwrap();
+        assert!(matches!(
+            store.remove_answer(answer.id.0, AccountId(9)).await,
+            Err(Error::AnswerNotFound)
+        ));
+
+        let log = store.get_audit_log(None, 0).await.unwrap();
+        let actions: Vec<AuditAction> =
+            log.iter().map(|r| r.action).collect();
+        assert_eq!(
+            actions,
+            vec![AuditAction::DeleteAnswer, AuditAction::UpdateQuestion]
+        );
+        assert!(log.iter().all(|r| r.account_id == AccountId(9)));
+    }
+
+    #[tokio::test]
+    async fn remove_question_with_answers() {
+        let store = InMemoryStore::with_seed();
+        let answer = store
+            .add_answer(
+                NewAnswer {
+                    content: "answer".to_string(),
+                    question_id: QuestionId(1),
+                    moderation_status: ModerationStatus::Approved,
+                    original_content: None,
+                },
+                AccountId(2),
+            )
+            .await
+            .unwrap();
+        store
+            .vote_answer(answer.id.0, AccountId(3), VoteDirection::Up)
+            .await
+            .unwrap();
+
+        store.remove_question(1, AccountId(9)).await.unwrap();
+
+        // 回答と投票、回答の所有者もまとめて削除する
+        assert!(matches!(
+            store.get_answer(answer.id.0).await,
+            Err(Error::AnswerNotFound)
+        ));
+        assert!(!store
+            .is_answer_owner(answer.id.0, &AccountId(2))
+            .await
+            .unwrap());
+        assert!(store.answer_votes.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn pending_posts_cannot_be_voted_accepted_or_answered() {
+        let store = InMemoryStore::new();
+        let pending = store
+            .add_question(
+                NewQuestion {
+                    moderation_status: ModerationStatus::PendingModeration,
+                    ..new_question("title")
+                },
+                AccountId(1),
+            )
+            .await
+            .unwrap();
+        let new_answer = |moderation_status| NewAnswer {
+            content: "answer".to_string(),
+            question_id: pending.id.clone(),
+            moderation_status,
+            original_content: None,
+        };
+
+        // 公開されていない質問は存在しない質問と同じく扱う
+        assert!(matches!(
+            store
+                .vote_question(
+                    pending.id.0,
+                    AccountId(2),
+                    VoteDirection::Up
+                )
+                .await,
+            Err(Error::QuestionNotFound)
+        ));
+        assert!(matches!(
+            store
+                .add_answer(
+                    new_answer(ModerationStatus::Approved),
+                    AccountId(2)
+                )
+                .await,
+            Err(Error::QuestionNotFound)
+        ));
+
+        let question = store
+            .add_question(new_question("approved"), AccountId(1))
+            .await
+            .unwrap();
+        let answer = store
+            .add_answer(
+                NewAnswer {
+                    question_id: question.id.clone(),
+                    ..new_answer(ModerationStatus::PendingModeration)
+                },
+                AccountId(2),
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            store
+                .vote_answer(answer.id.0, AccountId(3), VoteDirection::Up)
+                .await,
+            Err(Error::AnswerNotFound)
+        ));
+        assert!(matches!(
+            store
+                .accept_answer(question.id.0, answer.id.0, AccountId(1))
+                .await,
+            Err(Error::AnswerNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn deleted_question_id_is_not_reused() {
+        let store = InMemoryStore::with_seed();
+        let question = store
+            .add_question(new_question("newest"), AccountId(1))
+            .await
+            .unwrap();
+        assert_eq!(question.id, QuestionId(2));
+
+        store
+            .remove_question(question.id.0, AccountId(9))
+            .await
+            .unwrap();
+
+        // 監査ログの `target_id` が新しい質問を指さないよう、IDを再利用しない
+        let next = store
+            .add_question(new_question("next"), AccountId(1))
+            .await
+            .unwrap();
+        assert_eq!(next.id, QuestionId(3));
+        let log = store.get_audit_log(None, 0).await.unwrap();
+        assert_eq!(log[0].target_id, question.id.0);
+    }
+
+    #[tokio::test]
+    async fn recheck_pending_question() {
+        let store = InMemoryStore::new();
+        let pending = store
+            .add_question(
+                NewQuestion {
+                    moderation_status: ModerationStatus::PendingModeration,
+                    ..new_question("title")
+                },
+                AccountId(1),
+            )
+            .await
+            .unwrap();
+        store
+            .add_question(new_question("approved"), AccountId(1))
+            .await
+            .unwrap();
+
+        let queue = store.get_moderation_queue().await.unwrap();
+        assert_eq!(queue.questions.len(), 1);
+        assert_eq!(queue.questions[0].id, pending.id);
+
+        let checked = Question {
+            title: "checked".to_string(),
+            moderation_status: ModerationStatus::Approved,
+            ..pending.clone()
+        };
+        // 再チェックの間に編集された場合は上書きしない
+        let edited = Question {
+            content: "edited".to_string(),
+            ..pending.clone()
+        };
+        assert!(!store
+            .recheck_question(&edited, checked.clone())
+            .await
+            .unwrap());
+
+        assert!(store.recheck_question(&pending, checked).await.unwrap());
+        assert_eq!(store.get_question(1).await.unwrap().title, "checked");
+        assert!(store
+            .get_moderation_queue()
+            .await
+            .unwrap()
+            .questions
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn duplicate_account() {
+        let store = InMemoryStore::new();
+        let account = Account {
+            id: AccountId(0),
+            email: "test@example.com".to_string(),
+            password: "password".to_string(),
+            role: Role::User,
+            email_verified: false,
+        };
+
+        store.add_account(account.clone()).await.unwrap();
+        assert!(matches!(
+            store.add_account(account).await,
+            Err(Error::AccountAlreadyExists)
+        ));
+        assert!(matches!(
+            store.get_account("unknown@example.com".to_string()).await,
+            Err(Error::AccountNotFound)
+        ));
+
+        let account = store
+            .get_account("test@example.com".to_string())
+            .await
+            .unwrap();
+        assert_eq!(account.id, AccountId(1));
+    }
+
+    #[tokio::test]
+    async fn deleted_account_id_is_not_reused() {
+        let store = InMemoryStore::with_seed();
+        let account = |email: &str| Account {
+            id: AccountId(0),
+            email: email.to_string(),
+            password: "password".to_string(),
+            role: Role::User,
+            email_verified: false,
+        };
+
+        store.add_account(account("old@example.com")).await.unwrap();
+        store
+            .vote_question(1, AccountId(1), VoteDirection::Up)
+            .await
+            .unwrap();
+        store.delete_account(&AccountId(1)).await.unwrap();
+
+        // 新しいアカウントが削除済みのアカウントの投票を引き継がない
+        store.add_account(account("new@example.com")).await.unwrap();
+        let new_account = store
+            .get_account("new@example.com".to_string())
+            .await
+            .unwrap();
+        assert_eq!(new_account.id, AccountId(2));
+        let score = store
+            .retract_question_vote(1, new_account.id)
+            .await
+            .unwrap();
+        assert_eq!(score, 1);
+    }
+}
@@ -0,0 +1,516 @@
+use std::{fmt::Debug, ops::Deref, sync::Arc};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::types::{
+    account::{Account, AccountId, Role},
+    answer::{Answer, AnswerSearchResult, NewAnswer},
+    api_key::{ApiKey, NewApiKey},
+    audit::AuditRecord,
+    filter::QuestionFilter,
+    login::{FailedLogins, LoginAttempt},
+    moderation::ModerationQueue,
+    pagination::{CursorPage, CursorPagination},
+    question::{NewQuestion, Question, QuestionSearchResult},
+    sort::Sort,
+    tag::TagCount,
+    vote::VoteDirection,
+};
+use handle_errors::Error;
+
+mod memory;
+mod postgres;
+
+pub use memory::InMemoryStore;
+pub use postgres::PostgresStore;
+
+/// 質問の永続化を担当する
+///
+/// 一覧・検索・取得では、不適切な単語のチェックを待っている
+/// (`ModerationStatus::PendingModeration`) 質問を返さない
+#[async_trait]
+pub trait QuestionStore {
+    /// `sort` の値が同じ場合は `id` の順に並べる
+    async fn get_questions(
+        &self,
+        limit: Option<u32>,
+        offset: u32,
+        filter: QuestionFilter,
+        sort: Sort,
+    ) -> Result<Vec<Question>, Error>;
+
+    /// 絞り込み条件に一致する質問の総数を返す
+    async fn count_questions(
+        &self,
+        filter: QuestionFilter,
+    ) -> Result<i64, Error>;
+
+    /// `(created_on, id)` の順に並べ、カーソルより後ろの質問を返す
+    async fn get_questions_after(
+        &self,
+        pagination: CursorPagination,
+        filter: QuestionFilter,
+    ) -> Result<CursorPage<Question>, Error>;
+
+    /// 使用されているタグを質問数の多い順に返す
+    async fn get_tags(&self) -> Result<Vec<TagCount>, Error>;
+
+    /// タイトルと本文を全文検索し、関連度の高い順に返す
+    async fn search_questions(
+        &self,
+        query: String,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<QuestionSearchResult>, Error>;
+
+    /// 存在しない場合は `Error::QuestionNotFound` を返す
+    async fn get_question(
+        &self,
+        question_id: i32,
+    ) -> Result<Question, Error>;
+
+    async fn add_question(
+        &self,
+        new_question: NewQuestion,
+        account_id: AccountId,
+    ) -> Result<Question, Error>;
+
+    async fn update_question(
+        &self,
+        question: Question,
+        question_id: i32,
+        account_id: AccountId,
+    ) -> Result<Question, Error>;
+
+    async fn delete_question(
+        &self,
+        question_id: i32,
+        account_id: AccountId,
+    ) -> Result<bool, Error>;
+
+    async fn is_question_owner(
+        &self,
+        question_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error>;
+
+    /// 質問に対する回答を採用し、採用した回答を返す
+    ///
+    /// 回答が質問に紐づいていない場合は `Error::AnswerNotFound` を返す。
+    /// 既に採用された回答がある場合は置き換える
+    async fn accept_answer(
+        &self,
+        question_id: i32,
+        answer_id: i32,
+        account_id: AccountId,
+    ) -> Result<Answer, Error>;
+}
+
+/// 回答の永続化を担当する
+///
+/// 一覧と検索では、不適切な単語のチェックを待っている回答を返さない
+#[async_trait]
+pub trait AnswerStore {
+    /// チェックを待っている回答も返す。存在しない場合は `Error::AnswerNotFound` を返す
+    async fn get_answer(&self, answer_id: i32) -> Result<Answer, Error>;
+
+    async fn get_answers(
+        &self,
+        question_id: i32,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<Answer>, Error>;
+
+    /// 回答の本文を全文検索し、関連度の高い順に返す
+    async fn search_answers(
+        &self,
+        query: String,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<AnswerSearchResult>, Error>;
+
+    async fn add_answer(
+        &self,
+        new_answer: NewAnswer,
+        account_id: AccountId,
+    ) -> Result<Answer, Error>;
+
+    async fn update_answer(
+        &self,
+        answer: Answer,
+        answer_id: i32,
+        account_id: AccountId,
+    ) -> Result<Answer, Error>;
+
+    async fn delete_answer(
+        &self,
+        answer_id: i32,
+        account_id: AccountId,
+    ) -> Result<bool, Error>;
+
+    async fn is_answer_owner(
+        &self,
+        answer_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error>;
+}
+
+/// 質問・回答への投票を担当する
+///
+/// いずれのメソッドも投票を反映した後のスコアを返す
+#[async_trait]
+pub trait VoteStore {
+    /// 既に投票している場合は方向を変更する
+    async fn vote_question(
+        &self,
+        question_id: i32,
+        account_id: AccountId,
+        direction: VoteDirection,
+    ) -> Result<i64, Error>;
+
+    async fn retract_question_vote(
+        &self,
+        question_id: i32,
+        account_id: AccountId,
+    ) -> Result<i64, Error>;
+
+    /// 既に投票している場合は方向を変更する
+    async fn vote_answer(
+        &self,
+        answer_id: i32,
+        account_id: AccountId,
+        direction: VoteDirection,
+    ) -> Result<i64, Error>;
+
+    async fn retract_answer_vote(
+        &self,
+        answer_id: i32,
+        account_id: AccountId,
+    ) -> Result<i64, Error>;
+}
+
+/// アカウントの永続化を担当する
+///
+/// 存在しないアカウントに対する操作は `Error::AccountNotFound` を返す
+#[async_trait]
+pub trait AccountStore {
+    /// 登録済みのメールアドレスの場合は `Error::AccountAlreadyExists` を返す
+    async fn add_account(&self, account: Account) -> Result<bool, Error>;
+
+    async fn get_account(&self, email: String) -> Result<Account, Error>;
+
+    async fn get_account_by_id(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Account, Error>;
+
+    /// `password` はハッシュ化した値を渡す
+    async fn update_password(
+        &self,
+        account_id: &AccountId,
+        password: String,
+    ) -> Result<bool, Error>;
+
+    /// 新しいメールアドレスは確認が済むまで未確認として扱う
+    ///
+    /// 他のアカウントが使用している場合は `Error::AccountAlreadyExists` を返す
+    async fn update_email(
+        &self,
+        account_id: &AccountId,
+        email: String,
+    ) -> Result<bool, Error>;
+
+    /// アカウントを削除する
+    ///
+    /// 質問と回答は他のアカウントの回答や投票を残すために削除せず、所有者のいない状態にする
+    async fn delete_account(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<bool, Error>;
+}
+
+/// モデレーターと管理者による操作の永続化を担当する
+///
+/// 所有者に関係なく変更でき、全ての操作を監査ログに記録する
+#[async_trait]
+pub trait ModerationStore {
+    /// 不適切な単語のチェックを待っている質問も含めて取得する
+    ///
+    /// 存在しない場合は `Error::QuestionNotFound` を返す
+    async fn get_moderated_question(
+        &self,
+        question_id: i32,
+    ) -> Result<Question, Error>;
+
+    /// 存在しない場合は `Error::QuestionNotFound` を返す
+    async fn moderate_question(
+        &self,
+        question: Question,
+        question_id: i32,
+        moderator: AccountId,
+    ) -> Result<Question, Error>;
+
+    /// 存在しない場合は `Error::QuestionNotFound` を返す
+    async fn remove_question(
+        &self,
+        question_id: i32,
+        moderator: AccountId,
+    ) -> Result<bool, Error>;
+
+    /// 存在しない場合は `Error::AnswerNotFound` を返す
+    async fn moderate_answer(
+        &self,
+        answer: Answer,
+        answer_id: i32,
+        moderator: AccountId,
+    ) -> Result<Answer, Error>;
+
+    /// 存在しない場合は `Error::AnswerNotFound` を返す
+    async fn remove_answer(
+        &self,
+        answer_id: i32,
+        moderator: AccountId,
+    ) -> Result<bool, Error>;
+
+    /// 存在しない場合は `Error::AccountNotFound` を返す
+    async fn change_role(
+        &self,
+        account_id: i32,
+        role: Role,
+        admin: AccountId,
+    ) -> Result<bool, Error>;
+
+    /// 新しい順に返す
+    async fn get_audit_log(
+        &self,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<AuditRecord>, Error>;
+
+    /// 不適切な単語のチェックが済んでいない質問と回答を古い順に返す
+    async fn get_moderation_queue(&self)
+        -> Result<ModerationQueue, Error>;
+
+    /// バックグラウンドで再チェックした質問を保存する
+    ///
+    /// 再チェックの間に編集された場合や既にチェックが済んでいる場合は、
+    /// 変更せずに `false` を返す
+    async fn recheck_question(
+        &self,
+        original: &Question,
+        checked: Question,
+    ) -> Result<bool, Error>;
+
+    /// バックグラウンドで再チェックした回答を保存する
+    ///
+    /// `recheck_question` と同じく、内容が変わっていた場合は `false` を返す
+    async fn recheck_answer(
+        &self,
+        original: &Answer,
+        checked: Answer,
+    ) -> Result<bool, Error>;
+}
+
+/// 失効したトークンの永続化を担当する
+#[async_trait]
+pub trait TokenStore {
+    /// トークンIDを失効させる
+    ///
+    /// 既に失効していた場合は `false` を返す。
+    /// `expires_on` を過ぎた記録はトークン自体が無効になっているため削除してよい
+    async fn revoke_token(
+        &self,
+        jti: String,
+        expires_on: DateTime<Utc>,
+    ) -> Result<bool, Error>;
+
+    async fn is_token_revoked(&self, jti: &str) -> Result<bool, Error>;
+
+    /// アカウントに `issued_before` より前に発行したトークンを全て失効させる
+    ///
+    /// パスワードの変更や退会の際に使用する。
+    /// 既により後の時刻が設定されている場合はそのままにする
+    async fn revoke_account_tokens(
+        &self,
+        account_id: &AccountId,
+        issued_before: DateTime<Utc>,
+    ) -> Result<bool, Error>;
+
+    /// この時刻より前に発行したトークンは無効として扱う
+    async fn tokens_valid_after(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Option<DateTime<Utc>>, Error>;
+}
+
+/// パスワードリセット用トークンの永続化を担当する
+///
+/// トークンはハッシュ化した値のみを受け取る
+#[async_trait]
+pub trait PasswordResetStore {
+    async fn add_password_reset(
+        &self,
+        account_id: &AccountId,
+        token_hash: String,
+        expires_on: DateTime<Utc>,
+    ) -> Result<bool, Error>;
+
+    /// トークンを使用済みにしてパスワードを更新し、対象のアカウントを返す
+    ///
+    /// トークンが存在しないか有効期限が切れている場合は
+    /// `Error::InvalidResetToken` を返す。
+    /// 同じアカウントに発行された他のトークンも併せて無効にする
+    async fn reset_password(
+        &self,
+        token_hash: &str,
+        password: String,
+    ) -> Result<AccountId, Error>;
+}
+
+/// メールアドレスの確認用トークンの永続化を担当する
+///
+/// トークンはハッシュ化した値のみを受け取る
+#[async_trait]
+pub trait EmailVerificationStore {
+    /// 同じアカウントに以前発行したトークンは無効にする
+    async fn add_email_verification(
+        &self,
+        account_id: &AccountId,
+        token_hash: String,
+        expires_on: DateTime<Utc>,
+    ) -> Result<bool, Error>;
+
+    /// トークンを使用済みにしてアカウントを確認済みにし、対象のアカウントを返す
+    ///
+    /// トークンが存在しないか有効期限が切れている場合は
+    /// `Error::InvalidVerificationToken` を返す
+    async fn verify_email(
+        &self,
+        token_hash: &str,
+    ) -> Result<AccountId, Error>;
+}
+
+/// ログインの試行の記録を担当する
+#[async_trait]
+pub trait LoginAttemptStore {
+    async fn record_login_attempt(
+        &self,
+        attempt: LoginAttempt,
+    ) -> Result<bool, Error>;
+
+    /// `since` 以降、最後にログインに成功してから失敗した回数を返す
+    async fn failed_logins_for_account(
+        &self,
+        email: &str,
+        since: DateTime<Utc>,
+    ) -> Result<FailedLogins, Error>;
+
+    /// `since` 以降にIPアドレスからログインに失敗した回数を返す
+    ///
+    /// 攻撃者が自分のアカウントでログインして数え直せないよう、成功しても数え直さない
+    async fn failed_logins_from_ip(
+        &self,
+        ip: &str,
+        since: DateTime<Utc>,
+    ) -> Result<FailedLogins, Error>;
+}
+
+/// APIキーの永続化を担当する
+///
+/// キーはハッシュ化した値のみを受け取る
+#[async_trait]
+pub trait ApiKeyStore {
+    async fn add_api_key(
+        &self,
+        account_id: &AccountId,
+        new_key: NewApiKey,
+        prefix: String,
+        key_hash: String,
+    ) -> Result<ApiKey, Error>;
+
+    /// 作成した順に返す
+    async fn get_api_keys(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Vec<ApiKey>, Error>;
+
+    /// 他のアカウントのキーを指定した場合も `Error::ApiKeyNotFound` を返す
+    async fn revoke_api_key(
+        &self,
+        key_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error>;
+
+    /// アカウントのキーを全て失効させ、失効させた数を返す
+    ///
+    /// パスワードの変更や再設定の際に、盗まれたセッションで作られたキーも使えなくする
+    async fn revoke_account_api_keys(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<u64, Error>;
+
+    /// キーを検索し、最後に使用した日時を更新する
+    ///
+    /// 存在しないか失効している場合は `Error::InvalidApiKey` を返す
+    async fn authenticate_api_key(
+        &self,
+        key_hash: &str,
+    ) -> Result<ApiKey, Error>;
+}
+
+/// ルートハンドラが必要とする全てのストアの機能をまとめたトレイト
+pub trait Storage:
+    QuestionStore
+    + AnswerStore
+    + VoteStore
+    + AccountStore
+    + ModerationStore
+    + TokenStore
+    + PasswordResetStore
+    + EmailVerificationStore
+    + LoginAttemptStore
+    + ApiKeyStore
+    + Debug
+    + Send
+    + Sync
+{
+}
+
+impl<T> Storage for T where
+    T: QuestionStore
+        + AnswerStore
+        + VoteStore
+        + AccountStore
+        + ModerationStore
+        + TokenStore
+        + PasswordResetStore
+        + EmailVerificationStore
+        + LoginAttemptStore
+        + ApiKeyStore
+        + Debug
+        + Send
+        + Sync
+{
+}
+
+/// ルートハンドラに渡されるストア
+///
+/// 起動時に選択したバックエンドをトレイトオブジェクトとして保持するため、
+/// ハンドラ側は PostgreSQL かインメモリかを意識する必要がない
+#[derive(Debug, Clone)]
+pub struct Store(Arc<dyn Storage>);
+
+impl Store {
+    pub fn new<S: Storage + 'static>(storage: S) -> Self {
+        Store(Arc::new(storage))
+    }
+}
+
+impl Deref for Store {
+    type Target = dyn Storage;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
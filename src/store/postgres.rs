@@ -0,0 +1,2008 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{
+    postgres::{PgPoolOptions, PgRow},
+    PgPool, Postgres, Row, Transaction,
+};
+
+use crate::{
+    store::{
+        AccountStore, AnswerStore, ApiKeyStore, EmailVerificationStore,
+        LoginAttemptStore, ModerationStore, PasswordResetStore,
+        QuestionStore, TokenStore, VoteStore,
+    },
+    types::{
+        account::{Account, AccountId, Role},
+        answer::{Answer, AnswerId, AnswerSearchResult, NewAnswer},
+        api_key::{ApiKey, NewApiKey},
+        audit::{AuditAction, AuditRecord},
+        filter::QuestionFilter,
+        login::{FailedLogins, LoginAttempt},
+        moderation::{ModerationQueue, ModerationStatus},
+        pagination::{Cursor, CursorPage, CursorPagination},
+        question::{
+            NewQuestion, Question, QuestionId, QuestionSearchResult,
+        },
+        sort::{Sort, SortKey, SortOrder},
+        tag::{TagCount, TagMode},
+        vote::VoteDirection,
+    },
+};
+use handle_errors::Error;
+
+/// PostgreSQLをバックエンドとするストア
+#[derive(Debug, Clone)]
+pub struct PostgresStore {
+    pub connection: PgPool,
+}
+
+/// 投票の対象となるテーブル
+struct VoteTarget {
+    table: &'static str,
+    votes_table: &'static str,
+    column: &'static str,
+    not_found: fn() -> Error,
+}
+
+const QUESTION_VOTES: VoteTarget = VoteTarget {
+    table: "questions",
+    votes_table: "question_votes",
+    column: "question_id",
+    not_found: || Error::QuestionNotFound,
+};
+
+const ANSWER_VOTES: VoteTarget = VoteTarget {
+    table: "answers",
+    votes_table: "answer_votes",
+    column: "answer_id",
+    not_found: || Error::AnswerNotFound,
+};
+
+/// 質問の絞り込み条件を WHERE 句の条件に変換する
+///
+/// タグは `$tags_index` の位置にバインドする。
+/// チェックを待っている質問は常に除外する
+fn filter_conditions(
+    filter: &QuestionFilter,
+    tags_index: usize,
+) -> Vec<String> {
+    let mut conditions =
+        vec!["moderation_status <> 'pending_moderation'".to_string()];
+
+    // GINインデックスを使用できるように配列の演算子で絞り込む
+    if let Some(tags) = &filter.tags {
+        conditions.push(match tags.mode {
+            TagMode::All => format!("tags @> ${}", tags_index),
+            TagMode::Any => format!("tags && ${}", tags_index),
+        });
+    }
+
+    // チェックを待っている回答は、一覧から存在を推測されないよう数えない
+    let answered = "EXISTS (SELECT 1 FROM answers
+        WHERE answers.question_id = questions.id
+            AND answers.moderation_status <> 'pending_moderation')";
+    match filter.unanswered {
+        Some(true) => conditions.push(format!("NOT {}", answered)),
+        Some(false) => conditions.push(answered.to_string()),
+        None => {}
+    }
+
+    match filter.accepted {
+        Some(true) => {
+            conditions.push("accepted_answer_id IS NOT NULL".to_string())
+        }
+        Some(false) => {
+            conditions.push("accepted_answer_id IS NULL".to_string())
+        }
+        None => {}
+    }
+
+    conditions
+}
+
+/// 並べ替えに使用するカラム
+///
+/// 回答数にはチェックを待っている回答を含めない
+fn sort_column(key: &SortKey) -> &'static str {
+    match key {
+        SortKey::CreatedOn => "created_on",
+        SortKey::UpdatedOn => "updated_on",
+        SortKey::Votes => "score",
+        SortKey::Answers => {
+            "(SELECT COUNT(*) FROM answers
+                WHERE answers.question_id = questions.id
+                    AND answers.moderation_status <> 'pending_moderation')"
+        }
+    }
+}
+
+fn account_from_row(row: PgRow) -> Result<Account, sqlx::Error> {
+    Ok(Account {
+        id: AccountId(row.get("id")),
+        email: row.get("email"),
+        password: row.get("password"),
+        role: row
+            .get::<&str, _>("role")
+            .parse()
+            .map_err(|e: String| sqlx::Error::Decode(e.into()))?,
+        email_verified: row.get("email_verified"),
+    })
+}
+
+fn api_key_from_row(row: PgRow) -> Result<ApiKey, sqlx::Error> {
+    Ok(ApiKey {
+        id: row.get("id"),
+        account_id: AccountId(row.get("account_id")),
+        name: row.get("name"),
+        scope: row
+            .get::<&str, _>("scope")
+            .parse()
+            .map_err(|e: String| sqlx::Error::Decode(e.into()))?,
+        prefix: row.get("prefix"),
+        created_on: row.get("created_on"),
+        last_used_on: row.get("last_used_on"),
+    })
+}
+
+/// `moderation_status` と元の文章を含めて取得した行から質問を読み込む
+fn question_from_row(row: &PgRow) -> Result<Question, sqlx::Error> {
+    Ok(Question {
+        id: QuestionId(row.get("id")),
+        title: row.get("title"),
+        content: row.get("content"),
+        tags: row.get("tags"),
+        score: row.get("score"),
+        moderation_status: moderation_status_from_row(row)?,
+        original_title: row.get("original_title"),
+        original_content: row.get("original_content"),
+    })
+}
+
+/// `moderation_status`、元の文章、`accepted` を含めて取得した行から回答を読み込む
+fn answer_from_row(row: &PgRow) -> Result<Answer, sqlx::Error> {
+    Ok(Answer {
+        id: AnswerId(row.get("id")),
+        content: row.get("content"),
+        question_id: QuestionId(row.get("question_id")),
+        score: row.get("score"),
+        accepted: row.get("accepted"),
+        moderation_status: moderation_status_from_row(row)?,
+        original_content: row.get("original_content"),
+    })
+}
+
+fn moderation_status_from_row(
+    row: &PgRow,
+) -> Result<ModerationStatus, sqlx::Error> {
+    row.get::<&str, _>("moderation_status")
+        .parse()
+        .map_err(|e: String| sqlx::Error::Decode(e.into()))
+}
+
+fn failed_logins_from_row(row: PgRow) -> FailedLogins {
+    FailedLogins {
+        count: row.get("count"),
+        last_failed_on: row.get("last_failed_on"),
+    }
+}
+
+/// 監査ログを記録する
+///
+/// 操作と同じトランザクションで記録し、記録に失敗した場合は操作も取り消す
+async fn record_audit(
+    tx: &mut Transaction<'_, Postgres>,
+    account_id: &AccountId,
+    action: AuditAction,
+    target_id: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO audit_log (account_id, action, target_id)
+        VALUES ($1, $2, $3)",
+    )
+    .bind(account_id.0)
+    .bind(action.as_str())
+    .bind(target_id)
+    .execute(tx)
+    .await?;
+
+    Ok(())
+}
+
+/// モデレーターの操作で発生したエラーを変換する
+fn moderation_error(error: sqlx::Error, not_found: Error) -> Error {
+    match error {
+        sqlx::Error::RowNotFound => not_found,
+        e => {
+            tracing::event!(tracing::Level::ERROR, "{:?}", e);
+            Error::DatabaseQueryError(e)
+        }
+    }
+}
+
+/// 一意制約違反を表す PostgreSQL のエラーコード
+const UNIQUE_VIOLATION: &str = "23505";
+
+/// アカウントの操作で発生したエラーを型付きのエラーに変換する
+///
+/// メールアドレスは一意制約を持つため、一意制約違反は登録済みのアカウントとして扱う
+fn account_error(error: sqlx::Error) -> Error {
+    match error {
+        sqlx::Error::RowNotFound => Error::AccountNotFound,
+        sqlx::Error::Database(ref e)
+            if e.code().as_deref() == Some(UNIQUE_VIOLATION) =>
+        {
+            tracing::event!(
+                tracing::Level::WARN,
+                constraint = e.constraint(),
+                "{}",
+                e.message()
+            );
+            Error::AccountAlreadyExists
+        }
+        e => {
+            tracing::event!(tracing::Level::ERROR, "{:?}", e);
+            Error::DatabaseQueryError(e)
+        }
+    }
+}
+
+/// 外部キー制約違反を表す PostgreSQL のエラーコード
+const FOREIGN_KEY_VIOLATION: &str = "23503";
+
+/// 回答の追加で発生したエラーを型付きのエラーに変換する
+///
+/// 回答先の質問が削除されている場合は外部キー制約違反になるため、
+/// インメモリのストアと同じく存在しない質問として扱う
+fn new_answer_error(error: sqlx::Error) -> Error {
+    match error {
+        sqlx::Error::Database(ref e)
+            if e.code().as_deref() == Some(FOREIGN_KEY_VIOLATION) =>
+        {
+            tracing::event!(
+                tracing::Level::WARN,
+                constraint = e.constraint(),
+                "{}",
+                e.message()
+            );
+            Error::QuestionNotFound
+        }
+        e => {
+            tracing::event!(tracing::Level::ERROR, "{:?}", e);
+            Error::DatabaseQueryError(e)
+        }
+    }
+}
+
+fn where_clause(conditions: &[String]) -> String {
+    if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    }
+}
+
+impl PostgresStore {
+    pub async fn new(db_url: &str) -> Result<Self, sqlx::Error> {
+        tracing::warn!("{}", db_url);
+
+        let db_pool = match PgPoolOptions::new()
+            .max_connections(5)
+            .connect(db_url)
+            .await
+        {
+            Ok(pool) => pool,
+            Err(e) => panic!("DBに接続できない: {}", e),
+        };
+
+        Ok(PostgresStore {
+            connection: db_pool,
+        })
+    }
+}
+
+#[async_trait]
+impl QuestionStore for PostgresStore {
+    async fn get_questions(
+        &self,
+        limit: Option<u32>,
+        offset: u32,
+        filter: QuestionFilter,
+        sort: Sort,
+    ) -> Result<Vec<Question>, Error> {
+        let condition = where_clause(&filter_conditions(&filter, 3));
+        let column = sort_column(&sort.key);
+        let direction = match sort.order {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        };
+        let sql = format!(
+            "SELECT * FROM questions {}
+            ORDER BY {} {}, id {}
+            LIMIT $1 OFFSET $2",
+            condition, column, direction, direction
+        );
+
+        let mut query = sqlx::query(&sql)
+            .bind(limit.map(i64::from))
+            .bind(i64::from(offset));
+        if let Some(tags) = filter.tags {
+            query = query.bind(tags.tags);
+        }
+
+        match query
+            .try_map(|row: PgRow| question_from_row(&row))
+            .fetch_all(&self.connection)
+            .await
+        {
+            Ok(questions) => Ok(questions),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn count_questions(
+        &self,
+        filter: QuestionFilter,
+    ) -> Result<i64, Error> {
+        let condition = where_clause(&filter_conditions(&filter, 1));
+        let sql = format!(
+            "SELECT COUNT(*) AS total FROM questions {}",
+            condition
+        );
+
+        let mut query = sqlx::query(&sql);
+        if let Some(tags) = filter.tags {
+            query = query.bind(tags.tags);
+        }
+
+        match query
+            .map(|row: PgRow| row.get("total"))
+            .fetch_one(&self.connection)
+            .await
+        {
+            Ok(total) => Ok(total),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_questions_after(
+        &self,
+        pagination: CursorPagination,
+        filter: QuestionFilter,
+    ) -> Result<CursorPage<Question>, Error> {
+        let mut conditions = Vec::new();
+        let tags_index = if pagination.cursor.is_some() {
+            conditions.push("(created_on, id) > ($2, $3)".to_string());
+            4
+        } else {
+            2
+        };
+        conditions.extend(filter_conditions(&filter, tags_index));
+        let condition = where_clause(&conditions);
+        // 次のページがあるかを判定するために1件多く取得する
+        let sql = format!(
+            "SELECT * FROM questions {}
+            ORDER BY created_on, id
+            LIMIT $1",
+            condition
+        );
+
+        let mut query =
+            sqlx::query(&sql).bind(i64::from(pagination.limit) + 1);
+        if let Some(cursor) = pagination.cursor {
+            query = query.bind(cursor.created_on).bind(cursor.id);
+        }
+        if let Some(tags) = filter.tags {
+            query = query.bind(tags.tags);
+        }
+
+        match query
+            .try_map(|row: PgRow| {
+                let cursor = Cursor {
+                    created_on: row.get("created_on"),
+                    id: row.get("id"),
+                };
+                let question = question_from_row(&row)?;
+                Ok((question, cursor))
+            })
+            .fetch_all(&self.connection)
+            .await
+        {
+            Ok(mut rows) => {
+                let has_next = rows.len() > pagination.limit as usize;
+                rows.truncate(pagination.limit as usize);
+                let next_cursor = if has_next {
+                    rows.last().map(|(_, cursor)| cursor.encode())
+                } else {
+                    None
+                };
+
+                Ok(CursorPage {
+                    items: rows.into_iter().map(|(q, _)| q).collect(),
+                    next_cursor,
+                })
+            }
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_tags(&self) -> Result<Vec<TagCount>, Error> {
+        match sqlx::query(
+            "SELECT tag AS name, COUNT(*) AS count
+            FROM questions, unnest(tags) AS tag
+            WHERE moderation_status <> 'pending_moderation'
+            GROUP BY tag
+            ORDER BY count DESC, name",
+        )
+        .map(|row: PgRow| TagCount {
+            name: row.get("name"),
+            count: row.get("count"),
+        })
+        .fetch_all(&self.connection)
+        .await
+        {
+            Ok(tags) => Ok(tags),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn search_questions(
+        &self,
+        query: String,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<QuestionSearchResult>, Error> {
+        match sqlx::query(
+            "SELECT id, title, content, tags, score, moderation_status,
+                original_title, original_content,
+                ts_rank(search, query) AS rank,
+                ts_headline('english', escape_html(title), query)
+                    AS title_highlight,
+                ts_headline('english', escape_html(content), query)
+                    AS content_highlight
+            FROM questions, websearch_to_tsquery('english', $1) query
+            WHERE search @@ query
+                AND moderation_status <> 'pending_moderation'
+            ORDER BY rank DESC, id
+            LIMIT $2 OFFSET $3",
+        )
+        .bind(query)
+        .bind(limit.map(i64::from))
+        .bind(i64::from(offset))
+        .try_map(|row: PgRow| {
+            Ok(QuestionSearchResult {
+                question: question_from_row(&row)?,
+                rank: row.get("rank"),
+                title_highlight: row.get("title_highlight"),
+                content_highlight: row.get("content_highlight"),
+            })
+        })
+        .fetch_all(&self.connection)
+        .await
+        {
+            Ok(questions) => Ok(questions),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_question(
+        &self,
+        question_id: i32,
+    ) -> Result<Question, Error> {
+        match sqlx::query(
+            "SELECT id, title, content, tags, score, moderation_status,
+                original_title, original_content
+            FROM questions
+            WHERE id = $1 AND moderation_status <> 'pending_moderation'",
+        )
+        .bind(question_id)
+        .try_map(|row: PgRow| question_from_row(&row))
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(Some(question)) => Ok(question),
+            Ok(None) => Err(Error::QuestionNotFound),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_question(
+        &self,
+        new_question: NewQuestion,
+        account_id: AccountId,
+    ) -> Result<Question, Error> {
+        match sqlx::query(
+            "INSERT INTO questions
+                (title, content, tags, account_id, moderation_status,
+                    original_title, original_content)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                RETURNING id, title, content, tags, score, moderation_status,
+                original_title, original_content",
+        )
+        .bind(new_question.title)
+        .bind(new_question.content)
+        .bind(new_question.tags)
+        .bind(account_id.0)
+        .bind(new_question.moderation_status.as_str())
+        .bind(new_question.original_title)
+        .bind(new_question.original_content)
+        .try_map(|row: PgRow| question_from_row(&row))
+        .fetch_one(&self.connection)
+        .await
+        {
+            Ok(question) => Ok(question),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn update_question(
+        &self,
+        question: Question,
+        question_id: i32,
+        account_id: AccountId,
+    ) -> Result<Question, Error> {
+        match sqlx::query(
+            "UPDATE questions
+            SET title = $1, content = $2, tags = $3, moderation_status = $4,
+                original_title = $5, original_content = $6,
+                updated_on = NOW()
+            WHERE id = $7 AND account_id = $8
+            RETURNING id, title, content, tags, score, moderation_status,
+                original_title, original_content",
+        )
+        .bind(question.title)
+        .bind(question.content)
+        .bind(question.tags)
+        .bind(question.moderation_status.as_str())
+        .bind(question.original_title)
+        .bind(question.original_content)
+        .bind(question_id)
+        .bind(account_id.0)
+        .try_map(|row: PgRow| question_from_row(&row))
+        .fetch_one(&self.connection)
+        .await
+        {
+            Ok(question) => Ok(question),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn delete_question(
+        &self,
+        question_id: i32,
+        account_id: AccountId,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "DELETE FROM questions WHERE id = $1 AND account_id = $2",
+        )
+        .bind(question_id)
+        .bind(account_id.0)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn is_question_owner(
+        &self,
+        question_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "SELECT * FROM questions WHERE id = $1 and account_id = $2",
+        )
+        .bind(question_id)
+        .bind(account_id.0)
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(question) => Ok(question.is_some()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn accept_answer(
+        &self,
+        question_id: i32,
+        answer_id: i32,
+        account_id: AccountId,
+    ) -> Result<Answer, Error> {
+        match sqlx::query(
+            "UPDATE questions
+            SET accepted_answer_id = answers.id
+            FROM answers
+            WHERE questions.id = $1 AND questions.account_id = $3
+                AND questions.moderation_status <> 'pending_moderation'
+                AND answers.id = $2 AND answers.question_id = questions.id
+                AND answers.moderation_status <> 'pending_moderation'
+            RETURNING answers.id, answers.content, answers.question_id,
+                answers.score, answers.moderation_status,
+                answers.original_content, TRUE AS accepted",
+        )
+        .bind(question_id)
+        .bind(answer_id)
+        .bind(account_id.0)
+        .try_map(|row: PgRow| answer_from_row(&row))
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(Some(answer)) => Ok(answer),
+            Ok(None) => Err(Error::AnswerNotFound),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AnswerStore for PostgresStore {
+    async fn get_answer(&self, answer_id: i32) -> Result<Answer, Error> {
+        match sqlx::query(
+            "SELECT id, content, question_id, score, moderation_status,
+                original_content,
+                EXISTS (SELECT 1 FROM questions
+                    WHERE questions.accepted_answer_id = answers.id)
+                    AS accepted
+            FROM answers
+            WHERE id = $1",
+        )
+        .bind(answer_id)
+        .try_map(|row: PgRow| answer_from_row(&row))
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(Some(answer)) => Ok(answer),
+            Ok(None) => Err(Error::AnswerNotFound),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_answers(
+        &self,
+        question_id: i32,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<Answer>, Error> {
+        match sqlx::query(
+            "SELECT id, content, question_id, score, moderation_status,
+                original_content,
+                EXISTS (SELECT 1 FROM questions
+                    WHERE questions.accepted_answer_id = answers.id)
+                    AS accepted
+            FROM answers
+            WHERE question_id = $1
+                AND moderation_status <> 'pending_moderation'
+            ORDER BY id
+            LIMIT $2 OFFSET $3",
+        )
+        .bind(question_id)
+        .bind(limit.map(i64::from))
+        .bind(i64::from(offset))
+        .try_map(|row: PgRow| answer_from_row(&row))
+        .fetch_all(&self.connection)
+        .await
+        {
+            Ok(answers) => Ok(answers),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn search_answers(
+        &self,
+        query: String,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<AnswerSearchResult>, Error> {
+        match sqlx::query(
+            "SELECT id, content, question_id, score, moderation_status,
+                original_content,
+                EXISTS (SELECT 1 FROM questions
+                    WHERE questions.accepted_answer_id = answers.id)
+                    AS accepted,
+                ts_rank(search, query) AS rank,
+                ts_headline('english', escape_html(content), query)
+                    AS content_highlight
+            FROM answers, websearch_to_tsquery('english', $1) query
+            WHERE search @@ query
+                AND moderation_status <> 'pending_moderation'
+                AND EXISTS (SELECT 1 FROM questions
+                    WHERE questions.id = answers.question_id
+                        AND questions.moderation_status
+                            <> 'pending_moderation')
+            ORDER BY rank DESC, id
+            LIMIT $2 OFFSET $3",
+        )
+        .bind(query)
+        .bind(limit.map(i64::from))
+        .bind(i64::from(offset))
+        .try_map(|row: PgRow| {
+            Ok(AnswerSearchResult {
+                answer: answer_from_row(&row)?,
+                rank: row.get("rank"),
+                content_highlight: row.get("content_highlight"),
+            })
+        })
+        .fetch_all(&self.connection)
+        .await
+        {
+            Ok(answers) => Ok(answers),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn add_answer(
+        &self,
+        new_answer: NewAnswer,
+        account_id: AccountId,
+    ) -> Result<Answer, Error> {
+        match sqlx::query(
+            "INSERT INTO answers
+                (content, question_id, account_id, moderation_status,
+                    original_content)
+            SELECT $1, id, $3, $4, $5 FROM questions
+            WHERE id = $2 AND moderation_status <> 'pending_moderation'
+            RETURNING id, content, question_id, score, moderation_status,
+                original_content,
+                FALSE AS accepted",
+        )
+        .bind(new_answer.content)
+        .bind(new_answer.question_id.0)
+        .bind(account_id.0)
+        .bind(new_answer.moderation_status.as_str())
+        .bind(new_answer.original_content)
+        .try_map(|row: PgRow| answer_from_row(&row))
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(Some(answer)) => Ok(answer),
+            // 存在しない質問と、チェックを待っている質問には回答できない
+            Ok(None) => Err(Error::QuestionNotFound),
+            Err(e) => Err(new_answer_error(e)),
+        }
+    }
+
+    async fn update_answer(
+        &self,
+        answer: Answer,
+        answer_id: i32,
+        account_id: AccountId,
+    ) -> Result<Answer, Error> {
+        match sqlx::query(
+            "UPDATE answers
+            SET content = $1, moderation_status = $2, original_content = $3
+            WHERE id = $4 AND account_id = $5
+            RETURNING id, content, question_id, score, moderation_status,
+                original_content,
+                EXISTS (SELECT 1 FROM questions
+                    WHERE questions.accepted_answer_id = answers.id)
+                    AS accepted",
+        )
+        .bind(answer.content)
+        .bind(answer.moderation_status.as_str())
+        .bind(answer.original_content)
+        .bind(answer_id)
+        .bind(account_id.0)
+        .try_map(|row: PgRow| answer_from_row(&row))
+        .fetch_one(&self.connection)
+        .await
+        {
+            Ok(answer) => Ok(answer),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn delete_answer(
+        &self,
+        answer_id: i32,
+        account_id: AccountId,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "DELETE FROM answers WHERE id = $1 AND account_id = $2",
+        )
+        .bind(answer_id)
+        .bind(account_id.0)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn is_answer_owner(
+        &self,
+        answer_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "SELECT * FROM answers WHERE id = $1 and account_id = $2",
+        )
+        .bind(answer_id)
+        .bind(account_id.0)
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(answer) => Ok(answer.is_some()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+impl PostgresStore {
+    /// 投票を反映し、対象のスコアを再計算する
+    ///
+    /// `direction` が `None` の場合は投票を取り消す
+    async fn vote(
+        &self,
+        target: &VoteTarget,
+        id: i32,
+        account_id: AccountId,
+        direction: Option<VoteDirection>,
+    ) -> Result<i64, sqlx::Error> {
+        let mut tx = self.connection.begin().await?;
+
+        // 同時に投票された場合でもスコアがずれないように対象の行をロックする
+        // チェックを待っている投稿は、存在しない投稿と同じく扱う
+        let exists = sqlx::query(&format!(
+            "SELECT id FROM {} WHERE id = $1
+                AND moderation_status <> 'pending_moderation'
+            FOR UPDATE",
+            target.table
+        ))
+        .bind(id)
+        .fetch_optional(&mut tx)
+        .await?;
+        if exists.is_none() {
+            return Err(sqlx::Error::RowNotFound);
+        }
+
+        match direction {
+            Some(direction) => {
+                sqlx::query(&format!(
+                    "INSERT INTO {table} ({column}, account_id, value)
+                    VALUES ($1, $2, $3)
+                    ON CONFLICT ({column}, account_id)
+                    DO UPDATE SET value = EXCLUDED.value",
+                    table = target.votes_table,
+                    column = target.column
+                ))
+                .bind(id)
+                .bind(account_id.0)
+                .bind(direction.value())
+                .execute(&mut tx)
+                .await?;
+            }
+            None => {
+                sqlx::query(&format!(
+                    "DELETE FROM {} WHERE {} = $1 AND account_id = $2",
+                    target.votes_table, target.column
+                ))
+                .bind(id)
+                .bind(account_id.0)
+                .execute(&mut tx)
+                .await?;
+            }
+        }
+
+        let score = sqlx::query(&format!(
+            "UPDATE {table}
+            SET score = (
+                SELECT COALESCE(SUM(value), 0) FROM {votes_table}
+                WHERE {column} = $1
+            )
+            WHERE id = $1
+            RETURNING score",
+            table = target.table,
+            votes_table = target.votes_table,
+            column = target.column
+        ))
+        .bind(id)
+        .map(|row: PgRow| row.get("score"))
+        .fetch_one(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(score)
+    }
+
+    async fn vote_or_error(
+        &self,
+        target: &VoteTarget,
+        id: i32,
+        account_id: AccountId,
+        direction: Option<VoteDirection>,
+    ) -> Result<i64, Error> {
+        match self.vote(target, id, account_id, direction).await {
+            Ok(score) => Ok(score),
+            Err(sqlx::Error::RowNotFound) => Err((target.not_found)()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl VoteStore for PostgresStore {
+    async fn vote_question(
+        &self,
+        question_id: i32,
+        account_id: AccountId,
+        direction: VoteDirection,
+    ) -> Result<i64, Error> {
+        self.vote_or_error(
+            &QUESTION_VOTES,
+            question_id,
+            account_id,
+            Some(direction),
+        )
+        .await
+    }
+
+    async fn retract_question_vote(
+        &self,
+        question_id: i32,
+        account_id: AccountId,
+    ) -> Result<i64, Error> {
+        self.vote_or_error(&QUESTION_VOTES, question_id, account_id, None)
+            .await
+    }
+
+    async fn vote_answer(
+        &self,
+        answer_id: i32,
+        account_id: AccountId,
+        direction: VoteDirection,
+    ) -> Result<i64, Error> {
+        self.vote_or_error(
+            &ANSWER_VOTES,
+            answer_id,
+            account_id,
+            Some(direction),
+        )
+        .await
+    }
+
+    async fn retract_answer_vote(
+        &self,
+        answer_id: i32,
+        account_id: AccountId,
+    ) -> Result<i64, Error> {
+        self.vote_or_error(&ANSWER_VOTES, answer_id, account_id, None)
+            .await
+    }
+}
+
+#[async_trait]
+impl AccountStore for PostgresStore {
+    async fn add_account(&self, account: Account) -> Result<bool, Error> {
+        match sqlx::query(
+            "INSERT INTO accounts (email, password, role, email_verified)
+            VALUES ($1, $2, $3, $4)",
+        )
+        .bind(account.email)
+        .bind(account.password)
+        .bind(account.role.as_str())
+        .bind(account.email_verified)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(error) => Err(account_error(error)),
+        }
+    }
+
+    async fn get_account(&self, email: String) -> Result<Account, Error> {
+        match sqlx::query("SELECT * FROM accounts WHERE email = $1")
+            .bind(email)
+            .try_map(account_from_row)
+            .fetch_one(&self.connection)
+            .await
+        {
+            Ok(account) => Ok(account),
+            Err(error) => Err(account_error(error)),
+        }
+    }
+
+    async fn get_account_by_id(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Account, Error> {
+        match sqlx::query("SELECT * FROM accounts WHERE id = $1")
+            .bind(account_id.0)
+            .try_map(account_from_row)
+            .fetch_one(&self.connection)
+            .await
+        {
+            Ok(account) => Ok(account),
+            Err(error) => Err(account_error(error)),
+        }
+    }
+
+    async fn update_password(
+        &self,
+        account_id: &AccountId,
+        password: String,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "UPDATE accounts SET password = $1 WHERE id = $2",
+        )
+        .bind(password)
+        .bind(account_id.0)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(result) if result.rows_affected() > 0 => Ok(true),
+            Ok(_) => Err(Error::AccountNotFound),
+            Err(error) => Err(account_error(error)),
+        }
+    }
+
+    async fn update_email(
+        &self,
+        account_id: &AccountId,
+        email: String,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "UPDATE accounts SET email = $1, email_verified = FALSE
+            WHERE id = $2",
+        )
+        .bind(email)
+        .bind(account_id.0)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(result) if result.rows_affected() > 0 => Ok(true),
+            Ok(_) => Err(Error::AccountNotFound),
+            Err(error) => Err(account_error(error)),
+        }
+    }
+
+    async fn delete_account(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        match self.anonymize_and_delete_account(account_id).await {
+            Ok(()) => Ok(true),
+            Err(error) => Err(account_error(error)),
+        }
+    }
+}
+
+#[async_trait]
+impl TokenStore for PostgresStore {
+    async fn revoke_token(
+        &self,
+        jti: String,
+        expires_on: DateTime<Utc>,
+    ) -> Result<bool, Error> {
+        // 有効期限を過ぎた記録はついでに削除しておく
+        if let Err(e) = sqlx::query(
+            "DELETE FROM revoked_tokens WHERE expires_on < NOW()",
+        )
+        .execute(&self.connection)
+        .await
+        {
+            tracing::event!(tracing::Level::ERROR, "{:?}", e);
+            return Err(Error::DatabaseQueryError(e));
+        }
+
+        match sqlx::query(
+            "INSERT INTO revoked_tokens (jti, expires_on)
+            VALUES ($1, $2)
+            ON CONFLICT (jti) DO NOTHING",
+        )
+        .bind(jti)
+        .bind(expires_on)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(result) => Ok(result.rows_affected() > 0),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn is_token_revoked(&self, jti: &str) -> Result<bool, Error> {
+        match sqlx::query("SELECT jti FROM revoked_tokens WHERE jti = $1")
+            .bind(jti)
+            .fetch_optional(&self.connection)
+            .await
+        {
+            Ok(row) => Ok(row.is_some()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn revoke_account_tokens(
+        &self,
+        account_id: &AccountId,
+        issued_before: DateTime<Utc>,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "INSERT INTO token_cutoffs (account_id, tokens_valid_after)
+            VALUES ($1, $2)
+            ON CONFLICT (account_id) DO UPDATE
+            SET tokens_valid_after = GREATEST(
+                token_cutoffs.tokens_valid_after,
+                EXCLUDED.tokens_valid_after
+            )",
+        )
+        .bind(account_id.0)
+        .bind(issued_before)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn tokens_valid_after(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Option<DateTime<Utc>>, Error> {
+        match sqlx::query(
+            "SELECT tokens_valid_after FROM token_cutoffs
+            WHERE account_id = $1",
+        )
+        .bind(account_id.0)
+        .map(|row: PgRow| row.get("tokens_valid_after"))
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(cutoff) => Ok(cutoff),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl PasswordResetStore for PostgresStore {
+    async fn add_password_reset(
+        &self,
+        account_id: &AccountId,
+        token_hash: String,
+        expires_on: DateTime<Utc>,
+    ) -> Result<bool, Error> {
+        // 有効期限を過ぎたトークンはついでに削除しておく
+        if let Err(e) = sqlx::query(
+            "DELETE FROM password_resets WHERE expires_on < NOW()",
+        )
+        .execute(&self.connection)
+        .await
+        {
+            tracing::event!(tracing::Level::ERROR, "{:?}", e);
+            return Err(Error::DatabaseQueryError(e));
+        }
+
+        match sqlx::query(
+            "INSERT INTO password_resets (token_hash, account_id, expires_on)
+            VALUES ($1, $2, $3)",
+        )
+        .bind(token_hash)
+        .bind(account_id.0)
+        .bind(expires_on)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn reset_password(
+        &self,
+        token_hash: &str,
+        password: String,
+    ) -> Result<AccountId, Error> {
+        match self.consume_password_reset(token_hash, password).await {
+            Ok(account_id) => Ok(account_id),
+            Err(sqlx::Error::RowNotFound) => Err(Error::InvalidResetToken),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl EmailVerificationStore for PostgresStore {
+    async fn add_email_verification(
+        &self,
+        account_id: &AccountId,
+        token_hash: String,
+        expires_on: DateTime<Utc>,
+    ) -> Result<bool, Error> {
+        // 以前に発行したトークンは無効にする
+        // メールアドレスを変更した場合に古いアドレス宛てのトークンで確認されないようにするため
+        if let Err(e) = sqlx::query(
+            "DELETE FROM email_verifications
+            WHERE account_id = $1 OR expires_on < NOW()",
+        )
+        .bind(account_id.0)
+        .execute(&self.connection)
+        .await
+        {
+            tracing::event!(tracing::Level::ERROR, "{:?}", e);
+            return Err(Error::DatabaseQueryError(e));
+        }
+
+        match sqlx::query(
+            "INSERT INTO email_verifications (token_hash, account_id, expires_on)
+            VALUES ($1, $2, $3)",
+        )
+        .bind(token_hash)
+        .bind(account_id.0)
+        .bind(expires_on)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn verify_email(
+        &self,
+        token_hash: &str,
+    ) -> Result<AccountId, Error> {
+        match self.consume_email_verification(token_hash).await {
+            Ok(account_id) => Ok(account_id),
+            Err(sqlx::Error::RowNotFound) => {
+                Err(Error::InvalidVerificationToken)
+            }
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl LoginAttemptStore for PostgresStore {
+    async fn record_login_attempt(
+        &self,
+        attempt: LoginAttempt,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "INSERT INTO login_attempts (email, ip, succeeded)
+            VALUES ($1, $2, $3)",
+        )
+        .bind(attempt.email)
+        .bind(attempt.ip)
+        .bind(attempt.succeeded)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn failed_logins_for_account(
+        &self,
+        email: &str,
+        since: DateTime<Utc>,
+    ) -> Result<FailedLogins, Error> {
+        match sqlx::query(
+            "SELECT COUNT(*) AS count, MAX(created_on) AS last_failed_on
+            FROM login_attempts
+            WHERE email = $1 AND NOT succeeded AND created_on > $2
+            AND created_on > COALESCE(
+                (SELECT MAX(created_on) FROM login_attempts
+                WHERE email = $1 AND succeeded),
+                '-infinity'
+            )",
+        )
+        .bind(email)
+        .bind(since)
+        .map(failed_logins_from_row)
+        .fetch_one(&self.connection)
+        .await
+        {
+            Ok(failed) => Ok(failed),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn failed_logins_from_ip(
+        &self,
+        ip: &str,
+        since: DateTime<Utc>,
+    ) -> Result<FailedLogins, Error> {
+        match sqlx::query(
+            "SELECT COUNT(*) AS count, MAX(created_on) AS last_failed_on
+            FROM login_attempts
+            WHERE ip = $1 AND NOT succeeded AND created_on > $2",
+        )
+        .bind(ip)
+        .bind(since)
+        .map(failed_logins_from_row)
+        .fetch_one(&self.connection)
+        .await
+        {
+            Ok(failed) => Ok(failed),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ApiKeyStore for PostgresStore {
+    async fn add_api_key(
+        &self,
+        account_id: &AccountId,
+        new_key: NewApiKey,
+        prefix: String,
+        key_hash: String,
+    ) -> Result<ApiKey, Error> {
+        match sqlx::query(
+            "INSERT INTO api_keys (account_id, name, scope, prefix, key_hash)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, account_id, name, scope, prefix, created_on, last_used_on",
+        )
+        .bind(account_id.0)
+        .bind(new_key.name)
+        .bind(new_key.scope.as_str())
+        .bind(prefix)
+        .bind(key_hash)
+        .try_map(api_key_from_row)
+        .fetch_one(&self.connection)
+        .await
+        {
+            Ok(api_key) => Ok(api_key),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_api_keys(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Vec<ApiKey>, Error> {
+        match sqlx::query(
+            "SELECT id, account_id, name, scope, prefix, created_on, last_used_on
+            FROM api_keys
+            WHERE account_id = $1
+            ORDER BY id",
+        )
+        .bind(account_id.0)
+        .try_map(api_key_from_row)
+        .fetch_all(&self.connection)
+        .await
+        {
+            Ok(api_keys) => Ok(api_keys),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn revoke_api_key(
+        &self,
+        key_id: i32,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "DELETE FROM api_keys WHERE id = $1 AND account_id = $2",
+        )
+        .bind(key_id)
+        .bind(account_id.0)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(result) if result.rows_affected() > 0 => Ok(true),
+            Ok(_) => Err(Error::ApiKeyNotFound),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn revoke_account_api_keys(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<u64, Error> {
+        match sqlx::query("DELETE FROM api_keys WHERE account_id = $1")
+            .bind(account_id.0)
+            .execute(&self.connection)
+            .await
+        {
+            Ok(result) => Ok(result.rows_affected()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn authenticate_api_key(
+        &self,
+        key_hash: &str,
+    ) -> Result<ApiKey, Error> {
+        match sqlx::query(
+            "UPDATE api_keys SET last_used_on = NOW()
+            WHERE key_hash = $1
+            RETURNING id, account_id, name, scope, prefix, created_on, last_used_on",
+        )
+        .bind(key_hash)
+        .try_map(api_key_from_row)
+        .fetch_one(&self.connection)
+        .await
+        {
+            Ok(api_key) => Ok(api_key),
+            Err(sqlx::Error::RowNotFound) => Err(Error::InvalidApiKey),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+impl PostgresStore {
+    async fn update_question_audited(
+        &self,
+        question: Question,
+        question_id: i32,
+        moderator: AccountId,
+    ) -> Result<Question, sqlx::Error> {
+        let mut tx = self.connection.begin().await?;
+
+        let question = sqlx::query(
+            "UPDATE questions
+            SET title = $1, content = $2, tags = $3, moderation_status = $4,
+                original_title = $5, original_content = $6,
+                updated_on = NOW()
+            WHERE id = $7
+            RETURNING id, title, content, tags, score, moderation_status,
+                original_title, original_content",
+        )
+        .bind(question.title)
+        .bind(question.content)
+        .bind(question.tags)
+        .bind(question.moderation_status.as_str())
+        .bind(question.original_title)
+        .bind(question.original_content)
+        .bind(question_id)
+        .try_map(|row: PgRow| question_from_row(&row))
+        .fetch_one(&mut tx)
+        .await?;
+        record_audit(
+            &mut tx,
+            &moderator,
+            AuditAction::UpdateQuestion,
+            question_id,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(question)
+    }
+
+    async fn update_answer_audited(
+        &self,
+        answer: Answer,
+        answer_id: i32,
+        moderator: AccountId,
+    ) -> Result<Answer, sqlx::Error> {
+        let mut tx = self.connection.begin().await?;
+
+        let answer = sqlx::query(
+            "UPDATE answers
+            SET content = $1, moderation_status = $2, original_content = $3
+            WHERE id = $4
+            RETURNING id, content, question_id, score, moderation_status,
+                original_content,
+                EXISTS (SELECT 1 FROM questions
+                    WHERE questions.accepted_answer_id = answers.id)
+                    AS accepted",
+        )
+        .bind(answer.content)
+        .bind(answer.moderation_status.as_str())
+        .bind(answer.original_content)
+        .bind(answer_id)
+        .try_map(|row: PgRow| answer_from_row(&row))
+        .fetch_one(&mut tx)
+        .await?;
+        record_audit(
+            &mut tx,
+            &moderator,
+            AuditAction::UpdateAnswer,
+            answer_id,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(answer)
+    }
+
+    /// 行を削除し、監査ログに記録する
+    ///
+    /// `table` には固定のテーブル名のみを渡す
+    async fn delete_audited(
+        &self,
+        table: &str,
+        id: i32,
+        account_id: AccountId,
+        action: AuditAction,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.connection.begin().await?;
+
+        let deleted =
+            sqlx::query(&format!("DELETE FROM {} WHERE id = $1", table))
+                .bind(id)
+                .execute(&mut tx)
+                .await?;
+        if deleted.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound);
+        }
+        record_audit(&mut tx, &account_id, action, id).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn change_role_audited(
+        &self,
+        account_id: i32,
+        role: Role,
+        admin: AccountId,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.connection.begin().await?;
+
+        let updated =
+            sqlx::query("UPDATE accounts SET role = $1 WHERE id = $2")
+                .bind(role.as_str())
+                .bind(account_id)
+                .execute(&mut tx)
+                .await?;
+        if updated.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound);
+        }
+        record_audit(&mut tx, &admin, AuditAction::ChangeRole, account_id)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ModerationStore for PostgresStore {
+    async fn get_moderated_question(
+        &self,
+        question_id: i32,
+    ) -> Result<Question, Error> {
+        match sqlx::query(
+            "SELECT id, title, content, tags, score, moderation_status,
+                original_title, original_content
+            FROM questions WHERE id = $1",
+        )
+        .bind(question_id)
+        .try_map(|row: PgRow| question_from_row(&row))
+        .fetch_one(&self.connection)
+        .await
+        {
+            Ok(question) => Ok(question),
+            Err(e) => Err(moderation_error(e, Error::QuestionNotFound)),
+        }
+    }
+
+    async fn moderate_question(
+        &self,
+        question: Question,
+        question_id: i32,
+        moderator: AccountId,
+    ) -> Result<Question, Error> {
+        self.update_question_audited(question, question_id, moderator)
+            .await
+            .map_err(|e| moderation_error(e, Error::QuestionNotFound))
+    }
+
+    async fn remove_question(
+        &self,
+        question_id: i32,
+        moderator: AccountId,
+    ) -> Result<bool, Error> {
+        self.delete_audited(
+            "questions",
+            question_id,
+            moderator,
+            AuditAction::DeleteQuestion,
+        )
+        .await
+        .map(|_| true)
+        .map_err(|e| moderation_error(e, Error::QuestionNotFound))
+    }
+
+    async fn moderate_answer(
+        &self,
+        answer: Answer,
+        answer_id: i32,
+        moderator: AccountId,
+    ) -> Result<Answer, Error> {
+        self.update_answer_audited(answer, answer_id, moderator)
+            .await
+            .map_err(|e| moderation_error(e, Error::AnswerNotFound))
+    }
+
+    async fn remove_answer(
+        &self,
+        answer_id: i32,
+        moderator: AccountId,
+    ) -> Result<bool, Error> {
+        self.delete_audited(
+            "answers",
+            answer_id,
+            moderator,
+            AuditAction::DeleteAnswer,
+        )
+        .await
+        .map(|_| true)
+        .map_err(|e| moderation_error(e, Error::AnswerNotFound))
+    }
+
+    async fn change_role(
+        &self,
+        account_id: i32,
+        role: Role,
+        admin: AccountId,
+    ) -> Result<bool, Error> {
+        self.change_role_audited(account_id, role, admin)
+            .await
+            .map(|_| true)
+            .map_err(|e| moderation_error(e, Error::AccountNotFound))
+    }
+
+    async fn get_audit_log(
+        &self,
+        limit: Option<u32>,
+        offset: u32,
+    ) -> Result<Vec<AuditRecord>, Error> {
+        match sqlx::query(
+            "SELECT id, account_id, action, target_id, created_on
+            FROM audit_log
+            ORDER BY id DESC
+            LIMIT $1 OFFSET $2",
+        )
+        .bind(limit.map(i64::from))
+        .bind(i64::from(offset))
+        .try_map(|row: PgRow| {
+            Ok(AuditRecord {
+                id: row.get("id"),
+                account_id: AccountId(row.get("account_id")),
+                action: row
+                    .get::<&str, _>("action")
+                    .parse()
+                    .map_err(|e: String| sqlx::Error::Decode(e.into()))?,
+                target_id: row.get("target_id"),
+                created_on: row.get("created_on"),
+            })
+        })
+        .fetch_all(&self.connection)
+        .await
+        {
+            Ok(records) => Ok(records),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn get_moderation_queue(
+        &self,
+    ) -> Result<ModerationQueue, Error> {
+        let questions = sqlx::query(
+            "SELECT id, title, content, tags, score, moderation_status,
+                original_title, original_content
+            FROM questions
+            WHERE moderation_status <> 'approved'
+            ORDER BY id",
+        )
+        .try_map(|row: PgRow| question_from_row(&row))
+        .fetch_all(&self.connection);
+        let answers = sqlx::query(
+            "SELECT id, content, question_id, score, moderation_status,
+                original_content,
+                EXISTS (SELECT 1 FROM questions
+                    WHERE questions.accepted_answer_id = answers.id)
+                    AS accepted
+            FROM answers
+            WHERE moderation_status <> 'approved'
+            ORDER BY id",
+        )
+        .try_map(|row: PgRow| answer_from_row(&row))
+        .fetch_all(&self.connection);
+
+        match tokio::try_join!(questions, answers) {
+            Ok((questions, answers)) => {
+                Ok(ModerationQueue { questions, answers })
+            }
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn recheck_question(
+        &self,
+        original: &Question,
+        checked: Question,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "UPDATE questions
+            SET title = $1, content = $2, moderation_status = $3,
+                original_title = $4, original_content = $5
+            WHERE id = $6 AND moderation_status = 'pending_moderation'
+                AND title = $7 AND content = $8",
+        )
+        .bind(checked.title)
+        .bind(checked.content)
+        .bind(checked.moderation_status.as_str())
+        .bind(checked.original_title)
+        .bind(checked.original_content)
+        .bind(original.id.0)
+        .bind(&original.title)
+        .bind(&original.content)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(result) => Ok(result.rows_affected() > 0),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+
+    async fn recheck_answer(
+        &self,
+        original: &Answer,
+        checked: Answer,
+    ) -> Result<bool, Error> {
+        match sqlx::query(
+            "UPDATE answers
+            SET content = $1, moderation_status = $2, original_content = $3
+            WHERE id = $4 AND moderation_status = 'pending_moderation'
+                AND content = $5",
+        )
+        .bind(checked.content)
+        .bind(checked.moderation_status.as_str())
+        .bind(checked.original_content)
+        .bind(original.id.0)
+        .bind(&original.content)
+        .execute(&self.connection)
+        .await
+        {
+            Ok(result) => Ok(result.rows_affected() > 0),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", e);
+                Err(Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+impl PostgresStore {
+    /// 質問と回答の所有者を外してからアカウントを削除する
+    async fn anonymize_and_delete_account(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.connection.begin().await?;
+
+        for table in ["questions", "answers"] {
+            sqlx::query(&format!(
+                "UPDATE {} SET account_id = NULL WHERE account_id = $1",
+                table
+            ))
+            .bind(account_id.0)
+            .execute(&mut tx)
+            .await?;
+        }
+
+        let deleted = sqlx::query("DELETE FROM accounts WHERE id = $1")
+            .bind(account_id.0)
+            .execute(&mut tx)
+            .await?;
+        if deleted.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound);
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// トークンを削除してパスワードを更新する
+    ///
+    /// 削除と更新を同じトランザクションで行うため、トークンは一度しか使えない
+    async fn consume_password_reset(
+        &self,
+        token_hash: &str,
+        password: String,
+    ) -> Result<AccountId, sqlx::Error> {
+        let mut tx = self.connection.begin().await?;
+
+        let account_id = sqlx::query(
+            "DELETE FROM password_resets
+            WHERE token_hash = $1 AND expires_on > NOW()
+            RETURNING account_id",
+        )
+        .bind(token_hash)
+        .map(|row: PgRow| AccountId(row.get("account_id")))
+        .fetch_one(&mut tx)
+        .await?;
+
+        sqlx::query("DELETE FROM password_resets WHERE account_id = $1")
+            .bind(account_id.0)
+            .execute(&mut tx)
+            .await?;
+
+        sqlx::query("UPDATE accounts SET password = $1 WHERE id = $2")
+            .bind(password)
+            .bind(account_id.0)
+            .execute(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(account_id)
+    }
+
+    /// トークンを削除してアカウントを確認済みにする
+    async fn consume_email_verification(
+        &self,
+        token_hash: &str,
+    ) -> Result<AccountId, sqlx::Error> {
+        let mut tx = self.connection.begin().await?;
+
+        let account_id = sqlx::query(
+            "DELETE FROM email_verifications
+            WHERE token_hash = $1 AND expires_on > NOW()
+            RETURNING account_id",
+        )
+        .bind(token_hash)
+        .map(|row: PgRow| AccountId(row.get("account_id")))
+        .fetch_one(&mut tx)
+        .await?;
+
+        sqlx::query(
+            "UPDATE accounts SET email_verified = TRUE WHERE id = $1",
+        )
+        .bind(account_id.0)
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(account_id)
+    }
+}
+
+#[cfg(test)]
+mod postgres_tests {
+    use super::{filter_conditions, sort_column};
+    use crate::types::{filter::QuestionFilter, sort::SortKey};
+
+    const HIDE_PENDING_ANSWERS: &str =
+        "answers.moderation_status <> 'pending_moderation'";
+
+    #[test]
+    fn ignore_pending_answers_when_filtering_unanswered() {
+        for unanswered in [true, false] {
+            let filter = QuestionFilter {
+                unanswered: Some(unanswered),
+                ..QuestionFilter::default()
+            };
+            let conditions = filter_conditions(&filter, 1);
+
+            assert!(
+                conditions
+                    .iter()
+                    .any(|condition| condition
+                        .contains(HIDE_PENDING_ANSWERS))
+            );
+        }
+    }
+
+    #[test]
+    fn ignore_pending_answers_when_sorting_by_answers() {
+        assert!(
+            sort_column(&SortKey::Answers).contains(HIDE_PENDING_ANSWERS)
+        );
+    }
+}
@@ -0,0 +1,35 @@
+//! 複数のモジュールのテストで共有する部品
+
+use async_trait::async_trait;
+
+use crate::{
+    profanity::{Censored, CircuitState, ProfanityFilter},
+    types::account::{AccountId, Role, Session, TokenKind},
+};
+use handle_errors::Error;
+
+/// サーキットブレーカーが開いて外部のAPIが使えない状態を再現する
+#[derive(Debug)]
+pub(crate) struct UnavailableFilter;
+
+#[async_trait]
+impl ProfanityFilter for UnavailableFilter {
+    async fn censor(&self, _content: String) -> Result<Censored, Error> {
+        Err(Error::CircuitOpen(30))
+    }
+
+    fn circuit_state(&self) -> Option<CircuitState> {
+        Some(CircuitState::Open)
+    }
+}
+
+/// トークンで認証済みのセッション
+pub(crate) fn session(account_id: i32, role: Role) -> Session {
+    Session {
+        exp: chrono::Utc::now() + chrono::Duration::minutes(1),
+        account_id: AccountId(account_id),
+        jti: "jti".to_string(),
+        kind: TokenKind::Access,
+        role,
+    }
+}
@@ -1,10 +1,55 @@
+use std::str::FromStr;
+
 use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
 
+/// トークンの種類
+///
+/// リフレッシュトークンをアクセストークンとして使えないように区別する
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenKind {
+    Access,
+    Refresh,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Session {
     pub exp: DateTime<Utc>,
     pub account_id: AccountId,
+    /// トークンID
+    ///
+    /// 同時に発行したアクセストークンとリフレッシュトークンは同じ値を持ち、
+    /// 失効させる場合は両方がまとめて無効になる
+    pub jti: String,
+    pub kind: TokenKind,
+    pub role: Role,
+}
+
+/// APIキーで認証した場合のトークンIDの接頭辞
+///
+/// APIキーにはトークンが無いため、`api-key-<キーのID>` をトークンIDとして扱う
+pub const API_KEY_JTI_PREFIX: &str = "api-key-";
+
+impl Session {
+    /// `X-API-Key` ヘッダーのAPIキーで認証したかを返す
+    pub fn is_api_key(&self) -> bool {
+        self.jti.starts_with(API_KEY_JTI_PREFIX)
+    }
+}
+
+/// ログイン時やトークンの更新時に返すトークンの組
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// アクセストークンの有効期間 (秒)
+    pub expires_in: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RefreshToken {
+    pub refresh_token: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -12,6 +57,120 @@ pub struct Account {
     pub id: AccountId,
     pub email: String,
     pub password: String,
+    /// 登録時に権限を指定できないように、リクエストの値は読み込まない
+    #[serde(default, skip_deserializing)]
+    pub role: Role,
+    /// メールアドレスの確認は `/verify` でのみ行う
+    #[serde(default, skip_deserializing)]
+    pub email_verified: bool,
+}
+
+/// アカウントの権限
+///
+/// 上位の権限は下位の権限でできることを全て行える
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    User,
+    /// 全ての質問と回答を編集・削除できる
+    Moderator,
+    /// アカウントの権限を変更し、監査ログを閲覧できる
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Moderator => "moderator",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl FromStr for Role {
+    /// データベースに保存された値を読み込む際に使用する
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "user" => Ok(Role::User),
+            "moderator" => Ok(Role::Moderator),
+            "admin" => Ok(Role::Admin),
+            _ => Err(format!("unknown role: {}", s)),
+        }
+    }
+}
+
+/// 権限の変更
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NewRole {
+    pub role: Role,
+}
+
+/// `GET /me` で返すアカウントの情報 (パスワードは含めない)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Profile {
+    pub id: AccountId,
+    pub email: String,
+    pub role: Role,
+    pub email_verified: bool,
+}
+
+impl From<Account> for Profile {
+    fn from(account: Account) -> Self {
+        Profile {
+            id: account.id,
+            email: account.email,
+            role: account.role,
+            email_verified: account.email_verified,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PasswordChange {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+/// メールアドレスの変更
+///
+/// 本人であることを確認するため、現在のパスワードも送る
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EmailChange {
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountDeletion {
+    pub password: String,
+}
+
+/// パスワードリセットの申請
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ForgotPassword {
+    pub email: String,
+}
+
+/// メールで受け取ったトークンを使ったパスワードの再設定
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResetPassword {
+    pub token: String,
+    pub password: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -28,3 +187,64 @@ pub struct NewAccount {
     pub email: String,
     pub password: String,
 }
+
+/// メールアドレスの書式を検証する
+///
+/// `local@domain` の形で、ドメインはドットで区切られた空でないラベルからなる。
+/// 実際に届くかどうかは確認メールで検証する
+pub fn validate_email(email: &str) -> Result<(), handle_errors::Error> {
+    let invalid = || handle_errors::Error::InvalidEmail(email.to_string());
+
+    if email.len() > 254 || email.chars().any(char::is_whitespace) {
+        return Err(invalid());
+    }
+
+    let (local, domain) = email.rsplit_once('@').ok_or_else(invalid)?;
+    if local.is_empty() || local.len() > 64 || local.contains('@') {
+        return Err(invalid());
+    }
+
+    let labels: Vec<&str> = domain.split('.').collect();
+    let valid_label = |label: &&str| {
+        !label.is_empty()
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+    };
+    if labels.len() < 2 || !labels.iter().all(valid_label) {
+        return Err(invalid());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod account_tests {
+    use super::validate_email;
+
+    #[test]
+    fn validate_email_syntax() {
+        for email in
+            ["test@example.com", "first.last+tag@mail.example.co.jp"]
+        {
+            assert!(validate_email(email).is_ok(), "{}", email);
+        }
+
+        for email in [
+            "",
+            "test",
+            "@example.com",
+            "test@",
+            "test@localhost",
+            "test@example..com",
+            "test@-example.com",
+            "te st@example.com",
+            "test@@example.com",
+        ] {
+            assert!(matches!(
+                validate_email(email),
+                Err(handle_errors::Error::InvalidEmail(_))
+            ));
+        }
+    }
+}
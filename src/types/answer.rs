@@ -1,4 +1,4 @@
-use crate::types::question::QuestionId;
+use crate::types::{moderation::ModerationStatus, question::QuestionId};
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
@@ -9,10 +9,41 @@ pub struct Answer {
     pub id: AnswerId,
     pub content: String,
     pub question_id: QuestionId,
+    /// 投票の合計値 (リクエストで指定された値は使用しない)
+    #[serde(default)]
+    pub score: i64,
+    /// 質問の投稿者に採用された回答か (リクエストで指定された値は使用しない)
+    #[serde(default)]
+    pub accepted: bool,
+    /// 不適切な単語のチェックの状況
+    /// (リクエストで指定された値は使用せず、モデレーター向けのためレスポンスには含めない)
+    #[serde(default, skip_serializing)]
+    pub moderation_status: ModerationStatus,
+    /// 伏せ字に置き換える前の本文 (モデレーター向けで、レスポンスには含めない)
+    #[serde(skip)]
+    pub original_content: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct NewAnswer {
     pub content: String,
     pub question_id: QuestionId,
+    /// 不適切な単語のチェックの状況 (リクエストで指定された値は使用しない)
+    #[serde(default)]
+    pub moderation_status: ModerationStatus,
+    /// 伏せ字に置き換える前の本文 (モデレーター向けで、レスポンスには含めない)
+    #[serde(skip)]
+    pub original_content: Option<String>,
+}
+
+/// 全文検索でヒットした回答
+///
+/// `content_highlight` は一致した語を `<b>` タグで囲んだ抜粋。
+/// 投稿に含まれるHTMLはエスケープしてある
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AnswerSearchResult {
+    #[serde(flatten)]
+    pub answer: Answer,
+    pub rank: f32,
+    pub content_highlight: String,
 }
@@ -0,0 +1,79 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use warp::http::Method;
+
+use crate::types::account::AccountId;
+
+/// APIキーで行える操作の範囲
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKeyScope {
+    /// 参照のみ (`GET` と `HEAD`)
+    Read,
+    /// 参照に加えて作成・更新・削除も行える
+    Write,
+}
+
+impl ApiKeyScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiKeyScope::Read => "read",
+            ApiKeyScope::Write => "write",
+        }
+    }
+
+    /// リクエストのメソッドがこの範囲で許可されているかを返す
+    pub fn allows(&self, method: &Method) -> bool {
+        match self {
+            ApiKeyScope::Read => {
+                method == Method::GET || method == Method::HEAD
+            }
+            ApiKeyScope::Write => true,
+        }
+    }
+}
+
+impl FromStr for ApiKeyScope {
+    /// データベースに保存された値を読み込む際に使用する
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(ApiKeyScope::Read),
+            "write" => Ok(ApiKeyScope::Write),
+            _ => Err(format!("unknown api key scope: {}", s)),
+        }
+    }
+}
+
+/// ボットなどのクライアントがパスワードの代わりに使用するAPIキー
+///
+/// キー自体は保存せず、ハッシュと見分けるための先頭の数文字のみを保持する
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApiKey {
+    pub id: i32,
+    pub account_id: AccountId,
+    pub name: String,
+    pub scope: ApiKeyScope,
+    /// キーの先頭の数文字
+    pub prefix: String,
+    pub created_on: NaiveDateTime,
+    pub last_used_on: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NewApiKey {
+    pub name: String,
+    pub scope: ApiKeyScope,
+}
+
+/// APIキーの作成時に返す情報
+///
+/// キーはこのレスポンスでしか返さない
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub key: String,
+}
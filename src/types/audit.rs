@@ -0,0 +1,56 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::types::account::AccountId;
+
+/// 監査ログに記録する操作
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    UpdateQuestion,
+    DeleteQuestion,
+    UpdateAnswer,
+    DeleteAnswer,
+    ChangeRole,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::UpdateQuestion => "update_question",
+            AuditAction::DeleteQuestion => "delete_question",
+            AuditAction::UpdateAnswer => "update_answer",
+            AuditAction::DeleteAnswer => "delete_answer",
+            AuditAction::ChangeRole => "change_role",
+        }
+    }
+}
+
+impl FromStr for AuditAction {
+    /// データベースに保存された値を読み込む際に使用する
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "update_question" => Ok(AuditAction::UpdateQuestion),
+            "delete_question" => Ok(AuditAction::DeleteQuestion),
+            "update_answer" => Ok(AuditAction::UpdateAnswer),
+            "delete_answer" => Ok(AuditAction::DeleteAnswer),
+            "change_role" => Ok(AuditAction::ChangeRole),
+            _ => Err(format!("unknown audit action: {}", s)),
+        }
+    }
+}
+
+/// モデレーターや管理者が他人のリソースに対して行った操作の記録
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditRecord {
+    pub id: i32,
+    /// 操作したアカウント
+    pub account_id: AccountId,
+    pub action: AuditAction,
+    /// 操作の対象となった質問・回答・アカウントのID
+    pub target_id: i32,
+    pub created_on: NaiveDateTime,
+}
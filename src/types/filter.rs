@@ -0,0 +1,94 @@
+use handle_errors::Error;
+
+use crate::types::tag::{extract_tag_filter, TagFilter};
+
+/// 質問の一覧の絞り込み条件
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuestionFilter {
+    pub tags: Option<TagFilter>,
+    /// `true` なら回答のない質問、`false` なら回答のある質問に絞り込む
+    pub unanswered: Option<bool>,
+    /// `true` なら回答が採用された質問、`false` なら未解決の質問に絞り込む
+    pub accepted: Option<bool>,
+}
+
+/// "/questions" パスのクエリパラメータから絞り込みの条件を抽出する
+/// # サンプルクエリ
+/// `/questions?tag=rust&unanswered=true`
+/// `/questions?accepted=false`
+pub fn extract_question_filter(
+    params: &[(String, String)],
+) -> Result<QuestionFilter, Error> {
+    Ok(QuestionFilter {
+        tags: extract_tag_filter(params)?,
+        unanswered: extract_bool(params, "unanswered")?,
+        accepted: extract_bool(params, "accepted")?,
+    })
+}
+
+/// 同じキーが複数ある場合は最後の値を使う
+fn extract_bool(
+    params: &[(String, String)],
+    name: &str,
+) -> Result<Option<bool>, Error> {
+    match params.iter().rev().find(|(key, _)| key == name) {
+        None => Ok(None),
+        Some((_, value)) => match value.as_str() {
+            "true" => Ok(Some(true)),
+            "false" => Ok(Some(false)),
+            _ => {
+                Err(Error::InvalidFilter(name.to_string(), value.clone()))
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod filter_tests {
+    use super::{extract_question_filter, QuestionFilter};
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn no_filter() {
+        let filter = extract_question_filter(&params(&[])).unwrap();
+
+        assert_eq!(filter, QuestionFilter::default());
+    }
+
+    #[test]
+    fn answer_filters() {
+        let filter = extract_question_filter(&params(&[
+            ("unanswered", "true"),
+            ("accepted", "false"),
+        ]))
+        .unwrap();
+
+        assert_eq!(filter.unanswered, Some(true));
+        assert_eq!(filter.accepted, Some(false));
+        assert_eq!(filter.tags, None);
+    }
+
+    #[test]
+    fn invalid_filter_value() {
+        let error = format!(
+            "{}",
+            extract_question_filter(&params(&[("unanswered", "yes")]))
+                .unwrap_err()
+        );
+
+        let expected = format!(
+            "{}",
+            handle_errors::Error::InvalidFilter(
+                String::from("unanswered"),
+                String::from("yes")
+            )
+        );
+        assert_eq!(error, expected);
+    }
+}
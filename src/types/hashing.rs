@@ -0,0 +1,124 @@
+use argon2::{Variant, Version};
+use rand::Rng;
+
+/// パスワードのハッシュ化に使用する Argon2 のパラメータ
+///
+/// 既定値は `argon2::Config::default()` と同じ。
+/// パラメータを変更した場合、以前のパラメータのハッシュはログイン時に作り直す
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordHashing {
+    pub variant: Variant,
+    /// 使用するメモリ (KiB)
+    pub mem_cost: u32,
+    /// 反復回数
+    pub time_cost: u32,
+    /// 並列度
+    pub lanes: u32,
+}
+
+impl Default for PasswordHashing {
+    fn default() -> Self {
+        let config = argon2::Config::default();
+        PasswordHashing {
+            variant: config.variant,
+            mem_cost: config.mem_cost,
+            time_cost: config.time_cost,
+            lanes: config.lanes,
+        }
+    }
+}
+
+impl PasswordHashing {
+    pub fn hash(&self, password: &[u8]) -> Result<String, argon2::Error> {
+        let salt = rand::thread_rng().gen::<[u8; 32]>();
+        argon2::hash_encoded(password, &salt, &self.config())
+    }
+
+    /// パラメータの組み合わせで実際にハッシュを作れるか確かめる
+    ///
+    /// 並列度が 0 の場合やメモリが並列度の 8 倍に満たない場合などは、
+    /// 最初の登録やログインまで気付けないため起動時に確かめる
+    pub fn validate(&self) -> Result<(), argon2::Error> {
+        self.hash(b"password").map(|_| ())
+    }
+
+    /// ハッシュが現在のパラメータで作られていない場合に `true` を返す
+    ///
+    /// ハッシュは `$argon2i$v=19$m=4096,t=3,p=1$<salt>$<hash>` の形式で、
+    /// 読み取れない場合も作り直す対象とする
+    pub fn needs_rehash(&self, encoded: &str) -> bool {
+        let expected_params = format!(
+            "m={},t={},p={}",
+            self.mem_cost, self.time_cost, self.lanes
+        );
+        let expected_version =
+            format!("v={}", self.config().version.as_u32());
+
+        match encoded.split('$').collect::<Vec<_>>().as_slice() {
+            ["", variant, version, params, _salt, _hash] => {
+                *variant != self.variant.as_lowercase_str()
+                    || *version != expected_version
+                    || *params != expected_params
+            }
+            _ => true,
+        }
+    }
+
+    fn config(&self) -> argon2::Config<'static> {
+        argon2::Config {
+            variant: self.variant,
+            version: Version::Version13,
+            mem_cost: self.mem_cost,
+            time_cost: self.time_cost,
+            lanes: self.lanes,
+            ..argon2::Config::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod hashing_tests {
+    use argon2::Variant;
+
+    use super::PasswordHashing;
+
+    #[test]
+    fn detect_outdated_hashes() {
+        let current = PasswordHashing::default();
+        let hash = current.hash(b"password").unwrap();
+
+        assert!(argon2::verify_encoded(&hash, b"password").unwrap());
+        assert!(!current.needs_rehash(&hash));
+
+        let stronger = PasswordHashing {
+            variant: Variant::Argon2id,
+            mem_cost: 8192,
+            ..current
+        };
+        assert!(stronger.needs_rehash(&hash));
+
+        let rehashed = stronger.hash(b"password").unwrap();
+        assert!(rehashed.starts_with("$argon2id$v=19$m=8192,t=3,p=1$"));
+        assert!(!stronger.needs_rehash(&rehashed));
+
+        assert!(current.needs_rehash("not a hash"));
+    }
+
+    #[test]
+    fn reject_invalid_parameters() {
+        assert!(PasswordHashing::default().validate().is_ok());
+
+        let no_lanes = PasswordHashing {
+            lanes: 0,
+            ..PasswordHashing::default()
+        };
+        assert!(no_lanes.validate().is_err());
+
+        let too_little_memory = PasswordHashing {
+            mem_cost: 8 * 4 - 1,
+            lanes: 4,
+            ..PasswordHashing::default()
+        };
+        assert!(too_little_memory.validate().is_err());
+    }
+}
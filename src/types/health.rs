@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+use crate::profanity::{CacheStats, CircuitState};
+
+/// サービス全体の状態
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Ok,
+    /// 外部APIが使えないため、一部の機能が失敗する
+    Degraded,
+}
+
+/// `GET /health` で返す情報
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Health {
+    pub status: HealthStatus,
+    /// 不適切な単語の検出に外部APIを使用しない場合は `None`
+    pub profanity_circuit: Option<CircuitState>,
+    /// チェック結果をキャッシュしない場合は `None`
+    pub profanity_cache: Option<CacheStats>,
+}
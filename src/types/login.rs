@@ -0,0 +1,164 @@
+use chrono::{DateTime, Duration, Utc};
+
+/// 監査用に記録するログインの試行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoginAttempt {
+    pub email: String,
+    /// 接続元のIPアドレス (取得できない場合は `None`)
+    pub ip: Option<String>,
+    pub succeeded: bool,
+}
+
+/// 集計期間内に失敗したログインの回数
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FailedLogins {
+    pub count: i64,
+    pub last_failed_on: Option<DateTime<Utc>>,
+}
+
+/// ログインの試行回数の制限
+///
+/// 失敗が上限に達すると一定時間ログインを受け付けず、
+/// その後も失敗するたびにロックする時間を倍にする
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoginPolicy {
+    /// アカウントごとに許容する連続した失敗の回数
+    pub max_attempts: u32,
+    /// IPアドレスごとに許容する失敗の回数
+    pub max_attempts_per_ip: u32,
+    /// 上限に達した際に最初にロックする時間 (秒)
+    pub lockout_seconds: u64,
+    /// ロックする時間の上限 (秒)
+    pub max_lockout_seconds: u64,
+}
+
+impl Default for LoginPolicy {
+    fn default() -> Self {
+        LoginPolicy {
+            max_attempts: 5,
+            max_attempts_per_ip: 50,
+            lockout_seconds: 60,
+            max_lockout_seconds: 3600,
+        }
+    }
+}
+
+impl LoginPolicy {
+    /// 失敗の回数を数える期間の始まり
+    ///
+    /// ロックする時間の上限より前の失敗は数えない。
+    /// 日時の範囲を超える場合は全ての失敗を数える
+    pub fn window_start(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        seconds(self.max_lockout_seconds)
+            .and_then(|window| now.checked_sub_signed(window))
+            .unwrap_or(DateTime::<Utc>::MIN_UTC)
+    }
+
+    /// ロック中であれば、ログインを再開できるまでの秒数を返す
+    pub fn retry_after(
+        &self,
+        failed: &FailedLogins,
+        max_attempts: u32,
+        now: DateTime<Utc>,
+    ) -> Option<u64> {
+        let excess = failed.count - i64::from(max_attempts);
+        if excess < 0 {
+            return None;
+        }
+
+        // 上限に達した回数から1回失敗するごとにロックする時間を倍にする
+        let lockout = 2u64
+            .checked_pow(excess.min(63) as u32)
+            .and_then(|factor| factor.checked_mul(self.lockout_seconds))
+            .unwrap_or(u64::MAX)
+            .min(self.max_lockout_seconds);
+
+        let last_failed_on = failed.last_failed_on?;
+        // 日時の範囲を超える場合はロックの時間をそのまま返す
+        let locked_until = match seconds(lockout)
+            .and_then(|lockout| last_failed_on.checked_add_signed(lockout))
+        {
+            Some(locked_until) => locked_until,
+            None => return Some(lockout),
+        };
+        let remaining = (locked_until - now).num_milliseconds();
+
+        // `Retry-After` は秒単位のため、1秒未満の残りは切り上げる
+        (remaining > 0).then(|| (remaining as u64).div_ceil(1000))
+    }
+}
+
+/// 範囲を超える場合に panic する `Duration::seconds` の代わりに使う
+fn seconds(seconds: u64) -> Option<Duration> {
+    Duration::from_std(std::time::Duration::from_secs(seconds)).ok()
+}
+
+#[cfg(test)]
+mod login_tests {
+    use chrono::{DateTime, Duration, Utc};
+
+    use super::{FailedLogins, LoginPolicy};
+
+    fn failed(
+        count: i64,
+        seconds_ago: i64,
+        now: DateTime<Utc>,
+    ) -> FailedLogins {
+        FailedLogins {
+            count,
+            last_failed_on: Some(now - Duration::seconds(seconds_ago)),
+        }
+    }
+
+    #[test]
+    fn lockout_grows_exponentially() {
+        let policy = LoginPolicy::default();
+        let now = Utc::now();
+
+        assert_eq!(policy.retry_after(&failed(4, 0, now), 5, now), None);
+        assert_eq!(
+            policy.retry_after(&failed(5, 0, now), 5, now),
+            Some(60)
+        );
+        assert_eq!(
+            policy.retry_after(&failed(6, 0, now), 5, now),
+            Some(120)
+        );
+        assert_eq!(
+            policy.retry_after(&failed(7, 10, now), 5, now),
+            Some(230)
+        );
+        assert_eq!(
+            policy.retry_after(&failed(100, 0, now), 5, now),
+            Some(3600)
+        );
+    }
+
+    #[test]
+    fn lockout_expires() {
+        let policy = LoginPolicy::default();
+        let now = Utc::now();
+
+        assert_eq!(policy.retry_after(&failed(5, 61, now), 5, now), None);
+        assert_eq!(
+            policy.retry_after(&FailedLogins::default(), 0, now),
+            None
+        );
+    }
+
+    #[test]
+    fn do_not_panic_on_huge_lockout() {
+        let policy = LoginPolicy {
+            lockout_seconds: u64::MAX,
+            max_lockout_seconds: u64::MAX,
+            ..LoginPolicy::default()
+        };
+        let now = Utc::now();
+
+        assert_eq!(policy.window_start(now), DateTime::<Utc>::MIN_UTC);
+        assert_eq!(
+            policy.retry_after(&failed(5, 0, now), 5, now),
+            Some(u64::MAX)
+        );
+    }
+}
@@ -1,4 +1,14 @@
 pub mod account;
 pub mod answer;
+pub mod api_key;
+pub mod audit;
+pub mod filter;
+pub mod hashing;
+pub mod health;
+pub mod login;
+pub mod moderation;
 pub mod pagination;
 pub mod question;
+pub mod sort;
+pub mod tag;
+pub mod vote;
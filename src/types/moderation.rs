@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::types::{answer::Answer, question::Question};
+
+/// 投稿に対する不適切な単語のチェックの状況
+///
+/// 大きい値ほどモデレーターの確認が必要な状態を表す
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationStatus {
+    /// チェック済み
+    #[default]
+    Approved,
+    /// チェックできないまま公開したか、再チェックで不適切な単語が多く見つかったため、
+    /// モデレーターの確認が必要
+    Flagged,
+    /// チェックできなかったため、バックグラウンドで再チェックを待っている
+    PendingModeration,
+}
+
+impl ModerationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModerationStatus::Approved => "approved",
+            ModerationStatus::Flagged => "flagged",
+            ModerationStatus::PendingModeration => "pending_moderation",
+        }
+    }
+}
+
+impl FromStr for ModerationStatus {
+    /// データベースに保存された値を読み込む際に使用する
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "approved" => Ok(ModerationStatus::Approved),
+            "flagged" => Ok(ModerationStatus::Flagged),
+            "pending_moderation" => {
+                Ok(ModerationStatus::PendingModeration)
+            }
+            _ => Err(format!("unknown moderation status: {}", s)),
+        }
+    }
+}
+
+/// モデレーターの確認を待っている投稿
+#[derive(Debug, Clone, Default)]
+pub struct ModerationQueue {
+    pub questions: Vec<Question>,
+    pub answers: Vec<Answer>,
+}
+
+/// チェックの状況と伏せ字に置き換える前の内容を含めた、モデレーターの確認を待っている投稿
+///
+/// `GET /moderation/queue` でモデレーターにのみ返す
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ModeratedQueue {
+    pub questions: Vec<ModeratedQuestion>,
+    pub answers: Vec<ModeratedAnswer>,
+}
+
+impl From<ModerationQueue> for ModeratedQueue {
+    fn from(queue: ModerationQueue) -> Self {
+        ModeratedQueue {
+            questions: queue
+                .questions
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            answers: queue.answers.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// チェックの状況と伏せ字に置き換える前の内容を含めた質問
+///
+/// `GET /moderation/questions/{id}` でモデレーターにのみ返す
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModeratedQuestion {
+    #[serde(flatten)]
+    pub question: Question,
+    pub moderation_status: ModerationStatus,
+    /// 伏せ字に置き換えていない場合は `None`
+    pub original_title: Option<String>,
+    pub original_content: Option<String>,
+}
+
+impl From<Question> for ModeratedQuestion {
+    fn from(question: Question) -> Self {
+        ModeratedQuestion {
+            moderation_status: question.moderation_status,
+            original_title: question.original_title.clone(),
+            original_content: question.original_content.clone(),
+            question,
+        }
+    }
+}
+
+/// チェックの状況と伏せ字に置き換える前の内容を含めた回答
+///
+/// `GET /moderation/answers/{id}` でモデレーターにのみ返す
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModeratedAnswer {
+    #[serde(flatten)]
+    pub answer: Answer,
+    pub moderation_status: ModerationStatus,
+    /// 伏せ字に置き換えていない場合は `None`
+    pub original_content: Option<String>,
+}
+
+impl From<Answer> for ModeratedAnswer {
+    fn from(answer: Answer) -> Self {
+        ModeratedAnswer {
+            moderation_status: answer.moderation_status,
+            original_content: answer.original_content.clone(),
+            answer,
+        }
+    }
+}
@@ -1,6 +1,18 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::NaiveDateTime;
 use handle_errors::Error;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// カーソルモードで `limit` が指定されなかった場合の件数
+pub const DEFAULT_CURSOR_LIMIT: u32 = 20;
+
+/// カーソルモードで1ページに返す最大件数
+pub const MAX_CURSOR_LIMIT: u32 = 100;
+
+/// 作成日時の書式 (マイクロ秒まで保持する)
+const CURSOR_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.6f";
+
 /// Paginationの構造体であり、クエリパラメータから値を抽出する
 #[derive(Default, Debug, PartialEq)]
 pub struct Pagination {
@@ -16,13 +28,14 @@ pub struct Pagination {
 /// # サンプル
 /// ```rust
 /// use std::collections::HashMap;
+/// use rust_web_dev::types::pagination;
 ///
 /// let mut query = HashMap::new();
 /// query.insert("limit".to_string(), "1".to_string());
 /// query.insert("offset".to_string(), "10".to_string());
 /// let p = pagination::extract_pagination(query).unwrap();
-/// assert(p.limit, Some(1));
-/// assert(p.offset, 10);
+/// assert_eq!(p.limit, Some(1));
+/// assert_eq!(p.offset, 10);
 /// ```
 pub fn extract_pagination(
     params: HashMap<String, String>,
@@ -47,11 +60,244 @@ pub fn extract_pagination(
     Err(Error::MissingParameters)
 }
 
+/// オフセットモードで件数を含めて返す場合のレスポンス
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub limit: Option<u32>,
+    pub offset: u32,
+}
+
+/// レスポンスをエンベロープで包むかどうかを判定する
+///
+/// 既存のクライアントが配列のまま受け取れるように、
+/// `envelope=true` もしくは `X-Pagination-Envelope: true` を指定した場合のみ有効にする
+pub fn wants_envelope(
+    params: &HashMap<String, String>,
+    header: Option<&str>,
+) -> bool {
+    params.get("envelope").map(String::as_str) == Some("true")
+        || header == Some("true")
+}
+
+/// RFC 8288 の `Link` ヘッダーの値を組み立てる
+///
+/// `query` の `limit` と `offset` を置き換え、それ以外のパラメータは保持する。
+/// `limit` が指定されていない場合は1ページしかないため `None` を返す
+pub fn link_header(
+    path: &str,
+    query: &[(String, String)],
+    pagination: &Pagination,
+    total: i64,
+) -> Option<String> {
+    let limit = i64::from(pagination.limit.filter(|limit| *limit > 0)?);
+    let offset = i64::from(pagination.offset);
+
+    let url = |offset: i64| {
+        let mut params: Vec<(String, String)> = query
+            .iter()
+            .filter(|(key, _)| key != "limit" && key != "offset")
+            .cloned()
+            .collect();
+        params.push(("limit".to_string(), limit.to_string()));
+        params.push(("offset".to_string(), offset.to_string()));
+        format!(
+            "{}?{}",
+            path,
+            serde_urlencoded::to_string(params).unwrap_or_default()
+        )
+    };
+
+    let last = if total > 0 {
+        (total - 1) / limit * limit
+    } else {
+        0
+    };
+
+    let mut links = vec![format!("<{}>; rel=\"first\"", url(0))];
+    if offset > 0 {
+        let prev = (offset - limit).max(0);
+        links.push(format!("<{}>; rel=\"prev\"", url(prev)));
+    }
+    if offset + limit < total {
+        links.push(format!("<{}>; rel=\"next\"", url(offset + limit)));
+    }
+    links.push(format!("<{}>; rel=\"last\"", url(last)));
+
+    Some(links.join(", "))
+}
+
+/// キーセットページネーションの位置を表すカーソル
+///
+/// `(created_on, id)` の順で並べたときに、最後に返した項目を指す
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_on: NaiveDateTime,
+    pub id: i32,
+}
+
+impl Cursor {
+    /// クライアントからは中身を意識させないように Base64 でエンコードする
+    pub fn encode(&self) -> String {
+        let raw = format!(
+            "{}|{}",
+            self.created_on.format(CURSOR_TIME_FORMAT),
+            self.id
+        );
+        URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, Error> {
+        let raw = URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or(Error::InvalidCursor)?;
+
+        let (created_on, id) =
+            raw.split_once('|').ok_or(Error::InvalidCursor)?;
+
+        Ok(Cursor {
+            created_on: NaiveDateTime::parse_from_str(
+                created_on,
+                CURSOR_TIME_FORMAT,
+            )
+            .map_err(|_| Error::InvalidCursor)?,
+            id: id.parse::<i32>().map_err(|_| Error::InvalidCursor)?,
+        })
+    }
+}
+
+/// カーソルモードのページネーション
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorPagination {
+    /// `None` の場合は先頭のページ
+    pub cursor: Option<Cursor>,
+    pub limit: u32,
+}
+
+/// カーソルモードのレスポンス
+///
+/// `next_cursor` が `None` の場合は最後のページ
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+/// "/questions" パスのクエリパラメータからカーソルを抽出する
+/// # サンプルクエリ
+/// `/questions?cursor=MjAyMy0wMy0wMVQwMDowMDowMC4wMDAwMDB8MQ&limit=10`
+///
+/// `cursor` が指定されていない場合は従来のオフセットモードとして `None` を返す。
+/// 先頭のページを取得する場合は `cursor=` のように空の値を指定する。
+/// `limit` は `MAX_CURSOR_LIMIT` 件までに切り詰める
+pub fn extract_cursor_pagination(
+    params: &HashMap<String, String>,
+) -> Result<Option<CursorPagination>, Error> {
+    let cursor = match params.get("cursor") {
+        Some(cursor) => cursor,
+        None => return Ok(None),
+    };
+
+    let cursor = if cursor.is_empty() {
+        None
+    } else {
+        Some(Cursor::decode(cursor)?)
+    };
+
+    let limit = match params.get("limit") {
+        Some(limit) => limit
+            .parse::<u32>()
+            .map_err(Error::ParseError)?
+            .min(MAX_CURSOR_LIMIT),
+        None => DEFAULT_CURSOR_LIMIT,
+    };
+
+    Ok(Some(CursorPagination { cursor, limit }))
+}
+
+/// 全文検索のクエリ `q` を抽出する
+///
+/// 空白のみの場合は検索しないものとして扱う
+pub fn extract_search_query(
+    params: &HashMap<String, String>,
+) -> Option<String> {
+    params
+        .get("q")
+        .map(|q| q.trim().to_string())
+        .filter(|q| !q.is_empty())
+}
+
+/// 全文検索と組み合わせられないパラメータ
+///
+/// 検索結果は関連度順に返すため、絞り込み・並べ替え・カーソル・エンベロープは適用できない
+const SEARCH_EXCLUSIVE_PARAMS: [&str; 8] = [
+    "tag",
+    "tag_mode",
+    "unanswered",
+    "accepted",
+    "sort",
+    "order",
+    "cursor",
+    "envelope",
+];
+
+/// 全文検索のクエリと組み合わせられないパラメータが指定されていないか確かめる
+///
+/// 指定されていた場合は無視せずにエラーを返す
+pub fn check_search_params(
+    params: &HashMap<String, String>,
+    envelope_header: Option<&str>,
+) -> Result<(), Error> {
+    if let Some(name) = SEARCH_EXCLUSIVE_PARAMS
+        .iter()
+        .find(|name| params.contains_key(**name))
+    {
+        return Err(Error::UnsupportedSearchParameter(name.to_string()));
+    }
+    if envelope_header == Some("true") {
+        return Err(Error::UnsupportedSearchParameter(
+            "X-Pagination-Envelope".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// カーソルと組み合わせられないパラメータ
+///
+/// カーソルは `(created_on, id)` をキーにしているため、並べ替えは適用できない
+const CURSOR_EXCLUSIVE_PARAMS: [&str; 2] = ["sort", "order"];
+
+/// カーソルと組み合わせられないパラメータが指定されていないか確かめる
+pub fn check_cursor_params(
+    params: &HashMap<String, String>,
+) -> Result<(), Error> {
+    match CURSOR_EXCLUSIVE_PARAMS
+        .iter()
+        .find(|name| params.contains_key(**name))
+    {
+        Some(name) => {
+            Err(Error::UnsupportedCursorParameter(name.to_string()))
+        }
+        None => Ok(()),
+    }
+}
+
 #[cfg(test)]
 mod pagination_tests {
     use std::collections::HashMap;
 
-    use super::{extract_pagination, Pagination};
+    use chrono::NaiveDate;
+    use warp::Reply;
+
+    use super::{
+        extract_cursor_pagination, extract_pagination, link_header,
+        Cursor, CursorPagination, Pagination, DEFAULT_CURSOR_LIMIT,
+        MAX_CURSOR_LIMIT,
+    };
 
     #[test]
     fn valid_pagination() {
@@ -137,4 +383,99 @@ mod pagination_tests {
 
         assert_eq!(pagination_result, expected);
     }
+
+    #[test]
+    fn cursor_roundtrip() {
+        let cursor = Cursor {
+            created_on: NaiveDate::from_ymd_opt(2023, 3, 1)
+                .unwrap()
+                .and_hms_micro_opt(12, 30, 0, 123456)
+                .unwrap(),
+            id: 42,
+        };
+
+        assert_eq!(Cursor::decode(&cursor.encode()).unwrap(), cursor);
+    }
+
+    #[test]
+    fn invalid_cursor() {
+        let pagination_result =
+            format!("{}", Cursor::decode("NOT_A_CURSOR").unwrap_err());
+
+        let expected = format!("{}", handle_errors::Error::InvalidCursor);
+
+        assert_eq!(pagination_result, expected);
+    }
+
+    #[tokio::test]
+    async fn reply_bad_request_for_invalid_cursor() {
+        let error = Cursor::decode("NOT_A_CURSOR").unwrap_err();
+
+        let res = handle_errors::return_error(warp::reject::custom(error))
+            .await
+            .unwrap()
+            .into_response();
+
+        assert_eq!(res.status(), 400);
+    }
+
+    #[test]
+    fn cursor_pagination() {
+        let mut params = HashMap::new();
+        assert_eq!(extract_cursor_pagination(&params).unwrap(), None);
+
+        params.insert(String::from("cursor"), String::new());
+        let expected = CursorPagination {
+            cursor: None,
+            limit: DEFAULT_CURSOR_LIMIT,
+        };
+        assert_eq!(
+            extract_cursor_pagination(&params).unwrap(),
+            Some(expected)
+        );
+    }
+
+    #[test]
+    fn clamp_cursor_limit() {
+        let mut params = HashMap::new();
+        params.insert(String::from("cursor"), String::new());
+        params.insert(String::from("limit"), String::from("4294967295"));
+
+        let pagination =
+            extract_cursor_pagination(&params).unwrap().unwrap();
+
+        assert_eq!(pagination.limit, MAX_CURSOR_LIMIT);
+    }
+
+    #[test]
+    fn link_header_in_middle_page() {
+        let query = vec![
+            (String::from("tag"), String::from("rust")),
+            (String::from("limit"), String::from("10")),
+            (String::from("offset"), String::from("10")),
+        ];
+        let pagination = Pagination {
+            limit: Some(10),
+            offset: 10,
+        };
+
+        let link = link_header("/questions", &query, &pagination, 35);
+
+        let expected = [
+            r#"</questions?tag=rust&limit=10&offset=0>; rel="first""#,
+            r#"</questions?tag=rust&limit=10&offset=0>; rel="prev""#,
+            r#"</questions?tag=rust&limit=10&offset=20>; rel="next""#,
+            r#"</questions?tag=rust&limit=10&offset=30>; rel="last""#,
+        ]
+        .join(", ");
+        assert_eq!(link, Some(expected));
+    }
+
+    #[test]
+    fn link_header_without_limit() {
+        let link =
+            link_header("/questions", &[], &Pagination::default(), 5);
+
+        assert_eq!(link, None);
+    }
 }
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::types::{answer::Answer, moderation::ModerationStatus};
+
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct QuestionId(pub i32);
 
@@ -9,6 +11,19 @@ pub struct Question {
     pub title: String,
     pub content: String,
     pub tags: Option<Vec<String>>,
+    /// 投票の合計値 (リクエストで指定された値は使用しない)
+    #[serde(default)]
+    pub score: i64,
+    /// 不適切な単語のチェックの状況
+    /// (リクエストで指定された値は使用せず、モデレーター向けのためレスポンスには含めない)
+    #[serde(default, skip_serializing)]
+    pub moderation_status: ModerationStatus,
+    /// 伏せ字に置き換える前のタイトル (モデレーター向けで、レスポンスには含めない)
+    #[serde(skip)]
+    pub original_title: Option<String>,
+    /// 伏せ字に置き換える前の本文 (モデレーター向けで、レスポンスには含めない)
+    #[serde(skip)]
+    pub original_content: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -16,6 +31,36 @@ pub struct NewQuestion {
     pub title: String,
     pub content: String,
     pub tags: Option<Vec<String>>,
+    /// 不適切な単語のチェックの状況 (リクエストで指定された値は使用しない)
+    #[serde(default)]
+    pub moderation_status: ModerationStatus,
+    /// 伏せ字に置き換える前のタイトル (モデレーター向けで、レスポンスには含めない)
+    #[serde(skip)]
+    pub original_title: Option<String>,
+    /// 伏せ字に置き換える前の本文 (モデレーター向けで、レスポンスには含めない)
+    #[serde(skip)]
+    pub original_content: Option<String>,
+}
+
+/// 回答を埋め込んだ質問
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct QuestionWithAnswers {
+    #[serde(flatten)]
+    pub question: Question,
+    pub answers: Vec<Answer>,
+}
+
+/// 全文検索でヒットした質問
+///
+/// `*_highlight` は一致した語を `<b>` タグで囲んだ抜粋。
+/// 投稿に含まれるHTMLはエスケープしてある
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct QuestionSearchResult {
+    #[serde(flatten)]
+    pub question: Question,
+    pub rank: f32,
+    pub title_highlight: String,
+    pub content_highlight: String,
 }
 
 impl std::fmt::Display for QuestionId {
@@ -0,0 +1,113 @@
+use handle_errors::Error;
+use std::collections::HashMap;
+
+/// 質問の一覧を並び替えるキー
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortKey {
+    /// 作成日時
+    #[default]
+    CreatedOn,
+    /// 更新日時
+    UpdatedOn,
+    /// 回答数
+    Answers,
+    /// 投票のスコア
+    Votes,
+}
+
+/// 並び替えの順序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// 並び替えの条件
+///
+/// 同じ値の質問が複数ある場合でも順序が変わらないように、
+/// ストア側では必ず `id` を第2キーとして並べる
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Sort {
+    pub key: SortKey,
+    pub order: SortOrder,
+}
+
+/// "/questions" パスのクエリパラメータから並び替えの条件を抽出する
+/// # サンプルクエリ
+/// `/questions?sort=answers&order=desc`
+///
+/// 指定がない場合は作成日時の昇順になる
+pub fn extract_sort(
+    params: &HashMap<String, String>,
+) -> Result<Sort, Error> {
+    let key = match params.get("sort").map(String::as_str) {
+        None | Some("created_on") => SortKey::CreatedOn,
+        Some("updated_on") => SortKey::UpdatedOn,
+        Some("answers") => SortKey::Answers,
+        Some("votes") => SortKey::Votes,
+        Some(key) => return Err(Error::InvalidSortKey(key.to_string())),
+    };
+
+    let order = match params.get("order").map(String::as_str) {
+        None | Some("asc") => SortOrder::Asc,
+        Some("desc") => SortOrder::Desc,
+        Some(order) => {
+            return Err(Error::InvalidSortOrder(order.to_string()))
+        }
+    };
+
+    Ok(Sort { key, order })
+}
+
+#[cfg(test)]
+mod sort_tests {
+    use std::collections::HashMap;
+
+    use super::{extract_sort, Sort, SortKey, SortOrder};
+
+    #[test]
+    fn default_sort() {
+        let sort = extract_sort(&HashMap::new()).unwrap();
+
+        assert_eq!(sort, Sort::default());
+    }
+
+    #[test]
+    fn valid_sort() {
+        let mut params = HashMap::new();
+        params.insert(String::from("sort"), String::from("answers"));
+        params.insert(String::from("order"), String::from("desc"));
+
+        let sort = extract_sort(&params).unwrap();
+
+        let expected = Sort {
+            key: SortKey::Answers,
+            order: SortOrder::Desc,
+        };
+        assert_eq!(sort, expected);
+    }
+
+    #[test]
+    fn invalid_sort_key() {
+        let mut params = HashMap::new();
+        params.insert(String::from("sort"), String::from("title"));
+
+        let sort_result =
+            format!("{}", extract_sort(&params).unwrap_err());
+
+        let expected = format!(
+            "{}",
+            handle_errors::Error::InvalidSortKey(String::from("title"))
+        );
+        assert_eq!(sort_result, expected);
+    }
+
+    #[test]
+    fn invalid_sort_order() {
+        let mut params = HashMap::new();
+        params.insert(String::from("order"), String::from("up"));
+
+        assert!(extract_sort(&params).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+use handle_errors::Error;
+use serde::{Deserialize, Serialize};
+
+/// 1つの質問に付与できるタグの最大数
+pub const MAX_TAGS: usize = 5;
+
+/// タグと、そのタグが付与された質問の数
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TagCount {
+    pub name: String,
+    pub count: i64,
+}
+
+/// 複数のタグを指定した場合の絞り込み方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagMode {
+    /// いずれかのタグを持つ質問
+    Any,
+    /// 全てのタグを持つ質問
+    All,
+}
+
+/// タグによる質問の絞り込み条件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagFilter {
+    pub tags: Vec<String>,
+    pub mode: TagMode,
+}
+
+/// タグの表記揺れをなくすために正規化する
+///
+/// 前後の空白を除去して小文字にし、連続する空白は1つにまとめる。
+/// 空のタグと重複は取り除き、`MAX_TAGS` を超える場合はエラーにする
+pub fn normalize_tags(
+    tags: Option<Vec<String>>,
+) -> Result<Option<Vec<String>>, Error> {
+    let tags = match tags {
+        Some(tags) => tags,
+        None => return Ok(None),
+    };
+
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = tag
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+
+    if normalized.len() > MAX_TAGS {
+        return Err(Error::TooManyTags(MAX_TAGS));
+    }
+
+    Ok(Some(normalized))
+}
+
+/// "/questions" パスのクエリパラメータからタグの絞り込み条件を抽出する
+/// # サンプルクエリ
+/// `/questions?tag=rust&tag=warp&tag_mode=all`
+///
+/// `tag` は複数指定でき、`tag_mode` を省略した場合は `any` として扱う
+pub fn extract_tag_filter(
+    params: &[(String, String)],
+) -> Result<Option<TagFilter>, Error> {
+    let tags: Vec<String> = params
+        .iter()
+        .filter(|(key, _)| key == "tag")
+        .map(|(_, value)| value.clone())
+        .collect();
+
+    let mode = match params.iter().rev().find(|(key, _)| key == "tag_mode")
+    {
+        None => TagMode::Any,
+        Some((_, mode)) => match mode.as_str() {
+            "any" => TagMode::Any,
+            "all" => TagMode::All,
+            _ => return Err(Error::InvalidTagMode(mode.clone())),
+        },
+    };
+
+    match normalize_tags(Some(tags))? {
+        Some(tags) if !tags.is_empty() => {
+            Ok(Some(TagFilter { tags, mode }))
+        }
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tag_tests {
+    use warp::Reply;
+
+    use super::{
+        extract_tag_filter, normalize_tags, TagFilter, TagMode, MAX_TAGS,
+    };
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn normalize_case_and_whitespace() {
+        let tags = vec![
+            " Rust ".to_string(),
+            "rust".to_string(),
+            "Web   Development".to_string(),
+            "   ".to_string(),
+        ];
+
+        let normalized = normalize_tags(Some(tags)).unwrap();
+
+        assert_eq!(
+            normalized,
+            Some(vec!["rust".to_string(), "web development".to_string()])
+        );
+    }
+
+    #[test]
+    fn too_many_tags() {
+        let tags = (0..=MAX_TAGS).map(|i| format!("tag{}", i)).collect();
+
+        let error = format!("{}", normalize_tags(Some(tags)).unwrap_err());
+
+        let expected =
+            format!("{}", handle_errors::Error::TooManyTags(MAX_TAGS));
+        assert_eq!(error, expected);
+    }
+
+    #[test]
+    fn tag_filter_from_query() {
+        let filter = extract_tag_filter(&params(&[
+            ("tag", "Rust"),
+            ("tag", "warp"),
+            ("tag_mode", "all"),
+        ]))
+        .unwrap();
+
+        let expected = TagFilter {
+            tags: vec!["rust".to_string(), "warp".to_string()],
+            mode: TagMode::All,
+        };
+        assert_eq!(filter, Some(expected));
+    }
+
+    #[test]
+    fn no_tag_filter() {
+        let filter =
+            extract_tag_filter(&params(&[("limit", "10")])).unwrap();
+
+        assert_eq!(filter, None);
+    }
+
+    #[test]
+    fn invalid_tag_mode() {
+        let result = extract_tag_filter(&params(&[
+            ("tag", "rust"),
+            ("tag_mode", "some"),
+        ]));
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn reply_bad_request_for_invalid_tags() {
+        let tags = (0..=MAX_TAGS).map(|i| format!("tag{}", i)).collect();
+        let too_many = normalize_tags(Some(tags)).unwrap_err();
+        let invalid_mode = extract_tag_filter(&params(&[
+            ("tag", "rust"),
+            ("tag_mode", "some"),
+        ]))
+        .unwrap_err();
+
+        for error in [too_many, invalid_mode] {
+            let res =
+                handle_errors::return_error(warp::reject::custom(error))
+                    .await
+                    .unwrap()
+                    .into_response();
+            assert_eq!(res.status(), 400);
+        }
+    }
+}
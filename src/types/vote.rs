@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+/// 投票の方向
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VoteDirection {
+    Up,
+    Down,
+}
+
+impl VoteDirection {
+    /// スコアに加算する値
+    pub fn value(&self) -> i16 {
+        match self {
+            VoteDirection::Up => 1,
+            VoteDirection::Down => -1,
+        }
+    }
+}
+
+/// 質問・回答への投票
+///
+/// 1つのアカウントにつき1票で、再度投票すると方向が変更される
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct NewVote {
+    pub direction: VoteDirection,
+}
+
+/// 投票後のスコア
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct VoteScore {
+    pub score: i64,
+}